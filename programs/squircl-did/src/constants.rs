@@ -1,26 +1,63 @@
+use anchor_lang::prelude::Pubkey;
+
 pub const DISCRIMINATOR_LENGTH: usize = 8;
 pub const U8_LENGTH: usize = 1;
 pub const U64_LENGTH: usize = 8;
 pub const I64_LENGTH: usize = 8;
 pub const BOOL_LENGTH: usize = 1;
 pub const PUBLIC_KEY_LENGTH: usize = 32;
+pub const HASH_LENGTH: usize = 32;
 pub const STRING_LENGTH_PREFIX: usize = 4;
 pub const STRING_CHAR_MULTIPLIER: usize = 4;
+pub const VEC_LENGTH_PREFIX: usize = 4;
+
+pub const DID_PREFIX: &str = "did:squircl:";
+pub const DID_ID_MAX_CHARS: usize = 48;
+pub const DID_MAX_CHARS: usize = DID_PREFIX.len() + DID_ID_MAX_CHARS;
+pub const DERIVED_DID_HASH_BYTES: usize = 20;
+
+pub const DID_LENGTH: usize = STRING_LENGTH_PREFIX + (DID_MAX_CHARS * STRING_CHAR_MULTIPLIER); // 60 chars
 
-pub const DID_LENGTH: usize = STRING_LENGTH_PREFIX + (48 * STRING_CHAR_MULTIPLIER); // 48 chars
+pub const ALIAS_MIN_CHARS: usize = 3;
+pub const ALIAS_MAX_CHARS: usize = 32; // the handle is a PDA seed on its own
 
-pub const ETH_ADDRESS_CHARS: usize = 42;
-pub const SOL_ADDRESS_CHARS: usize = 44;
-pub const ETH_SIGNATURE_CHARS: usize = 132;
-pub const SOL_SIGNATURE_CHARS: usize = 88;
+pub const SERVICE_ID_MAX_CHARS: usize = 64;
+pub const SERVICE_TYPE_MAX_CHARS: usize = 64;
+pub const SERVICE_ENDPOINT_MAX_CHARS: usize = 256;
 
-pub const ETH_ADDRESS_LENGTH: usize =
-    STRING_LENGTH_PREFIX + (ETH_ADDRESS_CHARS * STRING_CHAR_MULTIPLIER); // 42 chars
-pub const SOL_ADDRESS_LENGTH: usize =
-    STRING_LENGTH_PREFIX + (SOL_ADDRESS_CHARS * STRING_CHAR_MULTIPLIER); // 44 chars
-pub const ETH_SIGNATURE_LENGTH: usize =
-    STRING_LENGTH_PREFIX + (ETH_SIGNATURE_CHARS * STRING_CHAR_MULTIPLIER); // 132 chars
-pub const SOL_SIGNATURE_LENGTH: usize =
-    STRING_LENGTH_PREFIX + (SOL_SIGNATURE_CHARS * STRING_CHAR_MULTIPLIER); // 88 chars
+// addresses are stored as raw keys rather than their string encodings
+pub const ETH_ADDRESS_BYTES: usize = 20;
+pub const SOL_ADDRESS_BYTES: usize = PUBLIC_KEY_LENGTH;
+pub const BTC_ADDRESS_BYTES: usize = 21; // address type || hash160 of the public key
+pub const PASSKEY_ADDRESS_BYTES: usize = 33; // compressed secp256r1 public key
 
 pub const ETHEREUM_MSG_PREFIX: &str = "\x19Ethereum Signed Message:\n";
+pub const BITCOIN_MSG_PREFIX: &str = "\x18Bitcoin Signed Message:\n";
+
+pub const OFFCHAIN_MESSAGE_SIGNING_DOMAIN: &[u8] = b"\xffsolana offchain";
+pub const OFFCHAIN_MESSAGE_VERSION: u8 = 0;
+pub const OFFCHAIN_MESSAGE_MAX_LEDGER_LENGTH: usize = 1212; // longest message Ledger signs
+
+pub const MESSAGE_HEADER: &str = "Squircl DID operation";
+pub const MESSAGE_VERSION: u8 = 1;
+
+pub const EIP712_DOMAIN_NAME: &str = "Squircl DID";
+pub const EIP712_DOMAIN_VERSION: &str = "1";
+
+#[cfg(feature = "devnet")]
+pub const CLUSTER: &str = "devnet";
+#[cfg(not(feature = "devnet"))]
+pub const CLUSTER: &str = "mainnet-beta";
+
+// Secp256r1SigVerify1111111111111111111111111, the secp256r1 precompile (SIMD-0075) is not
+// exposed by this version of solana-program
+pub const SECP256R1_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    6, 146, 13, 236, 47, 234, 113, 181, 183, 35, 129, 77, 116, 45, 169, 3, 28, 131, 231, 95, 219,
+    121, 93, 86, 142, 117, 71, 128, 32, 0, 0, 0,
+]);
+pub const WEBAUTHN_GET_TYPE: &str = "webauthn.get";
+pub const WEBAUTHN_AUTH_DATA_MIN_LENGTH: usize = 37; // rpIdHash + flags + signCount
+pub const WEBAUTHN_FLAG_USER_PRESENT: u8 = 0x01;
+
+pub const NONCE_VALIDITY_WINDOW: i64 = 3600; // 1 hour
+pub const MAX_NONCE_FUTURE_DRIFT: i64 = 300; // 5 minutes
@@ -0,0 +1,217 @@
+// EIP-712 typed data for the operations EVM wallets can sign as structured prompts
+// https://eips.ethereum.org/EIPS/eip-712
+
+use anchor_lang::solana_program::keccak;
+
+use crate::{
+    constants::{CLUSTER, EIP712_DOMAIN_NAME, EIP712_DOMAIN_VERSION},
+    state::{AddressValidity, Chain, CredentialParams, Role},
+    utils::get_operation_digest_message,
+};
+
+pub const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,bytes32 salt)";
+pub const CREATE_DID_TYPE: &str = "CreateDid(string did,string controller,int64 nonce)";
+pub const ADD_ADDRESS_TYPE: &str =
+    "AddAddress(string did,string chain,string address,string role,int64 nonce)";
+pub const ADD_TEMPORARY_ADDRESS_TYPE: &str = "AddTemporaryAddress(string did,string chain,string address,string role,int64 validFrom,int64 validUntil,int64 nonce)";
+pub const REMOVE_ADDRESS_TYPE: &str =
+    "RemoveAddress(string did,string chain,string address,int64 nonce)";
+pub const ISSUE_CREDENTIAL_TYPE: &str = "IssueCredential(string credentialId,string issuerDid,string subjectDid,string uri,string hash,bool isMutable,bool isRevokable,int64 expiresAt,int64 nonce)";
+pub const UPDATE_CREDENTIAL_TYPE: &str = "UpdateCredential(string credentialId,string issuerDid,string subjectDid,string uri,string hash,bool isMutable,bool isRevokable,int64 expiresAt,int64 nonce)";
+pub const REVOKE_CREDENTIAL_TYPE: &str =
+    "RevokeCredential(string credentialId,string issuerDid,string subjectDid,int64 nonce)";
+
+/// Message a `Sig` signs for an operation: the default text, and the EIP-712 struct hash for
+/// the operations that can also be signed as typed data
+pub struct OperationMessage {
+    pub text: String,
+    pub struct_hash: Option<[u8; 32]>,
+}
+
+impl OperationMessage {
+    pub fn typed(text: String, struct_hash: [u8; 32]) -> Self {
+        Self {
+            text,
+            struct_hash: Some(struct_hash),
+        }
+    }
+
+    pub fn digest(&self) -> String {
+        get_operation_digest_message(&self.text)
+    }
+}
+
+impl From<String> for OperationMessage {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            struct_hash: None,
+        }
+    }
+}
+
+fn hash_string(value: &str) -> [u8; 32] {
+    keccak::hash(value.as_bytes()).to_bytes()
+}
+
+// int64 words are sign extended to 32 bytes
+fn encode_i64(value: i64) -> [u8; 32] {
+    let mut word = [if value < 0 { 0xff } else { 0 }; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn encode_bool(value: bool) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[31] = value as u8;
+    word
+}
+
+fn hash_struct(type_: &str, words: &[[u8; 32]]) -> [u8; 32] {
+    let mut encoded = hash_string(type_).to_vec();
+    for word in words {
+        encoded.extend_from_slice(word);
+    }
+
+    keccak::hash(&encoded).to_bytes()
+}
+
+/// The salt binds signatures to this program and cluster, so they can't be replayed against
+/// another deployment or be confused with another app's typed data
+pub fn get_domain_separator() -> [u8; 32] {
+    let salt = keccak::hashv(&[crate::ID.as_ref(), CLUSTER.as_bytes()]).to_bytes();
+
+    hash_struct(
+        EIP712_DOMAIN_TYPE,
+        &[
+            hash_string(EIP712_DOMAIN_NAME),
+            hash_string(EIP712_DOMAIN_VERSION),
+            salt,
+        ],
+    )
+}
+
+/// `0x19 0x01 || domainSeparator || hashStruct(message)`, the secp256k1 precompile hashes it
+pub fn get_typed_data_message(struct_hash: &[u8; 32]) -> Vec<u8> {
+    [&[0x19, 0x01], &get_domain_separator()[..], &struct_hash[..]].concat()
+}
+
+pub fn hash_create_did(did: &str, controller: &str, nonce: i64) -> [u8; 32] {
+    hash_struct(
+        CREATE_DID_TYPE,
+        &[hash_string(did), hash_string(controller), encode_i64(nonce)],
+    )
+}
+
+// an address with a validity window is added with its own type, an unbounded start or end is
+// encoded as the earliest or latest int64
+pub fn hash_add_address(
+    did: &str,
+    chain: &Chain,
+    address: &str,
+    role: &Role,
+    validity: AddressValidity,
+    nonce: i64,
+) -> [u8; 32] {
+    if validity.is_unbounded() {
+        return hash_struct(
+            ADD_ADDRESS_TYPE,
+            &[
+                hash_string(did),
+                hash_string(&chain.to_string()),
+                hash_string(address),
+                hash_string(&role.to_string()),
+                encode_i64(nonce),
+            ],
+        );
+    }
+
+    hash_struct(
+        ADD_TEMPORARY_ADDRESS_TYPE,
+        &[
+            hash_string(did),
+            hash_string(&chain.to_string()),
+            hash_string(address),
+            hash_string(&role.to_string()),
+            encode_i64(validity.valid_from.unwrap_or(i64::MIN)),
+            encode_i64(validity.valid_until.unwrap_or(i64::MAX)),
+            encode_i64(nonce),
+        ],
+    )
+}
+
+pub fn hash_remove_address(did: &str, chain: &Chain, address: &str, nonce: i64) -> [u8; 32] {
+    hash_struct(
+        REMOVE_ADDRESS_TYPE,
+        &[
+            hash_string(did),
+            hash_string(&chain.to_string()),
+            hash_string(address),
+            encode_i64(nonce),
+        ],
+    )
+}
+
+// a credential that never expires is encoded as the latest int64
+fn credential_words(
+    credential_id: &str,
+    issuer_did: &str,
+    subject_did: &str,
+    params: &CredentialParams,
+    nonce: i64,
+) -> [[u8; 32]; 9] {
+    [
+        hash_string(credential_id),
+        hash_string(issuer_did),
+        hash_string(subject_did),
+        hash_string(&params.uri),
+        hash_string(&params.credential_hash),
+        encode_bool(params.is_mutable),
+        encode_bool(params.is_revokable),
+        encode_i64(params.expires_at.unwrap_or(i64::MAX)),
+        encode_i64(nonce),
+    ]
+}
+
+pub fn hash_issue_credential(
+    credential_id: &str,
+    issuer_did: &str,
+    subject_did: &str,
+    params: &CredentialParams,
+    nonce: i64,
+) -> [u8; 32] {
+    hash_struct(
+        ISSUE_CREDENTIAL_TYPE,
+        &credential_words(credential_id, issuer_did, subject_did, params, nonce),
+    )
+}
+
+pub fn hash_update_credential(
+    credential_id: &str,
+    issuer_did: &str,
+    subject_did: &str,
+    params: &CredentialParams,
+    nonce: i64,
+) -> [u8; 32] {
+    hash_struct(
+        UPDATE_CREDENTIAL_TYPE,
+        &credential_words(credential_id, issuer_did, subject_did, params, nonce),
+    )
+}
+
+pub fn hash_revoke_credential(
+    credential_id: &str,
+    issuer_did: &str,
+    subject_did: &str,
+    nonce: i64,
+) -> [u8; 32] {
+    hash_struct(
+        REVOKE_CREDENTIAL_TYPE,
+        &[
+            hash_string(credential_id),
+            hash_string(issuer_did),
+            hash_string(subject_did),
+            encode_i64(nonce),
+        ],
+    )
+}
@@ -24,4 +24,76 @@ pub enum SquirclErrorCode {
     AddressDoesNotExistInDID,
     #[msg("Nonce expired")]
     NonceExpired,
+    #[msg("Nonce has already been used")]
+    NonceAlreadyUsed,
+    #[msg("Nonce is too far in the future")]
+    NonceTooFarInFuture,
+    #[msg("Controller role cannot be assigned")]
+    CannotAssignControllerRole,
+    #[msg("Cannot change the role of the controller address")]
+    CannotChangeControllerRole,
+    #[msg("Address is not the controller of the DID")]
+    AddressIsNotController,
+    #[msg("DID is deactivated")]
+    DidDeactivated,
+    #[msg("Invalid DID, expected did:squircl:<id>")]
+    InvalidDid,
+    #[msg("DID is not in canonical (lowercase) form")]
+    DidNotCanonical,
+    #[msg("Derived DID does not match the controller address")]
+    DerivedDidMismatch,
+    #[msg("Not enough distinct signatures to meet the DID threshold")]
+    ThresholdNotMet,
+    #[msg("The same address signed more than once")]
+    DuplicateSigner,
+    #[msg("Threshold must be between 1 and the number of controller and admin addresses")]
+    InvalidThreshold,
+    #[msg("Invalid recovery settings")]
+    InvalidRecoveryConfig,
+    #[msg("Address is not a guardian of the DID")]
+    NotAGuardian,
+    #[msg("Not enough guardians approved the recovery")]
+    RecoveryQuorumNotMet,
+    #[msg("A recovery is already pending")]
+    RecoveryAlreadyPending,
+    #[msg("No recovery is pending")]
+    NoPendingRecovery,
+    #[msg("The recovery delay has not passed yet")]
+    RecoveryTimelockActive,
+    #[msg("Invalid service, check the id, type and endpoint")]
+    InvalidService,
+    #[msg("Service already exists")]
+    ServiceAlreadyExists,
+    #[msg("Service does not exist in DID")]
+    ServiceDoesNotExist,
+    #[msg("DID has reached the maximum number of services")]
+    TooManyServices,
+    #[msg("Invalid WebAuthn authenticator data or client data")]
+    InvalidWebAuthnData,
+    #[msg("This operation can't be signed as EIP-712 typed data")]
+    TypedDataNotSupported,
+    #[msg("The account is not in the legacy layout, it may already be migrated")]
+    NotALegacyAccount,
+    #[msg("DID has reached the maximum number of addresses")]
+    TooManyAddresses,
+    #[msg("The address already belongs to another DID")]
+    AddressInAnotherDid,
+    #[msg("Invalid alias, it must be 3 to 32 characters of [a-z0-9_-]")]
+    InvalidAlias,
+    #[msg("The alias is already claimed")]
+    AliasAlreadyClaimed,
+    #[msg("The alias does not belong to this DID")]
+    AliasNotOwned,
+    #[msg("The alias expiry must be in the future")]
+    InvalidAliasExpiry,
+    #[msg("The address has expired or isn't valid yet")]
+    AddressNotValid,
+    #[msg("The validity of an address must end in the future, after it starts")]
+    InvalidAddressValidity,
+    #[msg("The DID has no expired addresses")]
+    NoExpiredAddresses,
+    #[msg("The lookup of a pruned address is missing")]
+    MissingAddressLookup,
+    #[msg("Zero-copy DIDs are only enabled in benchmark builds")]
+    ZeroCopyDisabled,
 }
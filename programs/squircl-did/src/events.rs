@@ -0,0 +1,204 @@
+// Events of every DID and credential mutation, emitted through a self-CPI so that they are
+// recorded in the instruction data of the transaction and can't be lost to log truncation.
+// `signer` and `signer_chain` are the address that authorised the operation
+
+use anchor_lang::prelude::*;
+
+use crate::state::{Chain, Role};
+
+// the address is the controller, which signed the creation
+#[event]
+pub struct DidCreated {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+}
+
+// the state of every version of a did, emitted along with the event of the mutation that made it.
+// `state` is the borsh encoding of `Did`, whose hash the next version commits to
+#[event]
+pub struct DidUpdated {
+    pub did: String,
+    pub version: u64,
+    pub state: Vec<u8>,
+}
+
+#[event]
+pub struct AddressAdded {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+    pub role: Role,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct AddressRemoved {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+// permissionless once the validity of the address has ended
+#[event]
+pub struct AddressPruned {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+}
+
+#[event]
+pub struct RoleChanged {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+    pub role: Role,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct ThresholdChanged {
+    pub did: String,
+    pub threshold: u8,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+// the address is the new controller
+#[event]
+pub struct ControllerTransferred {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct ServiceAdded {
+    pub did: String,
+    pub service_id: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct ServiceRemoved {
+    pub did: String,
+    pub service_id: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct GuardiansSet {
+    pub did: String,
+    pub quorum: u8,
+    pub delay: i64,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+// authorised by a quorum of guardians and the new controller, which is the address
+#[event]
+pub struct RecoveryStarted {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct RecoveryCancelled {
+    pub did: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+// permissionless once the delay has passed, the address is the new controller
+#[event]
+pub struct RecoveryExecuted {
+    pub did: String,
+    pub chain: Chain,
+    pub address: String,
+}
+
+#[event]
+pub struct DidDeactivated {
+    pub did: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct DidMigrated {
+    pub did: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct AliasClaimed {
+    pub did: String,
+    pub alias: String,
+    pub expires_at: Option<i64>,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct AliasTransferred {
+    pub did: String,
+    pub alias: String,
+    pub new_did: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct AliasReleased {
+    pub did: String,
+    pub alias: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct AliasExpiryChanged {
+    pub did: String,
+    pub alias: String,
+    pub expires_at: Option<i64>,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct CredentialIssued {
+    pub credential_id: String,
+    pub issuer: String,
+    pub subject: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct CredentialUpdated {
+    pub credential_id: String,
+    pub issuer: String,
+    pub subject: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
+
+#[event]
+pub struct CredentialRevoked {
+    pub credential_id: String,
+    pub issuer: String,
+    pub subject: String,
+    pub signer_chain: Chain,
+    pub signer: String,
+}
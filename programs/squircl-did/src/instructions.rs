@@ -1,11 +1,35 @@
 pub mod add_address;
+pub mod add_address_zero_copy;
+pub mod add_service;
+pub mod cancel_recovery;
+pub mod change_role;
+pub mod claim_alias;
 pub mod create_did;
+pub mod create_did_zero_copy;
+pub mod deactivate_did;
+pub mod execute_recovery;
 pub mod issue_credential;
+pub mod migrate_did;
+pub mod prune_addresses;
+pub mod release_alias;
 pub mod remove_address;
+pub mod remove_address_zero_copy;
+pub mod remove_service;
 pub mod revoke_credential;
+pub mod set_alias_expiry;
+pub mod set_guardians;
+pub mod set_threshold;
+pub mod start_recovery;
+pub mod transfer_alias;
+pub mod transfer_controller;
 pub mod update_credential;
+pub mod verify_credential;
 
 pub use {
-    add_address::*, create_did::*, issue_credential::*, remove_address::*, revoke_credential::*,
-    update_credential::*,
+    add_address::*, add_address_zero_copy::*, add_service::*, cancel_recovery::*, change_role::*,
+    claim_alias::*, create_did::*, create_did_zero_copy::*, deactivate_did::*, execute_recovery::*,
+    issue_credential::*, migrate_did::*, prune_addresses::*, release_alias::*, remove_address::*,
+    remove_address_zero_copy::*, remove_service::*, revoke_credential::*, set_alias_expiry::*,
+    set_guardians::*, set_threshold::*, start_recovery::*, transfer_alias::*,
+    transfer_controller::*, update_credential::*, verify_credential::*,
 };
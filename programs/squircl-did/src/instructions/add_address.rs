@@ -1,185 +1,154 @@
 use anchor_lang::{
     prelude::*,
-    solana_program::{
-        hash,
-        sysvar::instructions::{load_instruction_at_checked, ID as IX_ID},
-    },
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
 };
 
 use crate::{
+    eip712::{hash_add_address, OperationMessage},
     errors::SquirclErrorCode,
-    state::{Address, Did, Role, Sig},
-    utils::{get_default_add_message_as_controller, get_default_add_message_as_new_address},
+    events::{AddressAdded, DidUpdated},
+    state::{Address, AddressLookup, AddressValidity, Did, NonceRegistry, Role, Sig},
+    utils::get_default_add_address_message,
 };
 
 pub fn add_address_ix(
     ctx: Context<AddAddress>,
     new_address_sig: Sig,
     controller_sig: Sig,
+    role: Role,
+    validity: AddressValidity,
+    co_signer_sigs: Vec<Sig>,
 ) -> Result<()> {
+    require!(
+        !matches!(role, Role::Controller),
+        SquirclErrorCode::CannotAssignControllerRole
+    );
+
     let did = &mut ctx.accounts.did;
 
     let clock: Clock = Clock::get()?;
 
-    let timestamp_1_hour_ago = clock.unix_timestamp - 3600;
-
-    let new_address = if let Sig::Eth {
-        eth_sig,
-        index: _,
-        nonce: _,
-    } = &new_address_sig
-    {
-        Address::new_eth(
-            eth_sig.get_eth_address_hex(),
-            clock.unix_timestamp,
-            Role::Admin,
-        )
-    } else if let Sig::Sol {
-        sol_sig,
-        index: _,
-        nonce: _,
-    } = &new_address_sig
-    {
-        Address::new_sol(
-            sol_sig.address_base58.clone(),
-            clock.unix_timestamp,
-            Role::Admin,
-        )
-    } else {
-        return Err(SquirclErrorCode::InvalidSignature.into());
-    };
-
-    match controller_sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let add_message_as_controller = get_default_add_message_as_controller(
-                eth_sig.get_eth_address_hex(),
-                new_address.address.clone(),
-                nonce,
-            );
-
-            eth_sig.verify(&controller_address_sign_ix, add_message_as_controller)?;
-
-            let mut verified: bool = false;
-
-            did.eth_addresses.iter().for_each(|address| {
-                if address.address == eth_sig.get_eth_address_hex() {
-                    if !(matches!(address.role, Role::Controller)
-                        || matches!(address.role, Role::Admin))
-                    {
-                        panic!("{}", SquirclErrorCode::AddressDoesntHaveEnoughPermissions)
-                    } else {
-                        verified = true;
-                    }
-                }
-            });
-
-            if !verified {
-                panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-            }
-        }
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let add_message_as_controller = get_default_add_message_as_controller(
-                sol_sig.address_base58.clone(),
-                new_address.address.clone(),
-                nonce,
-            );
-
-            sol_sig.verify(&controller_address_sign_ix, add_message_as_controller)?;
-
-            let mut verified: bool = false;
-
-            did.sol_addresses.iter().for_each(|address| {
-                if address.address == sol_sig.address_base58 {
-                    if !(matches!(address.role, Role::Controller)
-                        || matches!(address.role, Role::Admin))
-                    {
-                        panic!("{}", SquirclErrorCode::AddressDoesntHaveEnoughPermissions)
-                    } else {
-                        verified = true;
-                    }
-                }
-            });
-
-            if !verified {
-                panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-            }
-        }
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&new_address_sig, &clock)?;
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
     }
 
-    match new_address_sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let new_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let new_address_hex = eth_sig.get_eth_address_hex();
-
-            let add_message_as_new_adress =
-                get_default_add_message_as_new_address(new_address_hex.clone(), nonce);
-
-            eth_sig.verify(&new_address_sign_ix, add_message_as_new_adress)?;
-
-            did.eth_addresses.iter().for_each(|address| {
-                if address.address == new_address.address {
-                    panic!("{}", SquirclErrorCode::AddressAlreadyExists)
-                }
-            });
-
-            did.add_address_eth(clock.clone(), new_address);
+    // a window has to end in the future, and after it starts
+    let is_valid_window = match (validity.valid_from, validity.valid_until) {
+        (_, None) => true,
+        (None, Some(valid_until)) => valid_until > clock.unix_timestamp,
+        (Some(valid_from), Some(valid_until)) => {
+            valid_until > clock.unix_timestamp && valid_from < valid_until
         }
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let new_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let new_address_hex = sol_sig.address_base58.clone();
-
-            let add_message_as_new_adress =
-                get_default_add_message_as_new_address(new_address_hex.clone(), nonce);
+    };
 
-            sol_sig.verify(&new_address_sign_ix, add_message_as_new_adress)?;
+    require!(is_valid_window, SquirclErrorCode::InvalidAddressValidity);
+
+    let new_address_str = new_address_sig.signer();
+
+    let mut new_address = Address::new(
+        new_address_sig.chain(),
+        &new_address_str,
+        clock.unix_timestamp,
+        role.clone(),
+    )?;
+
+    new_address.valid_from = validity.valid_from;
+    new_address.valid_until = validity.valid_until;
+
+    let message_for = |signer, nonce| {
+        OperationMessage::typed(
+            get_default_add_address_message(
+                &did.did,
+                signer,
+                &new_address.chain,
+                &new_address_str,
+                &role,
+                validity,
+                nonce,
+            ),
+            hash_add_address(
+                &did.did,
+                &new_address.chain,
+                &new_address_str,
+                &role,
+                validity,
+                nonce,
+            ),
+        )
+    };
 
-            did.sol_addresses.iter().for_each(|address| {
-                if address.address == new_address.address {
-                    panic!("{}", SquirclErrorCode::AddressAlreadyExists)
-                }
-            });
-
-            did.add_address_sol(clock.clone(), new_address);
-        }
-    }
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        message_for,
+    )?;
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(controller.clone(), controller_sig.nonce()),
+    )?;
+
+    let found_controller =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_controller.role.can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    new_address_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(new_address_str.clone(), new_address_sig.nonce()),
+    )?;
+
+    require!(
+        did.find_address(&new_address.chain, &new_address_str)
+            .is_none(),
+        SquirclErrorCode::AddressAlreadyExists
+    );
+
+    ctx.accounts.address_lookup.add_did(&did.did)?;
+
+    AddressLookup::fit(
+        &ctx.accounts.address_lookup,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+
+    did.add_address(clock, new_address);
+
+    emit_cpi!(AddressAdded {
+        did: did.did.clone(),
+        chain: new_address_sig.chain(),
+        address: new_address_str,
+        role,
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(did_str: String, new_address_sig: Sig)]
 pub struct AddAddress<'info> {
@@ -189,14 +158,32 @@ pub struct AddAddress<'info> {
         mut,
         seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
         bump,
-        realloc = Did::LEN_WITHOUT_ADDRESS + did.sol_addresses.len() as usize * Address::SOL_LEN + did.eth_addresses.len() as usize * Address::ETH_LEN + match new_address_sig {
-            Sig::Eth { .. } => Address::ETH_LEN,
-            Sig::Sol { .. } => Address::SOL_LEN,
-        },
+        realloc = did.space() + Address::len_for(&new_address_sig.chain()),
         realloc::payer = payer,
-        realloc::zero = false
+        realloc::zero = false,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
     )]
     pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    #[account(
+        init_if_needed,
+        seeds = [
+            AddressLookup::SEED_PREFIX.as_bytes(),
+            &[new_address_sig.chain() as u8],
+            &AddressLookup::address_seed(&new_address_sig.chain(), &new_address_sig.signer()),
+        ],
+        payer = payer,
+        bump,
+        space = AddressLookup::len_with(&did_str)
+    )]
+    pub address_lookup: Account<'info, AddressLookup>,
     pub system_program: Program<'info, System>,
     /// CHECK: we make sure the sysvar is the actual sysvar account
     #[account(address = IX_ID)]
@@ -0,0 +1,169 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+    system_program,
+};
+
+use crate::{
+    eip712::{hash_add_address, OperationMessage},
+    errors::SquirclErrorCode,
+    events::AddressAdded,
+    state::{AddressValidity, NonceRegistry, Role, Sig, ZeroCopyDid},
+    utils::get_default_add_address_message,
+};
+
+pub fn add_address_zero_copy_ix(
+    ctx: Context<AddAddressZeroCopy>,
+    new_address_sig: Sig,
+    controller_sig: Sig,
+    role: Role,
+) -> Result<()> {
+    require!(
+        cfg!(feature = "zero-copy-bench"),
+        SquirclErrorCode::ZeroCopyDisabled
+    );
+
+    require!(
+        !matches!(role, Role::Controller),
+        SquirclErrorCode::CannotAssignControllerRole
+    );
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&new_address_sig, &clock)?;
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    let did_str = ctx.accounts.did.load()?.did();
+
+    let new_address_chain = new_address_sig.chain();
+    let new_address = new_address_sig.signer();
+    let new_key = new_address_chain.decode_address(&new_address)?;
+
+    let controller = controller_sig.signer();
+
+    for (sig, signer) in [
+        (&controller_sig, controller.clone()),
+        (&new_address_sig, new_address.clone()),
+    ] {
+        sig.verify_at(
+            &ctx.accounts.ix_sysvar,
+            OperationMessage::typed(
+                get_default_add_address_message(
+                    &did_str,
+                    signer,
+                    &new_address_chain,
+                    &new_address,
+                    &role,
+                    AddressValidity::default(),
+                    sig.nonce(),
+                ),
+                hash_add_address(
+                    &did_str,
+                    &new_address_chain,
+                    &new_address,
+                    &role,
+                    AddressValidity::default(),
+                    sig.nonce(),
+                ),
+            ),
+        )?;
+    }
+
+    grow_if_full(&ctx)?;
+
+    let did_info = ctx.accounts.did.to_account_info();
+    let mut data = did_info.try_borrow_mut_data()?;
+    let (did, slots) = ZeroCopyDid::split(&mut data)?;
+
+    let found_controller = did
+        .find_address(slots, &controller_sig.chain(), &controller)
+        .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+    require!(
+        found_controller.role().can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    did.add_address(slots, &clock, &new_address_chain, &new_key, &role)?;
+
+    emit_cpi!(AddressAdded {
+        did: did_str,
+        chain: new_address_chain,
+        address: new_address,
+        role,
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    Ok(())
+}
+
+// adds a chunk of address slots once they are all in use, the payer tops up the rent
+fn grow_if_full(ctx: &Context<AddAddressZeroCopy>) -> Result<()> {
+    let address_capacity = {
+        let did = ctx.accounts.did.load()?;
+
+        if !did.is_full() {
+            return Ok(());
+        }
+
+        did.address_capacity
+            .checked_add(ZeroCopyDid::SLOT_CHUNK)
+            .ok_or(SquirclErrorCode::TooManyAddresses)?
+    };
+
+    let did_info = ctx.accounts.did.to_account_info();
+    let len = ZeroCopyDid::len_for(address_capacity);
+    let top_up = Rent::get()?
+        .minimum_balance(len)
+        .saturating_sub(did_info.lamports());
+
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: did_info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+
+    did_info.realloc(len, false)?;
+
+    ctx.accounts.did.load_mut()?.address_capacity = address_capacity;
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct AddAddressZeroCopy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub did: AccountLoader<'info, ZeroCopyDid>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
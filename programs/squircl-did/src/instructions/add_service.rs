@@ -0,0 +1,113 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidUpdated, ServiceAdded},
+    state::{Did, NonceRegistry, Role, Service, Sig},
+    utils::get_default_add_service_message,
+};
+
+pub fn add_service_ix(
+    ctx: Context<AddService>,
+    service: Service,
+    signer_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts.nonce_registry.use_nonce(&signer_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    service.validate()?;
+
+    require!(
+        did.find_service(&service.id).is_none(),
+        SquirclErrorCode::ServiceAlreadyExists
+    );
+    require!(
+        did.services.len() < Did::MAX_SERVICES,
+        SquirclErrorCode::TooManyServices
+    );
+
+    let signer = signer_sig.signer();
+
+    signer_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_add_service_message(&did.did, signer.clone(), &service, signer_sig.nonce()),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&signer_sig.chain(), &signer, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &signer_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| get_default_add_service_message(&did.did, signer, &service, nonce),
+    )?;
+
+    let service_id = service.id.clone();
+
+    did.add_service(clock, service);
+
+    emit_cpi!(ServiceAdded {
+        did: did.did.clone(),
+        service_id,
+        signer_chain: signer_sig.chain(),
+        signer,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String, service: Service)]
+pub struct AddService<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        realloc = did.space() + service.space(),
+        realloc::payer = payer,
+        realloc::zero = false,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
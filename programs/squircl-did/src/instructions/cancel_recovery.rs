@@ -0,0 +1,101 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::RecoveryCancelled,
+    state::{Did, NonceRegistry, Recovery, Role, Sig},
+    utils::get_default_cancel_recovery_message,
+};
+
+pub fn cancel_recovery_ix(
+    ctx: Context<CancelRecovery>,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &ctx.accounts.did;
+    let recovery = &mut ctx.accounts.recovery;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    require!(recovery.is_pending(), SquirclErrorCode::NoPendingRecovery);
+
+    let message_for = |signer, nonce| get_default_cancel_recovery_message(&did.did, signer, nonce);
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        message_for,
+    )?;
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(controller.clone(), controller_sig.nonce()),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    recovery.pending = None;
+
+    emit_cpi!(RecoveryCancelled {
+        did: did.did.clone(),
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct CancelRecovery<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        mut,
+        seeds = [Recovery::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub recovery: Account<'info, Recovery>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
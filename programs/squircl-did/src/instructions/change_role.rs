@@ -0,0 +1,141 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidUpdated, RoleChanged},
+    state::{Chain, Did, NonceRegistry, Role, Sig},
+    utils::get_default_change_role_message,
+};
+
+pub fn change_role_ix(
+    ctx: Context<ChangeRole>,
+    address_chain: Chain,
+    address: String,
+    role: Role,
+    changer_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    require!(
+        !matches!(role, Role::Controller),
+        SquirclErrorCode::CannotAssignControllerRole
+    );
+
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&changer_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &changer_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| {
+            get_default_change_role_message(
+                &did.did,
+                signer,
+                &address_chain,
+                &address,
+                &role,
+                nonce,
+            )
+        },
+    )?;
+
+    let changer = changer_sig.signer();
+
+    changer_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_change_role_message(
+            &did.did,
+            changer.clone(),
+            &address_chain,
+            &address,
+            &role,
+            changer_sig.nonce(),
+        ),
+    )?;
+
+    let found_changer =
+        did.find_valid_address(&changer_sig.chain(), &changer, clock.unix_timestamp)?;
+
+    require!(
+        found_changer.role.can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    let found_address = did
+        .find_address(&address_chain, &address)
+        .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+    require!(
+        !found_address.role.is_controller(),
+        SquirclErrorCode::CannotChangeControllerRole
+    );
+
+    let key = found_address.address.clone();
+
+    did.change_role(clock, &address_chain, &key, role.clone());
+
+    // demoting an admin must not leave the DID unable to meet its own threshold
+    require!(
+        did.manager_count() >= did.required_signatures(),
+        SquirclErrorCode::InvalidThreshold
+    );
+
+    emit_cpi!(RoleChanged {
+        did: did.did.clone(),
+        chain: address_chain,
+        address,
+        role,
+        signer_chain: changer_sig.chain(),
+        signer: changer,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct ChangeRole<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
@@ -0,0 +1,131 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::AliasClaimed,
+    state::{Alias, Did, NonceRegistry, Role, Sig},
+    utils::{get_default_claim_alias_message, validate_alias},
+};
+
+pub fn claim_alias_ix(
+    ctx: Context<ClaimAlias>,
+    handle: String,
+    expires_at: Option<i64>,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &ctx.accounts.did;
+    let alias = &mut ctx.accounts.alias;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    validate_alias(&handle)?;
+
+    // an expired alias is up for grabs again, whoever held it before
+    require!(
+        !alias.is_claimed(clock.unix_timestamp),
+        SquirclErrorCode::AliasAlreadyClaimed
+    );
+    require!(
+        match expires_at {
+            Some(expires_at) => expires_at > clock.unix_timestamp,
+            None => true,
+        },
+        SquirclErrorCode::InvalidAliasExpiry
+    );
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_claim_alias_message(
+            &did.did,
+            controller.clone(),
+            &handle,
+            expires_at,
+            controller_sig.nonce(),
+        ),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| {
+            get_default_claim_alias_message(&did.did, signer, &handle, expires_at, nonce)
+        },
+    )?;
+
+    alias.set_inner(Alias {
+        handle,
+        did_seed: Alias::did_seed(&did.did),
+        claimed_at: clock.unix_timestamp,
+        expires_at,
+    });
+
+    emit_cpi!(AliasClaimed {
+        did: did.did.clone(),
+        alias: alias.handle.clone(),
+        expires_at,
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String, handle: String)]
+pub struct ClaimAlias<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [Alias::SEED_PREFIX.as_bytes(), handle.as_bytes()],
+        payer = payer,
+        bump,
+        space = Alias::LEN
+    )]
+    pub alias: Account<'info, Alias>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
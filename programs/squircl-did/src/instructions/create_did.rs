@@ -1,77 +1,76 @@
+use crate::eip712::{hash_create_did, OperationMessage};
 use crate::errors::SquirclErrorCode;
-use crate::state::{Address, Did, Role, Sig};
-use crate::utils::get_default_create_message;
+use crate::events::{DidCreated, DidUpdated};
+use crate::state::{Address, AddressLookup, Did, NonceRegistry, Role, Sig};
+use crate::utils::{get_default_create_message, get_derived_did, is_derived_did, validate_did};
 use anchor_lang::{
     prelude::*,
-    solana_program::{
-        hash,
-        instruction::Instruction,
-        sysvar::instructions::{load_instruction_at_checked, ID as IX_ID},
-    },
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
 };
 
 pub fn create_did_ix(ctx: Context<CreateDID>, did_str: String, sig: Sig) -> Result<()> {
-    let did = &mut ctx.accounts.did;
-
-    let clock: Clock = Clock::get()?;
-
-    let timestamp_1_hour_ago = clock.unix_timestamp - 3600;
-
-    match sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let ix: Instruction =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let eth_address_hex = eth_sig.get_eth_address_hex();
+    validate_did(&did_str)?;
 
-            let new_did_message = get_default_create_message(eth_address_hex.clone(), nonce);
-
-            eth_sig.verify(&ix, new_did_message)?;
-
-            did.set_inner(Did::new_eth(
-                did_str,
-                clock.clone(),
-                Address::new_eth(eth_address_hex, clock.unix_timestamp, Role::Controller),
-            ));
-        }
-
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let ix: Instruction =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
+    if is_derived_did(&did_str) {
+        require!(
+            did_str == get_derived_did(&sig.chain(), &sig.signer()),
+            SquirclErrorCode::DerivedDidMismatch
+        );
+    }
 
-            let new_did_message = get_default_create_message(sol_sig.address_base58.clone(), nonce);
+    let did = &mut ctx.accounts.did;
 
-            sol_sig.verify(&ix, new_did_message)?;
+    let clock: Clock = Clock::get()?;
 
-            did.set_inner(Did::new_sol(
-                did_str,
-                clock.clone(),
-                Address::new_sol(
-                    sol_sig.address_base58,
-                    clock.unix_timestamp,
-                    Role::Controller,
-                ),
-            ));
-        }
-    }
+    ctx.accounts.nonce_registry.use_nonce(&sig, &clock)?;
+
+    let address = sig.signer();
+
+    sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        OperationMessage::typed(
+            get_default_create_message(&did_str, address.clone(), sig.nonce()),
+            hash_create_did(&did_str, &address, sig.nonce()),
+        ),
+    )?;
+
+    ctx.accounts.address_lookup.add_did(&did_str)?;
+
+    AddressLookup::fit(
+        &ctx.accounts.address_lookup,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+
+    did.set_inner(Did::new(
+        did_str,
+        clock.clone(),
+        Address::new(
+            sig.chain(),
+            &address,
+            clock.unix_timestamp,
+            Role::Controller,
+        )?,
+    ));
+
+    emit_cpi!(DidCreated {
+        did: did.did.clone(),
+        chain: sig.chain(),
+        address,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(did_str: String)]
+#[instruction(did_str: String, sig: Sig)]
 pub struct CreateDID<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -81,9 +80,29 @@ pub struct CreateDID<'info> {
         seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
         payer = payer,
         bump,
-        space = Did::LEN_WITHOUT_ADDRESS + Address::ETH_LEN
+        space = Did::len_without_address(&did_str) + Address::len_for(&sig.chain())
     )]
     pub did: Account<'info, Did>,
+    #[account(
+        init,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    #[account(
+        init_if_needed,
+        seeds = [
+            AddressLookup::SEED_PREFIX.as_bytes(),
+            &[sig.chain() as u8],
+            &AddressLookup::address_seed(&sig.chain(), &sig.signer()),
+        ],
+        payer = payer,
+        bump,
+        space = AddressLookup::len_with(&did_str)
+    )]
+    pub address_lookup: Account<'info, AddressLookup>,
     pub system_program: Program<'info, System>,
     /// CHECK: we make sure the sysvar is the actual sysvar account
     #[account(address = IX_ID)]
@@ -101,10 +120,7 @@ pub struct CreateDIDSOL<'info> {
         seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
         payer = payer,
         bump,
-        space = Did::LEN_WITHOUT_ADDRESS + match sig {
-            Sig::Eth { .. } => Address::ETH_LEN,
-            Sig::Sol { .. } => Address::SOL_LEN,
-        }
+        space = Did::len_without_address(&did_str) + Address::len_for(&sig.chain())
     )]
     pub did: Account<'info, Did>,
     pub system_program: Program<'info, System>,
@@ -0,0 +1,93 @@
+use crate::eip712::{hash_create_did, OperationMessage};
+use crate::errors::SquirclErrorCode;
+use crate::events::DidCreated;
+use crate::state::{NonceRegistry, Role, Sig, ZeroCopyDid};
+use crate::utils::{get_default_create_message, get_derived_did, is_derived_did, validate_did};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+pub fn create_did_zero_copy_ix(
+    ctx: Context<CreateDIDZeroCopy>,
+    did_str: String,
+    sig: Sig,
+) -> Result<()> {
+    require!(
+        cfg!(feature = "zero-copy-bench"),
+        SquirclErrorCode::ZeroCopyDisabled
+    );
+
+    validate_did(&did_str)?;
+
+    if is_derived_did(&did_str) {
+        require!(
+            did_str == get_derived_did(&sig.chain(), &sig.signer()),
+            SquirclErrorCode::DerivedDidMismatch
+        );
+    }
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts.nonce_registry.use_nonce(&sig, &clock)?;
+
+    let address = sig.signer();
+
+    sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        OperationMessage::typed(
+            get_default_create_message(&did_str, address.clone(), sig.nonce()),
+            hash_create_did(&did_str, &address, sig.nonce()),
+        ),
+    )?;
+
+    let key = sig.chain().decode_address(&address)?;
+
+    ctx.accounts
+        .did
+        .load_init()?
+        .init(&did_str, &clock, ZeroCopyDid::SLOT_CHUNK);
+
+    let did_info = ctx.accounts.did.to_account_info();
+    let mut data = did_info.try_borrow_mut_data()?;
+    let (did, slots) = ZeroCopyDid::split(&mut data)?;
+
+    did.add_address(slots, &clock, &sig.chain(), &key, &Role::Controller)?;
+
+    emit_cpi!(DidCreated {
+        did: did_str,
+        chain: sig.chain(),
+        address,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct CreateDIDZeroCopy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = ZeroCopyDid::len_for(ZeroCopyDid::SLOT_CHUNK)
+    )]
+    pub did: AccountLoader<'info, ZeroCopyDid>,
+    #[account(
+        init,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
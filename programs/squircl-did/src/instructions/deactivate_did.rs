@@ -0,0 +1,159 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{AliasReleased, DidDeactivated, DidUpdated},
+    state::{Address, AddressLookup, Alias, Did, NonceRegistry, Recovery, Role, Sig},
+    utils::get_default_deactivate_message,
+};
+
+// the lookups of all the addresses of the did are passed as remaining accounts, along with any
+// of its aliases, so none of them is left pointing at the tombstone
+pub fn deactivate_did_ix<'info>(
+    ctx: Context<'_, '_, '_, 'info, DeactivateDID<'info>>,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    let recipient = ctx.accounts.recipient.key();
+
+    let message_for =
+        |signer, nonce| get_default_deactivate_message(&did.did, signer, &recipient, nonce);
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        message_for,
+    )?;
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(controller.clone(), controller_sig.nonce()),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    let addresses: Vec<Address> = did.all_addresses().cloned().collect();
+
+    did.deactivate(clock);
+
+    // shrink the did down to its tombstone and hand the freed rent to the recipient
+    let did_info = did.to_account_info();
+    let tombstone_len = Did::len_without_address(&did.did);
+    let rent_exempt = Rent::get()?.minimum_balance(tombstone_len);
+    let reclaimed = did_info.lamports().saturating_sub(rent_exempt);
+
+    did_info.realloc(tombstone_len, false)?;
+
+    **did_info.try_borrow_mut_lamports()? -= reclaimed;
+    **ctx.accounts.recipient.try_borrow_mut_lamports()? += reclaimed;
+
+    let lookup_keys = AddressLookup::remove_did_from_all(
+        &did.did,
+        &addresses,
+        ctx.remaining_accounts,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+
+    for alias_info in ctx
+        .remaining_accounts
+        .iter()
+        .filter(|a| !lookup_keys.contains(&a.key()))
+    {
+        let alias = Account::<Alias>::try_from(alias_info)?;
+
+        require!(alias.is_owned_by(&did.did), SquirclErrorCode::AliasNotOwned);
+
+        alias.close(ctx.accounts.recipient.to_account_info())?;
+
+        emit_cpi!(AliasReleased {
+            did: did.did.clone(),
+            alias: alias.handle.clone(),
+            signer_chain: controller_sig.chain(),
+            signer: controller.clone(),
+        });
+    }
+
+    // the guardians and any pending recovery go with the did
+    if ctx.accounts.recovery.owner == &crate::ID {
+        Account::<Recovery>::try_from(&ctx.accounts.recovery)?
+            .close(ctx.accounts.recipient.to_account_info())?;
+    }
+
+    emit_cpi!(DidDeactivated {
+        did: did.did.clone(),
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct DeactivateDID<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        mut,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        close = recipient
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    /// CHECK: the recovery settings of the did, closed along with it when it has any
+    #[account(
+        mut,
+        seeds = [Recovery::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub recovery: AccountInfo<'info>,
+    /// CHECK: only receives lamports, and is covered by the controller signature
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
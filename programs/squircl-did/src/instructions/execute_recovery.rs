@@ -0,0 +1,126 @@
+use anchor_lang::{prelude::*, solana_program::hash};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidUpdated, RecoveryExecuted},
+    state::{AddressLookup, Did, Recovery},
+};
+
+// permissionless, anyone can crank a recovery once its delay has passed
+pub fn execute_recovery_ix(ctx: Context<ExecuteRecovery>) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+    let recovery = &mut ctx.accounts.recovery;
+
+    let clock: Clock = Clock::get()?;
+
+    let pending = recovery
+        .pending
+        .take()
+        .ok_or(SquirclErrorCode::NoPendingRecovery)?;
+
+    require!(
+        clock.unix_timestamp >= pending.executable_at,
+        SquirclErrorCode::RecoveryTimelockActive
+    );
+
+    let chain = pending.new_controller.chain.clone();
+    let address = pending.new_controller.get_address_string();
+
+    let is_same_controller = matches!(
+        did.controller(),
+        Some(c) if c.chain == chain && c.address == pending.new_controller.address
+    );
+
+    did.replace_controller(clock, pending.new_controller);
+
+    // the lookups follow the controller, the replaced one no longer resolves to the did
+    if !is_same_controller {
+        let controller_lookup = &mut ctx.accounts.controller_lookup;
+
+        controller_lookup.remove_did(&did.did);
+
+        if controller_lookup.dids.is_empty() {
+            controller_lookup.close(ctx.accounts.payer.to_account_info())?;
+        } else {
+            AddressLookup::fit(
+                controller_lookup,
+                &ctx.accounts.payer,
+                &ctx.accounts.system_program,
+            )?;
+        }
+    }
+
+    let new_controller_lookup = &mut ctx.accounts.new_controller_lookup;
+
+    new_controller_lookup.add_did(&did.did)?;
+
+    AddressLookup::fit(
+        new_controller_lookup,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+
+    emit_cpi!(RecoveryExecuted {
+        did: did.did.clone(),
+        chain,
+        address,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct ExecuteRecovery<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        realloc = recovery.did_len_after_execution(&did),
+        realloc::payer = payer,
+        realloc::zero = false,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        mut,
+        seeds = [Recovery::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub recovery: Account<'info, Recovery>,
+    // controllers set before lookups existed have none, it is created empty and closed right away
+    #[account(
+        init_if_needed,
+        seeds = [
+            AddressLookup::SEED_PREFIX.as_bytes(),
+            &AddressLookup::seeds_of(did.controller()).0,
+            &AddressLookup::seeds_of(did.controller()).1,
+        ],
+        payer = payer,
+        bump,
+        space = AddressLookup::LEN_WITHOUT_DIDS
+    )]
+    pub controller_lookup: Account<'info, AddressLookup>,
+    #[account(
+        init_if_needed,
+        seeds = [
+            AddressLookup::SEED_PREFIX.as_bytes(),
+            &AddressLookup::seeds_of(recovery.new_controller()).0,
+            &AddressLookup::seeds_of(recovery.new_controller()).1,
+        ],
+        payer = payer,
+        bump,
+        space = AddressLookup::len_with(&did_str)
+    )]
+    pub new_controller_lookup: Account<'info, AddressLookup>,
+    pub system_program: Program<'info, System>,
+}
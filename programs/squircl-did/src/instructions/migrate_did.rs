@@ -0,0 +1,137 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+    system_program,
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidMigrated, DidUpdated},
+    state::{LegacyDid, NonceRegistry, Role, Sig},
+    utils::get_default_migrate_message,
+};
+
+/// Rewrites a DID stored with string addresses in the current raw key layout, resizes the account
+/// to fit and hands any freed rent to the recipient
+pub fn migrate_did_ix(
+    ctx: Context<MigrateDID>,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did_info = ctx.accounts.did.to_account_info();
+
+    let did = LegacyDid::try_from_account_data(&did_info.try_borrow_data()?)?.into_did()?;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    let recipient = ctx.accounts.recipient.key();
+
+    let message_for =
+        |signer, nonce| get_default_migrate_message(&did.did, signer, &recipient, nonce);
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        message_for,
+    )?;
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(controller.clone(), controller_sig.nonce()),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    let did_len = did.space();
+
+    let rent_exempt = Rent::get()?.minimum_balance(did_len);
+    let lamports = did_info.lamports();
+
+    // a legacy did was sized for string addresses and a signature it never stored, so it
+    // shrinks. The payer only covers the rent of one that doesn't
+    if rent_exempt > lamports {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: did_info.clone(),
+                },
+            ),
+            rent_exempt - lamports,
+        )?;
+    } else {
+        **did_info.try_borrow_mut_lamports()? -= lamports - rent_exempt;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += lamports - rent_exempt;
+    }
+
+    did_info.realloc(did_len, false)?;
+
+    did.try_serialize(&mut &mut did_info.try_borrow_mut_data()?[..])?;
+
+    emit_cpi!(DidMigrated {
+        did: did.did.clone(),
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct MigrateDID<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: a DID in the legacy layout can't be loaded as `Account<Did>`, it is decoded by the
+    /// instruction
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub did: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    /// CHECK: only receives lamports, and is covered by the controller signature
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
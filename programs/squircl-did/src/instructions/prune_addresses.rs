@@ -0,0 +1,73 @@
+use anchor_lang::{prelude::*, solana_program::hash};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{AddressPruned, DidUpdated},
+    state::{AddressLookup, Did},
+};
+
+// permissionless, anyone can prune the expired addresses of a did and gets the rent it frees.
+// The lookups of all the pruned addresses are passed as remaining accounts, so none of them keeps
+// resolving to the did
+pub fn prune_addresses_ix<'info>(
+    ctx: Context<'_, '_, '_, 'info, PruneAddresses<'info>>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    let pruned = did.prune_expired(clock);
+
+    require!(!pruned.is_empty(), SquirclErrorCode::NoExpiredAddresses);
+
+    // shrink the did down to its remaining addresses and hand the freed rent to the payer
+    let did_info = did.to_account_info();
+    let did_len = did.space();
+    let rent_exempt = Rent::get()?.minimum_balance(did_len);
+    let reclaimed = did_info.lamports().saturating_sub(rent_exempt);
+
+    did_info.realloc(did_len, false)?;
+
+    **did_info.try_borrow_mut_lamports()? -= reclaimed;
+    **ctx.accounts.payer.try_borrow_mut_lamports()? += reclaimed;
+
+    AddressLookup::remove_did_from_all(
+        &did.did,
+        &pruned,
+        ctx.remaining_accounts,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+
+    for address in pruned {
+        emit_cpi!(AddressPruned {
+            did: did.did.clone(),
+            chain: address.chain.clone(),
+            address: address.get_address_string(),
+        });
+    }
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct PruneAddresses<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    pub system_program: Program<'info, System>,
+}
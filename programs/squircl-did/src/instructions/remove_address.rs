@@ -1,15 +1,14 @@
 use anchor_lang::{
     prelude::*,
-    solana_program::{
-        hash,
-        sysvar::instructions::{load_instruction_at_checked, ID as IX_ID},
-    },
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
 };
 
 use crate::{
+    eip712::{hash_remove_address, OperationMessage},
     errors::SquirclErrorCode,
-    state::{Address, Chain, Did, Role, Sig},
-    utils::{get_default_remove_message_as_address, get_default_remove_message_as_controller},
+    events::{AddressRemoved, DidUpdated},
+    state::{Address, AddressLookup, Chain, Did, NonceRegistry, Role, Sig},
+    utils::get_default_remove_address_message,
 };
 
 pub fn remove_address_ix(
@@ -17,171 +16,124 @@ pub fn remove_address_ix(
     address_chain: Chain,
     address: String,
     remover_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
 ) -> Result<()> {
     let did = &mut ctx.accounts.did;
 
     let clock: Clock = Clock::get()?;
 
-    let timestamp_1_hour_ago = clock.unix_timestamp - 3600;
-
-    match remover_sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let remover_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let eth_address = eth_sig.get_eth_address_hex();
-
-            let is_self_remove = address == eth_address;
-
-            if !is_self_remove {
-                let mut verified: bool = false;
-
-                did.eth_addresses.iter().for_each(|address| {
-                    if address.address == eth_address {
-                        if !(matches!(address.role, Role::Controller)
-                            || matches!(address.role, Role::Admin))
-                        {
-                            panic!("{}", SquirclErrorCode::AddressDoesntHaveEnoughPermissions)
-                        } else {
-                            verified = true;
-                        }
-                    }
-                });
-
-                if !verified {
-                    panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-                }
-            }
-
-            let message = if is_self_remove {
-                get_default_remove_message_as_address(eth_address, nonce)
-            } else {
-                get_default_remove_message_as_controller(eth_address, address.clone(), nonce)
-            };
-
-            msg!("message: {}", message);
-
-            msg!("is self remove: {}", is_self_remove);
-
-            eth_sig.verify(&remover_sign_ix, message)?;
-
-            match address_chain {
-                Chain::EVM => {
-                    let found_address = did
-                        .eth_addresses
-                        .iter()
-                        .find(|addr| addr.address == address);
-
-                    if !found_address.is_some() {
-                        panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-                    }
-
-                    if matches!(found_address.unwrap().role, Role::Controller) {
-                        panic!("{}", SquirclErrorCode::CannotRemoveControllerAddress)
-                    }
-
-                    did.remove_address_eth(clock, address)
-                }
-                Chain::SOL => {
-                    let found_address = did
-                        .sol_addresses
-                        .iter()
-                        .find(|addr| addr.address == address);
-
-                    if !found_address.is_some() {
-                        panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-                    }
-
-                    if matches!(found_address.unwrap().role, Role::Controller) {
-                        panic!("{}", SquirclErrorCode::CannotRemoveControllerAddress)
-                    }
-
-                    did.remove_address_sol(clock, address)
-                }
-            }
-        }
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let remover_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let sol_address = sol_sig.address_base58.clone();
-
-            let is_self_remove = address == sol_address;
-
-            if !is_self_remove {
-                let mut verified: bool = false;
-
-                did.sol_addresses.iter().for_each(|address| {
-                    if address.address == sol_address {
-                        if !(matches!(address.role, Role::Controller)
-                            || matches!(address.role, Role::Admin))
-                        {
-                            panic!("{}", SquirclErrorCode::AddressDoesntHaveEnoughPermissions)
-                        }
-                    } else {
-                        verified = true;
-                    }
-                });
-
-                if !verified {
-                    panic!("{}", SquirclErrorCode::AddressDoesNotExistInDID)
-                }
-            }
-
-            let message = if is_self_remove {
-                get_default_remove_message_as_address(sol_address, nonce)
-            } else {
-                get_default_remove_message_as_controller(sol_address, address.clone(), nonce)
-            };
-
-            msg!("message: {}", message);
-
-            sol_sig.verify(&remover_sign_ix, message)?;
-
-            match address_chain {
-                Chain::EVM => {
-                    did.eth_addresses.iter().for_each(|addr| {
-                        if addr.address == address {
-                            if matches!(addr.role, Role::Controller) {
-                                panic!("{}", SquirclErrorCode::CannotRemoveControllerAddress)
-                            }
-                        }
-                    });
-
-                    did.remove_address_eth(clock, address)
-                }
-                Chain::SOL => {
-                    did.sol_addresses.iter().for_each(|addr| {
-                        if addr.address == address {
-                            if matches!(addr.role, Role::Controller) {
-                                panic!("{}", SquirclErrorCode::CannotRemoveControllerAddress)
-                            }
-                        }
-                    });
-
-                    did.remove_address_sol(clock, address)
-                }
-            }
-        }
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&remover_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    // an address can always remove itself, removing anyone else is subject to the threshold
+    if remover_sig.chain() != address_chain || remover_sig.signer() != address {
+        did.check_threshold(
+            &ctx.accounts.ix_sysvar,
+            &remover_sig,
+            &co_signer_sigs,
+            Role::can_manage,
+            |signer, nonce| {
+                OperationMessage::typed(
+                    get_default_remove_address_message(
+                        &did.did,
+                        signer,
+                        &address_chain,
+                        &address,
+                        nonce,
+                    ),
+                    hash_remove_address(&did.did, &address_chain, &address, nonce),
+                )
+            },
+        )?;
     }
 
+    let remover = remover_sig.signer();
+
+    let is_self_remove = address_chain == remover_sig.chain() && address == remover;
+
+    if !is_self_remove {
+        let found_remover =
+            did.find_valid_address(&remover_sig.chain(), &remover, clock.unix_timestamp)?;
+
+        require!(
+            found_remover.role.can_manage(),
+            SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+        );
+    }
+
+    remover_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        OperationMessage::typed(
+            get_default_remove_address_message(
+                &did.did,
+                remover,
+                &address_chain,
+                &address,
+                remover_sig.nonce(),
+            ),
+            hash_remove_address(&did.did, &address_chain, &address, remover_sig.nonce()),
+        ),
+    )?;
+
+    let found_address = did
+        .find_address(&address_chain, &address)
+        .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+    require!(
+        !found_address.role.is_controller(),
+        SquirclErrorCode::CannotRemoveControllerAddress
+    );
+
+    let key = found_address.address.clone();
+
+    did.remove_address(clock, &address_chain, &key);
+
+    let address_lookup = &mut ctx.accounts.address_lookup;
+
+    address_lookup.remove_did(&did.did);
+
+    if address_lookup.dids.is_empty() {
+        address_lookup.close(ctx.accounts.payer.to_account_info())?;
+    } else {
+        AddressLookup::fit(
+            address_lookup,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+    }
+
+    require!(
+        did.manager_count() >= did.required_signatures(),
+        SquirclErrorCode::InvalidThreshold
+    );
+
+    emit_cpi!(AddressRemoved {
+        did: did.did.clone(),
+        chain: address_chain,
+        address,
+        signer_chain: remover_sig.chain(),
+        signer: remover_sig.signer(),
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(did_str: String, address_chain: Chain)]
+#[instruction(did_str: String, address_chain: Chain, address: String)]
 pub struct RemoveAddress<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -189,14 +141,33 @@ pub struct RemoveAddress<'info> {
         mut,
         seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
         bump,
-        realloc = Did::LEN_WITHOUT_ADDRESS + did.sol_addresses.len() as usize * Address::SOL_LEN + did.eth_addresses.len() as usize * Address::ETH_LEN - match address_chain {
-           Chain::EVM => Address::ETH_LEN,
-           Chain::SOL => Address::SOL_LEN,
-        },
+        realloc = did.space() - Address::len_for(&address_chain),
         realloc::payer = payer,
-        realloc::zero = false
+        realloc::zero = false,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
     )]
     pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    // addresses added before lookups existed have none, it is created empty and closed right away
+    #[account(
+        init_if_needed,
+        seeds = [
+            AddressLookup::SEED_PREFIX.as_bytes(),
+            &[address_chain.clone() as u8],
+            &AddressLookup::address_seed(&address_chain, &address),
+        ],
+        payer = payer,
+        bump,
+        space = AddressLookup::LEN_WITHOUT_DIDS
+    )]
+    pub address_lookup: Account<'info, AddressLookup>,
     pub system_program: Program<'info, System>,
     /// CHECK: we make sure the sysvar is the actual sysvar account
     #[account(address = IX_ID)]
@@ -0,0 +1,115 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    eip712::{hash_remove_address, OperationMessage},
+    errors::SquirclErrorCode,
+    events::AddressRemoved,
+    state::{Chain, NonceRegistry, Sig, ZeroCopyDid},
+    utils::get_default_remove_address_message,
+};
+
+pub fn remove_address_zero_copy_ix(
+    ctx: Context<RemoveAddressZeroCopy>,
+    address_chain: Chain,
+    address: String,
+    remover_sig: Sig,
+) -> Result<()> {
+    require!(
+        cfg!(feature = "zero-copy-bench"),
+        SquirclErrorCode::ZeroCopyDisabled
+    );
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&remover_sig, &clock)?;
+
+    let did_str = ctx.accounts.did.load()?.did();
+
+    let remover = remover_sig.signer();
+
+    remover_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        OperationMessage::typed(
+            get_default_remove_address_message(
+                &did_str,
+                remover.clone(),
+                &address_chain,
+                &address,
+                remover_sig.nonce(),
+            ),
+            hash_remove_address(&did_str, &address_chain, &address, remover_sig.nonce()),
+        ),
+    )?;
+
+    let did_info = ctx.accounts.did.to_account_info();
+    let mut data = did_info.try_borrow_mut_data()?;
+    let (did, slots) = ZeroCopyDid::split(&mut data)?;
+
+    // an address can always remove itself
+    let is_self_remove = address_chain == remover_sig.chain() && address == remover;
+
+    if !is_self_remove {
+        let found_remover = did
+            .find_address(slots, &remover_sig.chain(), &remover)
+            .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+        require!(
+            found_remover.role().can_manage(),
+            SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+        );
+    }
+
+    let found_address = did
+        .find_address(slots, &address_chain, &address)
+        .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+    require!(
+        !found_address.role().is_controller(),
+        SquirclErrorCode::CannotRemoveControllerAddress
+    );
+
+    let key = address_chain.decode_address(&address)?;
+
+    did.remove_address(slots, &clock, &address_chain, &key)?;
+
+    emit_cpi!(AddressRemoved {
+        did: did_str,
+        chain: address_chain,
+        address,
+        signer_chain: remover_sig.chain(),
+        signer: remover,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct RemoveAddressZeroCopy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub did: AccountLoader<'info, ZeroCopyDid>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
@@ -0,0 +1,110 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidUpdated, ServiceRemoved},
+    state::{Did, NonceRegistry, Role, Sig},
+    utils::get_default_remove_service_message,
+};
+
+pub fn remove_service_ix(
+    ctx: Context<RemoveService>,
+    service_id: String,
+    signer_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts.nonce_registry.use_nonce(&signer_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    require!(
+        did.find_service(&service_id).is_some(),
+        SquirclErrorCode::ServiceDoesNotExist
+    );
+
+    let signer = signer_sig.signer();
+
+    signer_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_remove_service_message(
+            &did.did,
+            signer.clone(),
+            &service_id,
+            signer_sig.nonce(),
+        ),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&signer_sig.chain(), &signer, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &signer_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| get_default_remove_service_message(&did.did, signer, &service_id, nonce),
+    )?;
+
+    did.remove_service(clock, service_id.clone());
+
+    emit_cpi!(ServiceRemoved {
+        did: did.did.clone(),
+        service_id,
+        signer_chain: signer_sig.chain(),
+        signer,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String, service_id: String)]
+pub struct RemoveService<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        realloc = did.space() - did.find_service(&service_id).map_or(0, |s| s.space()),
+        realloc::payer = payer,
+        realloc::zero = false,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
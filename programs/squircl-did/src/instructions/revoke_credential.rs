@@ -1,14 +1,13 @@
 use anchor_lang::{
     prelude::*,
-    solana_program::{
-        hash,
-        sysvar::instructions::{load_instruction_at_checked, ID as IX_ID},
-    },
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
 };
 
 use crate::{
+    eip712::{hash_revoke_credential, OperationMessage},
     errors::SquirclErrorCode,
-    state::{Credential, Did, Sig},
+    events::CredentialRevoked,
+    state::{Credential, Did, NonceRegistry, Role, Sig},
     utils::get_default_revoke_credential_message,
 };
 
@@ -16,76 +15,70 @@ pub fn revoke_credential_handler(
     ctx: Context<RevokeCredential>,
     credential_id: String,
     issuer_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
 ) -> Result<()> {
     let issuer_did = &ctx.accounts.issuer_did;
     let subject_did = &ctx.accounts.subject_did;
 
     let clock = Clock::get()?;
 
-    let timestamp_1_hour_ago = clock.unix_timestamp - 3600;
+    ctx.accounts.nonce_registry.use_nonce(&issuer_sig, &clock)?;
 
-    match issuer_sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
 
-            let issue_credential_message = get_default_revoke_credential_message(
-                &credential_id,
+    let message_for = |signer, nonce| {
+        OperationMessage::typed(
+            get_default_revoke_credential_message(
                 &issuer_did.did,
-                &subject_did.did,
-                nonce,
-            );
-
-            eth_sig.verify(&controller_address_sign_ix, issue_credential_message)?;
-
-            let found_address = issuer_did
-                .eth_addresses
-                .iter()
-                .find(|address| address.address == eth_sig.get_eth_address_hex());
-
-            if !found_address.is_some() {
-                return Err(SquirclErrorCode::AddressDoesNotExistInDID.into());
-            }
-        }
-
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let issue_credential_message = get_default_revoke_credential_message(
+                signer,
                 &credential_id,
-                &issuer_did.did,
                 &subject_did.did,
                 nonce,
-            );
-
-            sol_sig.verify(&controller_address_sign_ix, issue_credential_message)?;
-
-            let found_address = issuer_did
-                .sol_addresses
-                .iter()
-                .find(|address| address.address == sol_sig.address_base58);
-
-            if !found_address.is_some() {
-                return Err(SquirclErrorCode::AddressDoesNotExistInDID.into());
-            }
-        }
-    }
+            ),
+            hash_revoke_credential(&credential_id, &issuer_did.did, &subject_did.did, nonce),
+        )
+    };
+
+    issuer_did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &issuer_sig,
+        &co_signer_sigs,
+        Role::can_assert,
+        message_for,
+    )?;
+
+    issuer_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(issuer_sig.signer(), issuer_sig.nonce()),
+    )?;
+
+    let found_issuer = issuer_did.find_valid_address(
+        &issuer_sig.chain(),
+        &issuer_sig.signer(),
+        clock.unix_timestamp,
+    )?;
+
+    require!(
+        found_issuer.role.can_assert(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    emit_cpi!(CredentialRevoked {
+        credential_id,
+        issuer: issuer_did.did.clone(),
+        subject: subject_did.did.clone(),
+        signer_chain: issuer_sig.chain(),
+        signer: issuer_sig.signer(),
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(credential_id: String)]
 pub struct RevokeCredential<'info> {
@@ -101,6 +94,14 @@ pub struct RevokeCredential<'info> {
     pub credential: Account<'info, Credential>,
     pub issuer_did: Account<'info, Did>,
     pub subject_did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(issuer_did.did.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
     pub system_program: Program<'info, System>,
     /// CHECK: we make sure the sysvar is the actual sysvar account
     #[account(address = IX_ID)]
@@ -0,0 +1,151 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::GuardiansSet,
+    state::{Did, Guardian, NonceRegistry, Recovery, Role, Sig},
+    utils::{get_default_set_guardians_message, validate_did},
+};
+
+pub fn set_guardians_ix(
+    ctx: Context<SetGuardians>,
+    guardians: Vec<Guardian>,
+    quorum: u8,
+    delay: i64,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &ctx.accounts.did;
+    let recovery = &mut ctx.accounts.recovery;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    require!(
+        !recovery.is_pending(),
+        SquirclErrorCode::RecoveryAlreadyPending
+    );
+
+    require!(
+        !guardians.is_empty()
+            && guardians.len() <= Recovery::MAX_GUARDIANS
+            && quorum >= 1
+            && quorum as usize <= guardians.len()
+            && delay >= 0,
+        SquirclErrorCode::InvalidRecoveryConfig
+    );
+
+    let canonical_guardians = guardians
+        .iter()
+        .map(Guardian::canonicalize)
+        .collect::<Result<Vec<Guardian>>>()?;
+
+    for (i, guardian) in canonical_guardians.iter().enumerate() {
+        require!(
+            !canonical_guardians[..i].contains(guardian),
+            SquirclErrorCode::InvalidRecoveryConfig
+        );
+
+        if let Guardian::Did { did: guardian_did } = guardian {
+            validate_did(guardian_did)?;
+
+            require!(
+                *guardian_did != did.did,
+                SquirclErrorCode::InvalidRecoveryConfig
+            );
+        }
+    }
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_set_guardians_message(
+            &did.did,
+            controller.clone(),
+            &guardians,
+            quorum,
+            delay,
+            controller_sig.nonce(),
+        ),
+    )?;
+
+    // guardians can take over the controller role, so only the controller can appoint them
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| {
+            get_default_set_guardians_message(&did.did, signer, &guardians, quorum, delay, nonce)
+        },
+    )?;
+
+    recovery.guardians = canonical_guardians;
+    recovery.quorum = quorum;
+    recovery.delay = delay;
+
+    emit_cpi!(GuardiansSet {
+        did: did.did.clone(),
+        quorum,
+        delay,
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct SetGuardians<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [Recovery::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = Recovery::LEN
+    )]
+    pub recovery: Account<'info, Recovery>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
@@ -0,0 +1,103 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{DidUpdated, ThresholdChanged},
+    state::{Did, NonceRegistry, Role, Sig},
+    utils::get_default_set_threshold_message,
+};
+
+pub fn set_threshold_ix(
+    ctx: Context<SetThreshold>,
+    threshold: u8,
+    setter_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts.nonce_registry.use_nonce(&setter_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    let setter = setter_sig.signer();
+
+    setter_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_set_threshold_message(&did.did, setter.clone(), threshold, setter_sig.nonce()),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&setter_sig.chain(), &setter, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.can_manage(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
+
+    // the current threshold applies, so a policy can only be loosened by as many signers as it requires
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &setter_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| get_default_set_threshold_message(&did.did, signer, threshold, nonce),
+    )?;
+
+    require!(
+        threshold >= 1 && threshold as usize <= did.manager_count(),
+        SquirclErrorCode::InvalidThreshold
+    );
+
+    did.set_threshold(clock, threshold);
+
+    emit_cpi!(ThresholdChanged {
+        did: did.did.clone(),
+        threshold,
+        signer_chain: setter_sig.chain(),
+        signer: setter,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct SetThreshold<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
@@ -0,0 +1,167 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::RecoveryStarted,
+    state::{Address, Did, Guardian, NonceRegistry, PendingRecovery, Recovery, Role, Sig},
+    utils::get_default_start_recovery_message,
+};
+
+pub fn start_recovery_ix(
+    ctx: Context<StartRecovery>,
+    new_controller_sig: Sig,
+    guardian_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &ctx.accounts.did;
+    let recovery = &mut ctx.accounts.recovery;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&new_controller_sig, &clock)?;
+
+    for guardian_sig in guardian_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(guardian_sig, &clock)?;
+    }
+
+    require!(
+        !recovery.is_pending(),
+        SquirclErrorCode::RecoveryAlreadyPending
+    );
+
+    let new_controller_chain = new_controller_sig.chain();
+    let new_controller = new_controller_sig.signer();
+
+    new_controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_start_recovery_message(
+            &did.did,
+            new_controller.clone(),
+            &new_controller_chain,
+            &new_controller,
+            new_controller_sig.nonce(),
+        ),
+    )?;
+
+    // guardian DIDs approving through one of their addresses are passed as remaining accounts,
+    // the owner check is enough as the program only ever writes a did at its own PDA
+    let guardian_dids = ctx
+        .remaining_accounts
+        .iter()
+        .map(|info| {
+            require_keys_eq!(*info.owner, crate::ID, SquirclErrorCode::NotAGuardian);
+            Did::try_deserialize(&mut &info.data.borrow()[..])
+        })
+        .collect::<Result<Vec<Did>>>()?;
+
+    let mut approved: Vec<usize> = vec![];
+
+    for guardian_sig in guardian_sigs.iter() {
+        let chain = guardian_sig.chain();
+        let signer = guardian_sig.signer();
+
+        guardian_sig.verify_at(
+            &ctx.accounts.ix_sysvar,
+            get_default_start_recovery_message(
+                &did.did,
+                signer.clone(),
+                &new_controller_chain,
+                &new_controller,
+                guardian_sig.nonce(),
+            ),
+        )?;
+
+        let guardian_index = recovery
+            .guardians
+            .iter()
+            .position(|guardian| match guardian {
+                Guardian::Address { chain: c, address } => *c == chain && *address == signer,
+                Guardian::Did { did: guardian_did } => guardian_dids.iter().any(|d| {
+                    d.did == *guardian_did
+                        && !d.is_deactivated()
+                        && matches!(
+                            d.find_valid_address(&chain, &signer, clock.unix_timestamp),
+                            Ok(a) if a.role.can_manage()
+                        )
+                }),
+            })
+            .ok_or(SquirclErrorCode::NotAGuardian)?;
+
+        require!(
+            !approved.contains(&guardian_index),
+            SquirclErrorCode::DuplicateSigner
+        );
+
+        approved.push(guardian_index);
+    }
+
+    require!(
+        approved.len() >= recovery.quorum as usize,
+        SquirclErrorCode::RecoveryQuorumNotMet
+    );
+
+    let new_controller = Address::new(
+        new_controller_chain,
+        &new_controller,
+        clock.unix_timestamp,
+        Role::Controller,
+    )?;
+
+    let executable_at = clock
+        .unix_timestamp
+        .checked_add(recovery.delay)
+        .ok_or(SquirclErrorCode::InvalidRecoveryConfig)?;
+
+    recovery.pending = Some(PendingRecovery {
+        new_controller,
+        started_at: clock.unix_timestamp,
+        executable_at,
+    });
+
+    emit_cpi!(RecoveryStarted {
+        did: did.did.clone(),
+        chain: new_controller_sig.chain(),
+        address: new_controller_sig.signer(),
+        executable_at,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct StartRecovery<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        mut,
+        seeds = [Recovery::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+    )]
+    pub recovery: Account<'info, Recovery>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
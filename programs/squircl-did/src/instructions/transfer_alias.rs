@@ -0,0 +1,121 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::AliasTransferred,
+    state::{Alias, Did, NonceRegistry, Role, Sig},
+    utils::get_default_transfer_alias_message,
+};
+
+pub fn transfer_alias_ix(
+    ctx: Context<TransferAlias>,
+    handle: String,
+    controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &ctx.accounts.did;
+    let new_did = &ctx.accounts.new_did;
+    let alias = &mut ctx.accounts.alias;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    require!(
+        alias.is_owned_by(&did.did) && alias.is_claimed(clock.unix_timestamp),
+        SquirclErrorCode::AliasNotOwned
+    );
+
+    let controller = controller_sig.signer();
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        get_default_transfer_alias_message(
+            &did.did,
+            controller.clone(),
+            &handle,
+            &new_did.did,
+            controller_sig.nonce(),
+        ),
+    )?;
+
+    let found_address =
+        did.find_valid_address(&controller_sig.chain(), &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_address.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        |signer, nonce| {
+            get_default_transfer_alias_message(&did.did, signer, &handle, &new_did.did, nonce)
+        },
+    )?;
+
+    alias.did_seed = Alias::did_seed(&new_did.did);
+    alias.claimed_at = clock.unix_timestamp;
+
+    emit_cpi!(AliasTransferred {
+        did: did.did.clone(),
+        alias: handle,
+        new_did: new_did.did.clone(),
+        signer_chain: controller_sig.chain(),
+        signer: controller,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String, handle: String, new_did_str: String)]
+pub struct TransferAlias<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        seeds = [&hash::hash(new_did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !new_did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub new_did: Account<'info, Did>,
+    #[account(
+        mut,
+        seeds = [Alias::SEED_PREFIX.as_bytes(), handle.as_bytes()],
+        bump,
+    )]
+    pub alias: Account<'info, Alias>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
@@ -0,0 +1,132 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
+};
+
+use crate::{
+    errors::SquirclErrorCode,
+    events::{ControllerTransferred, DidUpdated},
+    state::{Did, NonceRegistry, Role, Sig},
+    utils::get_default_transfer_controller_message,
+};
+
+pub fn transfer_controller_ix(
+    ctx: Context<TransferController>,
+    controller_sig: Sig,
+    new_controller_sig: Sig,
+    co_signer_sigs: Vec<Sig>,
+) -> Result<()> {
+    let did = &mut ctx.accounts.did;
+
+    let clock: Clock = Clock::get()?;
+
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&controller_sig, &clock)?;
+    ctx.accounts
+        .nonce_registry
+        .use_nonce(&new_controller_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
+
+    let controller_chain = controller_sig.chain();
+    let controller = controller_sig.signer();
+    let new_controller_chain = new_controller_sig.chain();
+    let new_controller = new_controller_sig.signer();
+
+    let message_for = |signer, nonce| {
+        get_default_transfer_controller_message(
+            &did.did,
+            signer,
+            &new_controller_chain,
+            &new_controller,
+            nonce,
+        )
+    };
+
+    did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &controller_sig,
+        &co_signer_sigs,
+        Role::can_manage,
+        message_for,
+    )?;
+
+    controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(controller.clone(), controller_sig.nonce()),
+    )?;
+
+    let found_controller =
+        did.find_valid_address(&controller_chain, &controller, clock.unix_timestamp)?;
+
+    require!(
+        found_controller.role.is_controller(),
+        SquirclErrorCode::AddressIsNotController
+    );
+
+    new_controller_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(new_controller.clone(), new_controller_sig.nonce()),
+    )?;
+
+    did.find_valid_address(&new_controller_chain, &new_controller, clock.unix_timestamp)?;
+
+    let controller_key = controller_chain.decode_address(&controller)?;
+    let new_controller_key = new_controller_chain.decode_address(&new_controller)?;
+
+    did.transfer_controller(
+        clock,
+        &controller_chain,
+        &controller_key,
+        &new_controller_chain,
+        &new_controller_key,
+    );
+
+    emit_cpi!(ControllerTransferred {
+        did: did.did.clone(),
+        chain: new_controller_chain,
+        address: new_controller,
+        signer_chain: controller_chain,
+        signer: controller,
+    });
+
+    emit_cpi!(DidUpdated {
+        did: did.did.clone(),
+        version: did.version,
+        state: did.state(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(did_str: String)]
+pub struct TransferController<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&hash::hash(did_str.as_bytes()).to_bytes()],
+        bump,
+        constraint = !did.is_deactivated() @SquirclErrorCode::DidDeactivated,
+    )]
+    pub did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(did_str.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: we make sure the sysvar is the actual sysvar account
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
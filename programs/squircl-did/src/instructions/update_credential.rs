@@ -1,115 +1,106 @@
 use anchor_lang::{
     prelude::*,
-    solana_program::{
-        hash,
-        sysvar::instructions::{load_instruction_at_checked, ID as IX_ID},
-    },
+    solana_program::{hash, sysvar::instructions::ID as IX_ID},
 };
 
 use crate::{
+    eip712::{hash_update_credential, OperationMessage},
     errors::SquirclErrorCode,
-    state::{Credential, Did, Sig},
+    events::CredentialUpdated,
+    state::{Credential, CredentialParams, Did, NonceRegistry, Role, Sig},
     utils::get_default_update_credential_message,
 };
 
 pub fn update_credential_handler(
     ctx: Context<UpdateCredential>,
     credential_id: String,
+    params: CredentialParams,
     issuer_sig: Sig,
-    uri: String,
-    credential_hash: String,
-    is_mutable: bool,
-    is_revokable: bool,
-    expires_at: Option<i64>,
+    co_signer_sigs: Vec<Sig>,
 ) -> Result<()> {
     let clock: Clock = Clock::get()?;
 
-    let timestamp_1_hour_ago = clock.unix_timestamp - 3600;
+    ctx.accounts.nonce_registry.use_nonce(&issuer_sig, &clock)?;
+
+    for co_signer_sig in co_signer_sigs.iter() {
+        ctx.accounts
+            .nonce_registry
+            .use_nonce(co_signer_sig, &clock)?;
+    }
 
     let credential = &mut ctx.accounts.credential;
     let issuer_did = &ctx.accounts.issuer_did;
     let subject_did = &ctx.accounts.subject_did;
 
     require!(
-        expires_at == None || expires_at.unwrap() > clock.unix_timestamp,
+        params.expires_at == None || params.expires_at.unwrap() > clock.unix_timestamp,
         SquirclErrorCode::ExpiryCannotBeInThePast
     );
 
-    match issuer_sig {
-        Sig::Eth {
-            eth_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let issue_credential_message = get_default_update_credential_message(
-                &credential_id,
+    let message_for = |signer, nonce| {
+        OperationMessage::typed(
+            get_default_update_credential_message(
                 &issuer_did.did,
+                signer,
+                &credential_id,
                 &subject_did.did,
-                &uri,
-                &credential_hash,
+                &params,
                 nonce,
-            );
-
-            eth_sig.verify(&controller_address_sign_ix, issue_credential_message)?;
-
-            let found_address = issuer_did
-                .eth_addresses
-                .iter()
-                .find(|address| address.address == eth_sig.get_eth_address_hex());
-
-            if !found_address.is_some() {
-                return Err(SquirclErrorCode::AddressDoesNotExistInDID.into());
-            }
-        }
-
-        Sig::Sol {
-            sol_sig,
-            index,
-            nonce,
-        } => {
-            require!(nonce > timestamp_1_hour_ago, SquirclErrorCode::NonceExpired);
-
-            let controller_address_sign_ix =
-                load_instruction_at_checked(index.try_into().unwrap(), &ctx.accounts.ix_sysvar)?;
-
-            let issue_credential_message = get_default_update_credential_message(
+            ),
+            hash_update_credential(
                 &credential_id,
                 &issuer_did.did,
                 &subject_did.did,
-                &uri,
-                &credential_hash,
+                &params,
                 nonce,
-            );
+            ),
+        )
+    };
+
+    issuer_did.check_threshold(
+        &ctx.accounts.ix_sysvar,
+        &issuer_sig,
+        &co_signer_sigs,
+        Role::can_assert,
+        message_for,
+    )?;
+
+    issuer_sig.verify_at(
+        &ctx.accounts.ix_sysvar,
+        message_for(issuer_sig.signer(), issuer_sig.nonce()),
+    )?;
+
+    let found_issuer = issuer_did.find_valid_address(
+        &issuer_sig.chain(),
+        &issuer_sig.signer(),
+        clock.unix_timestamp,
+    )?;
 
-            sol_sig.verify(&controller_address_sign_ix, issue_credential_message)?;
+    require!(
+        found_issuer.role.can_assert(),
+        SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+    );
 
-            let found_address = issuer_did
-                .sol_addresses
-                .iter()
-                .find(|address| address.address == sol_sig.address_base58);
+    credential.uri = params.uri;
+    credential.credential_hash = params.credential_hash;
+    credential.expires_at = params.expires_at;
+    credential.is_mutable = params.is_mutable;
+    credential.is_revokable = params.is_revokable;
 
-            if !found_address.is_some() {
-                return Err(SquirclErrorCode::AddressDoesNotExistInDID.into());
-            }
-        }
-    }
-
-    credential.uri = uri;
-    credential.credential_hash = credential_hash;
-    credential.expires_at = expires_at;
-    credential.is_mutable = is_mutable;
-    credential.is_revokable = is_revokable;
+    emit_cpi!(CredentialUpdated {
+        credential_id,
+        issuer: issuer_did.did.clone(),
+        subject: subject_did.did.clone(),
+        signer_chain: issuer_sig.chain(),
+        signer: issuer_sig.signer(),
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(credential_id: String, uri: String, credential_hash: String)]
+#[instruction(credential_id: String, params: CredentialParams)]
 pub struct UpdateCredential<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -117,14 +108,24 @@ pub struct UpdateCredential<'info> {
         mut,
         seeds = [Credential::SEED_PREFIX.as_bytes(), &hash::hash(issuer_did.did.as_bytes()).to_bytes(), &hash::hash(subject_did.did.as_bytes()).to_bytes(), credential_id.as_bytes()],
         bump,
-        realloc = Credential::LEN_BASE + (4 + (4 * credential_id.len())) + (4 + (4 * uri.len())) + (4 + (4 * credential_hash.len())),
+        realloc = Credential::LEN_BASE + (4 + (4 * credential_id.len())) + (4 + (4 * params.uri.len())) + (4 + (4 * params.credential_hash.len())),
         realloc::payer = payer,
         realloc::zero = false,
         constraint = credential.is_mutable @SquirclErrorCode::CredentialIsNotMutable,
     )]
     pub credential: Account<'info, Credential>,
+    #[account(constraint = !issuer_did.is_deactivated() @SquirclErrorCode::DidDeactivated)]
     pub issuer_did: Account<'info, Did>,
+    #[account(constraint = !subject_did.is_deactivated() @SquirclErrorCode::DidDeactivated)]
     pub subject_did: Account<'info, Did>,
+    #[account(
+        init_if_needed,
+        seeds = [NonceRegistry::SEED_PREFIX.as_bytes(), &hash::hash(issuer_did.did.as_bytes()).to_bytes()],
+        payer = payer,
+        bump,
+        space = NonceRegistry::LEN
+    )]
+    pub nonce_registry: Account<'info, NonceRegistry>,
     pub system_program: Program<'info, System>,
     /// CHECK: we make sure the sysvar is the actual sysvar account
     #[account(address = IX_ID)]
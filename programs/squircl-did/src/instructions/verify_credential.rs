@@ -0,0 +1,24 @@
+use anchor_lang::{prelude::*, solana_program::hash};
+
+use crate::state::{Credential, CredentialStatus, Did};
+
+pub fn verify_credential_handler(ctx: Context<VerifyCredential>) -> Result<CredentialStatus> {
+    let clock: Clock = Clock::get()?;
+
+    Ok(ctx
+        .accounts
+        .credential
+        .status(&ctx.accounts.issuer_did, &ctx.accounts.subject_did, &clock))
+}
+
+#[derive(Accounts)]
+#[instruction(credential_id: String)]
+pub struct VerifyCredential<'info> {
+    #[account(
+        seeds = [Credential::SEED_PREFIX.as_bytes(), &hash::hash(issuer_did.did.as_bytes()).to_bytes(), &hash::hash(subject_did.did.as_bytes()).to_bytes(), credential_id.as_bytes()],
+        bump,
+    )]
+    pub credential: Account<'info, Credential>,
+    pub issuer_did: Account<'info, Did>,
+    pub subject_did: Account<'info, Did>,
+}
@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
 
 pub mod constants;
+pub mod eip712;
 pub mod errors;
+pub mod events;
 pub mod instructions;
+#[cfg(feature = "resolver")]
+pub mod resolver;
 pub mod state;
 pub mod utils;
 
@@ -25,8 +29,18 @@ pub mod squircl_did {
         _did_str: String,
         new_address_sig: Sig,
         controller_sig: Sig,
+        role: Role,
+        validity: AddressValidity,
+        co_signer_sigs: Vec<Sig>,
     ) -> Result<()> {
-        add_address_ix(ctx, new_address_sig, controller_sig)
+        add_address_ix(
+            ctx,
+            new_address_sig,
+            controller_sig,
+            role,
+            validity,
+            co_signer_sigs,
+        )
     }
 
     pub fn remove_address(
@@ -35,59 +49,240 @@ pub mod squircl_did {
         address_chain: Chain,
         address: String,
         remover_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        remove_address_ix(ctx, address_chain, address, remover_sig, co_signer_sigs)
+    }
+
+    pub fn add_service(
+        ctx: Context<AddService>,
+        _did_str: String,
+        service: Service,
+        signer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        add_service_ix(ctx, service, signer_sig, co_signer_sigs)
+    }
+
+    pub fn remove_service(
+        ctx: Context<RemoveService>,
+        _did_str: String,
+        service_id: String,
+        signer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        remove_service_ix(ctx, service_id, signer_sig, co_signer_sigs)
+    }
+
+    pub fn change_role(
+        ctx: Context<ChangeRole>,
+        _did_str: String,
+        address_chain: Chain,
+        address: String,
+        role: Role,
+        changer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        change_role_ix(
+            ctx,
+            address_chain,
+            address,
+            role,
+            changer_sig,
+            co_signer_sigs,
+        )
+    }
+
+    pub fn set_threshold(
+        ctx: Context<SetThreshold>,
+        _did_str: String,
+        threshold: u8,
+        setter_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        set_threshold_ix(ctx, threshold, setter_sig, co_signer_sigs)
+    }
+
+    pub fn transfer_controller(
+        ctx: Context<TransferController>,
+        _did_str: String,
+        controller_sig: Sig,
+        new_controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
     ) -> Result<()> {
-        remove_address_ix(ctx, address_chain, address, remover_sig)
+        transfer_controller_ix(ctx, controller_sig, new_controller_sig, co_signer_sigs)
+    }
+
+    pub fn set_guardians(
+        ctx: Context<SetGuardians>,
+        _did_str: String,
+        guardians: Vec<Guardian>,
+        quorum: u8,
+        delay: i64,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        set_guardians_ix(
+            ctx,
+            guardians,
+            quorum,
+            delay,
+            controller_sig,
+            co_signer_sigs,
+        )
+    }
+
+    pub fn start_recovery(
+        ctx: Context<StartRecovery>,
+        _did_str: String,
+        new_controller_sig: Sig,
+        guardian_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        start_recovery_ix(ctx, new_controller_sig, guardian_sigs)
+    }
+
+    pub fn cancel_recovery(
+        ctx: Context<CancelRecovery>,
+        _did_str: String,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        cancel_recovery_ix(ctx, controller_sig, co_signer_sigs)
+    }
+
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>, _did_str: String) -> Result<()> {
+        execute_recovery_ix(ctx)
+    }
+
+    pub fn deactivate_did<'info>(
+        ctx: Context<'_, '_, '_, 'info, DeactivateDID<'info>>,
+        _did_str: String,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        deactivate_did_ix(ctx, controller_sig, co_signer_sigs)
+    }
+
+    pub fn migrate_did(
+        ctx: Context<MigrateDID>,
+        _did_str: String,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        migrate_did_ix(ctx, controller_sig, co_signer_sigs)
+    }
+
+    pub fn prune_addresses<'info>(
+        ctx: Context<'_, '_, '_, 'info, PruneAddresses<'info>>,
+        _did_str: String,
+    ) -> Result<()> {
+        prune_addresses_ix(ctx)
+    }
+
+    pub fn create_did_zero_copy(
+        ctx: Context<CreateDIDZeroCopy>,
+        did_str: String,
+        sig: Sig,
+    ) -> Result<()> {
+        create_did_zero_copy_ix(ctx, did_str, sig)
+    }
+
+    pub fn add_address_zero_copy(
+        ctx: Context<AddAddressZeroCopy>,
+        _did_str: String,
+        new_address_sig: Sig,
+        controller_sig: Sig,
+        role: Role,
+    ) -> Result<()> {
+        add_address_zero_copy_ix(ctx, new_address_sig, controller_sig, role)
+    }
+
+    pub fn remove_address_zero_copy(
+        ctx: Context<RemoveAddressZeroCopy>,
+        _did_str: String,
+        address_chain: Chain,
+        address: String,
+        remover_sig: Sig,
+    ) -> Result<()> {
+        remove_address_zero_copy_ix(ctx, address_chain, address, remover_sig)
+    }
+
+    pub fn claim_alias(
+        ctx: Context<ClaimAlias>,
+        _did_str: String,
+        handle: String,
+        expires_at: Option<i64>,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        claim_alias_ix(ctx, handle, expires_at, controller_sig, co_signer_sigs)
+    }
+
+    pub fn transfer_alias(
+        ctx: Context<TransferAlias>,
+        _did_str: String,
+        handle: String,
+        _new_did_str: String,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        transfer_alias_ix(ctx, handle, controller_sig, co_signer_sigs)
+    }
+
+    pub fn release_alias(
+        ctx: Context<ReleaseAlias>,
+        _did_str: String,
+        handle: String,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        release_alias_ix(ctx, handle, controller_sig, co_signer_sigs)
+    }
+
+    pub fn set_alias_expiry(
+        ctx: Context<SetAliasExpiry>,
+        _did_str: String,
+        handle: String,
+        expires_at: Option<i64>,
+        controller_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
+    ) -> Result<()> {
+        set_alias_expiry_ix(ctx, handle, expires_at, controller_sig, co_signer_sigs)
     }
 
     pub fn issue_credential(
         ctx: Context<IssueCredential>,
         credential_id: String,
-        uri: String,
-        credential_hash: String,
-        is_mutable: bool,
-        is_revokable: bool,
-        expires_at: Option<i64>,
+        params: CredentialParams,
         issuer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
     ) -> Result<()> {
-        issue_credential_handler(
-            ctx,
-            credential_id,
-            issuer_sig,
-            uri,
-            credential_hash,
-            is_mutable,
-            is_revokable,
-            expires_at,
-        )
+        issue_credential_handler(ctx, credential_id, params, issuer_sig, co_signer_sigs)
     }
 
     pub fn update_credential(
         ctx: Context<UpdateCredential>,
         credential_id: String,
-        uri: String,
-        credential_hash: String,
-        is_mutable: bool,
-        is_revokable: bool,
-        expires_at: Option<i64>,
+        params: CredentialParams,
         issuer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
     ) -> Result<()> {
-        update_credential_handler(
-            ctx,
-            credential_id,
-            issuer_sig,
-            uri,
-            credential_hash,
-            is_mutable,
-            is_revokable,
-            expires_at,
-        )
+        update_credential_handler(ctx, credential_id, params, issuer_sig, co_signer_sigs)
     }
 
     pub fn revoke_credential(
         ctx: Context<RevokeCredential>,
         credential_id: String,
         issuer_sig: Sig,
+        co_signer_sigs: Vec<Sig>,
     ) -> Result<()> {
-        revoke_credential_handler(ctx, credential_id, issuer_sig)
+        revoke_credential_handler(ctx, credential_id, issuer_sig, co_signer_sigs)
+    }
+
+    pub fn verify_credential(
+        ctx: Context<VerifyCredential>,
+        _credential_id: String,
+    ) -> Result<CredentialStatus> {
+        verify_credential_handler(ctx)
     }
 }
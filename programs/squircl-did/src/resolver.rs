@@ -0,0 +1,474 @@
+// Off-chain resolver turning a `Did` account into a W3C DID Core document
+// https://www.w3.org/TR/did-core/
+
+use serde::Serialize;
+
+use crate::{
+    constants::DID_PREFIX,
+    state::{Address, Alias, Chain, Did, Role, Service},
+};
+
+pub const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+pub const SECP256K1_RECOVERY_CONTEXT: &str =
+    "https://w3id.org/security/suites/secp256k1recovery-2020/v2";
+pub const ED25519_CONTEXT: &str = "https://w3id.org/security/suites/ed25519-2020/v1";
+pub const MULTIKEY_CONTEXT: &str = "https://w3id.org/security/multikey/v1";
+
+// CAIP-2 chain ids used in the CAIP-10 `blockchainAccountId` of EVM and Bitcoin addresses
+pub const EVM_CAIP2_CHAIN_ID: &str = "eip155:1";
+pub const BTC_CAIP2_CHAIN_ID: &str = "bip122:000000000019d6689c085ae165831e93";
+
+// scheme of the `alsoKnownAs` URIs of aliases
+pub const ALIAS_URI_PREFIX: &str = "squircl:";
+
+// multicodec prefixes of ed25519 and compressed p256 public keys
+const ED25519_PUB_MULTICODEC: [u8; 2] = [0xed, 0x01];
+const P256_PUB_MULTICODEC: [u8; 2] = [0x80, 0x24];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub controller: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blockchain_account_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEndpoint {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub service_endpoint: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    pub controller: String,
+    pub verification_method: Vec<VerificationMethod>,
+    pub authentication: Vec<String>,
+    pub assertion_method: Vec<String>,
+    pub capability_invocation: Vec<String>,
+    pub capability_delegation: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_known_as: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub service: Vec<ServiceEndpoint>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocumentMetadata {
+    pub created: String,
+    pub updated: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deactivated: bool,
+    pub version_id: String,
+    // only set when resolving a version that has since been replaced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_update: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_version_id: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionResult {
+    pub did_document: DidDocument,
+    pub did_document_metadata: DidDocumentMetadata,
+}
+
+pub fn did_uri(did: &Did) -> String {
+    // DIDs created before did_str validation may lack the method prefix
+    if did.did.starts_with(DID_PREFIX) {
+        did.did.clone()
+    } else {
+        format!("{}{}", DID_PREFIX, did.did)
+    }
+}
+
+pub fn verification_method(did_uri: &str, address: &Address) -> VerificationMethod {
+    let address_str = address.get_address_string();
+
+    match address.chain {
+        Chain::EVM => VerificationMethod {
+            id: format!("{}#{}", did_uri, address_str),
+            type_: "EcdsaSecp256k1RecoveryMethod2020".to_string(),
+            controller: did_uri.to_string(),
+            blockchain_account_id: Some(format!("{}:{}", EVM_CAIP2_CHAIN_ID, address_str)),
+            public_key_multibase: None,
+        },
+        Chain::SOL => VerificationMethod {
+            id: format!("{}#{}", did_uri, address_str),
+            type_: "Ed25519VerificationKey2020".to_string(),
+            controller: did_uri.to_string(),
+            blockchain_account_id: None,
+            public_key_multibase: Some(format!(
+                "z{}",
+                bs58::encode([ED25519_PUB_MULTICODEC.as_slice(), &address.address].concat())
+                    .into_string()
+            )),
+        },
+        Chain::BTC => VerificationMethod {
+            id: format!("{}#{}", did_uri, address_str),
+            type_: "EcdsaSecp256k1RecoveryMethod2020".to_string(),
+            controller: did_uri.to_string(),
+            blockchain_account_id: Some(format!("{}:{}", BTC_CAIP2_CHAIN_ID, address_str)),
+            public_key_multibase: None,
+        },
+        Chain::PASSKEY => VerificationMethod {
+            id: format!("{}#{}", did_uri, address_str),
+            type_: "Multikey".to_string(),
+            controller: did_uri.to_string(),
+            blockchain_account_id: None,
+            public_key_multibase: Some(format!(
+                "z{}",
+                bs58::encode([P256_PUB_MULTICODEC.as_slice(), &address.address].concat())
+                    .into_string()
+            )),
+        },
+    }
+}
+
+pub fn service_endpoint(did_uri: &str, service: &Service) -> ServiceEndpoint {
+    ServiceEndpoint {
+        id: format!("{}#{}", did_uri, service.id),
+        type_: service.service_type.clone(),
+        service_endpoint: service.endpoint.clone(),
+    }
+}
+
+/// Resolve a DID as it stands at `now`, addresses outside their validity window are left out
+pub fn resolve(did: &Did, now: i64) -> ResolutionResult {
+    let id = did_uri(did);
+
+    let mut document = DidDocument {
+        context: vec![
+            DID_CONTEXT.to_string(),
+            SECP256K1_RECOVERY_CONTEXT.to_string(),
+            ED25519_CONTEXT.to_string(),
+            MULTIKEY_CONTEXT.to_string(),
+        ],
+        id: id.clone(),
+        controller: id.clone(),
+        verification_method: vec![],
+        authentication: vec![],
+        assertion_method: vec![],
+        capability_invocation: vec![],
+        capability_delegation: vec![],
+        also_known_as: vec![],
+        service: did
+            .services
+            .iter()
+            .map(|service| service_endpoint(&id, service))
+            .collect(),
+    };
+
+    for address in did.all_addresses().filter(|a| a.is_valid_at(now)) {
+        let method = verification_method(&id, address);
+
+        match address.role {
+            Role::Controller => {
+                document.authentication.push(method.id.clone());
+                document.assertion_method.push(method.id.clone());
+                document.capability_invocation.push(method.id.clone());
+                document.capability_delegation.push(method.id.clone());
+            }
+            Role::Admin => {
+                document.authentication.push(method.id.clone());
+                document.assertion_method.push(method.id.clone());
+                document.capability_invocation.push(method.id.clone());
+            }
+            Role::Assertion => {
+                document.assertion_method.push(method.id.clone());
+            }
+            Role::Authentication => {
+                document.authentication.push(method.id.clone());
+            }
+        }
+
+        document.verification_method.push(method);
+    }
+
+    ResolutionResult {
+        did_document: document,
+        did_document_metadata: DidDocumentMetadata {
+            created: to_xml_datetime(did.created_at),
+            updated: to_xml_datetime(did.updated_at),
+            deactivated: did.is_deactivated(),
+            version_id: did.version.to_string(),
+            next_update: None,
+            next_version_id: None,
+        },
+    }
+}
+
+pub fn alias_uri(alias: &Alias) -> String {
+    format!("{}{}", ALIAS_URI_PREFIX, alias.handle)
+}
+
+/// Resolve a DID along with the aliases fetched for it, the ones that point at it and haven't
+/// expired by `now` are listed as `alsoKnownAs`
+pub fn resolve_with_aliases(did: &Did, aliases: &[Alias], now: i64) -> ResolutionResult {
+    let mut result = resolve(did, now);
+
+    result.did_document.also_known_as = aliases
+        .iter()
+        .filter(|alias| alias.is_owned_by(&did.did) && alias.is_claimed(now))
+        .map(alias_uri)
+        .collect();
+
+    result
+}
+
+/// The `versionId` or `versionTime` DID parameter of a resolution
+#[derive(Clone, Copy, Debug)]
+pub enum VersionQuery {
+    Id(u64),
+    Time(i64),
+}
+
+/// Check that `history` holds every version of a DID, from its creation or migration, with each
+/// version committing to the state of the one before. The last version should be the DID account
+/// as it currently is, the others are the states emitted in its `DidUpdated` events
+pub fn verify_history(history: &[Did]) -> bool {
+    history
+        .first()
+        .is_some_and(|first| first.version == 1 && first.prev_state_hash == [0; 32])
+        && history.windows(2).all(|pair| {
+            pair[1].did == pair[0].did
+                && pair[1].version == pair[0].version + 1
+                && pair[1].prev_state_hash == pair[0].state_hash()
+        })
+}
+
+/// Position in a verified `history` of the version the query asks for, a time resolves to the
+/// version that was current at that time
+pub fn find_version(history: &[Did], query: VersionQuery) -> Option<usize> {
+    match query {
+        VersionQuery::Id(version) => history.iter().position(|did| did.version == version),
+        VersionQuery::Time(time) => history.iter().rposition(|did| did.updated_at <= time),
+    }
+}
+
+/// Resolve a past version of a DID out of its verified `history`. A version asked for by time
+/// lists the addresses valid at that time, one asked for by id the ones valid at `now`
+pub fn resolve_version(history: &[Did], query: VersionQuery, now: i64) -> Option<ResolutionResult> {
+    let index = find_version(history, query)?;
+    let at = match query {
+        VersionQuery::Id(_) => now,
+        VersionQuery::Time(time) => time,
+    };
+    let mut result = resolve(&history[index], at);
+
+    if let Some(next) = history.get(index + 1) {
+        result.did_document_metadata.next_update = Some(to_xml_datetime(next.updated_at));
+        result.did_document_metadata.next_version_id = Some(next.version.to_string());
+    }
+
+    Some(result)
+}
+
+/// Whether `address` was in the DID and within its validity window at `time`, such as when a
+/// credential signed by it was issued
+pub fn was_address_valid_at(history: &[Did], chain: &Chain, address: &str, time: i64) -> bool {
+    find_version(history, VersionQuery::Time(time)).is_some_and(|index| {
+        let did = &history[index];
+
+        !did.is_deactivated() && did.find_valid_address(chain, address, time).is_ok()
+    })
+}
+
+pub fn resolve_to_json(did: &Did, now: i64) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&resolve(did, now))
+}
+
+/// Format a unix timestamp as an XML Schema `dateTime` in UTC, as DID Core metadata expects
+pub fn to_xml_datetime(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86400);
+    let seconds_of_day = timestamp.rem_euclid(86400);
+
+    // civil_from_days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::prelude::Clock;
+
+    use super::*;
+
+    const DID: &str = "did:squircl:resolver-test";
+    const CONTROLLER: &str = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+
+    fn clock_at(unix_timestamp: i64) -> Clock {
+        Clock {
+            unix_timestamp,
+            ..Clock::default()
+        }
+    }
+
+    fn address(chain: Chain, key: Vec<u8>, role: Role) -> Address {
+        Address {
+            address: key,
+            added_at: 0,
+            chain,
+            role,
+            valid_from: None,
+            valid_until: None,
+        }
+    }
+
+    // every version of a did created at `created_at` and given a service at each of `updates`
+    fn history(created_at: i64, updates: &[i64]) -> Vec<Did> {
+        let controller =
+            Address::new(Chain::EVM, CONTROLLER, created_at, Role::Controller).unwrap();
+        let mut did = Did::new(DID.to_string(), clock_at(created_at), controller);
+        let mut history = vec![did.clone()];
+
+        for (i, updated_at) in updates.iter().enumerate() {
+            did.add_service(
+                clock_at(*updated_at),
+                Service {
+                    id: format!("hub-{}", i),
+                    service_type: "CredentialHub".to_string(),
+                    endpoint: "https://hub.squircl.xyz".to_string(),
+                },
+            );
+            history.push(did.clone());
+        }
+
+        history
+    }
+
+    // multicodec prefix and key of a `publicKeyMultibase`
+    fn decode_multibase(multibase: &str) -> Vec<u8> {
+        bs58::decode(multibase.strip_prefix('z').unwrap())
+            .into_vec()
+            .unwrap()
+    }
+
+    #[test]
+    fn xml_datetime_of_the_epoch() {
+        assert_eq!(to_xml_datetime(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn xml_datetime_of_a_leap_day() {
+        assert_eq!(to_xml_datetime(951827445), "2000-02-29T12:30:45Z");
+        assert_eq!(to_xml_datetime(-310521600), "1960-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn xml_datetime_before_the_epoch() {
+        assert_eq!(to_xml_datetime(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn verifies_a_hash_chained_history() {
+        assert!(verify_history(&history(100, &[200, 300])));
+    }
+
+    #[test]
+    fn rejects_a_broken_hash_chain() {
+        let mut history = history(100, &[200, 300]);
+
+        // the second version no longer matches what the third one committed to
+        history[1].services[0].endpoint = "https://evil.example".to_string();
+
+        assert!(!verify_history(&history));
+    }
+
+    #[test]
+    fn rejects_a_history_with_a_missing_version() {
+        let mut history = history(100, &[200, 300]);
+
+        history.remove(1);
+
+        assert!(!verify_history(&history));
+        assert!(!verify_history(&[]));
+    }
+
+    #[test]
+    fn resolves_the_version_current_at_a_time_between_versions() {
+        let history = history(100, &[200, 300]);
+
+        assert_eq!(find_version(&history, VersionQuery::Time(50)), None);
+        assert_eq!(find_version(&history, VersionQuery::Time(250)), Some(1));
+        assert_eq!(find_version(&history, VersionQuery::Time(300)), Some(2));
+
+        let metadata = resolve_version(&history, VersionQuery::Time(250), 1000)
+            .unwrap()
+            .did_document_metadata;
+
+        assert_eq!(metadata.version_id, "2");
+        assert_eq!(metadata.updated, to_xml_datetime(200));
+        assert_eq!(metadata.next_version_id.as_deref(), Some("3"));
+        assert_eq!(metadata.next_update, Some(to_xml_datetime(300)));
+    }
+
+    #[test]
+    fn prefixes_ed25519_keys_with_their_multicodec() {
+        let key = vec![7; 32];
+        let method = verification_method(DID, &address(Chain::SOL, key.clone(), Role::Admin));
+        let multibase = method.public_key_multibase.unwrap();
+
+        assert!(multibase.starts_with("z6Mk"));
+        assert_eq!(
+            decode_multibase(&multibase),
+            [ED25519_PUB_MULTICODEC.as_slice(), &key].concat()
+        );
+    }
+
+    #[test]
+    fn prefixes_p256_keys_with_their_multicodec() {
+        let key = [vec![2], vec![7; 32]].concat();
+        let method = verification_method(DID, &address(Chain::PASSKEY, key.clone(), Role::Admin));
+        let multibase = method.public_key_multibase.unwrap();
+
+        assert!(multibase.starts_with("zDn"));
+        assert_eq!(
+            decode_multibase(&multibase),
+            [P256_PUB_MULTICODEC.as_slice(), &key].concat()
+        );
+    }
+
+    #[test]
+    fn leaves_out_addresses_outside_their_validity_window() {
+        let mut did = history(100, &[]).remove(0);
+        let mut expired = address(Chain::SOL, vec![7; 32], Role::Admin);
+
+        expired.valid_until = Some(200);
+        did.sol_addresses.push(expired);
+
+        assert_eq!(resolve(&did, 150).did_document.verification_method.len(), 2);
+        assert_eq!(resolve(&did, 200).did_document.verification_method.len(), 1);
+    }
+}
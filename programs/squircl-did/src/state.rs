@@ -1,4 +1,12 @@
+pub mod address_lookup;
+pub mod alias;
 pub mod credential;
 pub mod did;
+pub mod nonce_registry;
+pub mod recovery;
+pub mod zero_copy_did;
 
-pub use {credential::*, did::*};
+pub use {
+    address_lookup::*, alias::*, credential::*, did::*, nonce_registry::*, recovery::*,
+    zero_copy_did::*,
+};
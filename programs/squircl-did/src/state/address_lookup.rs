@@ -0,0 +1,161 @@
+use anchor_lang::{prelude::*, solana_program::hash, system_program};
+
+use crate::{
+    constants::{DISCRIMINATOR_LENGTH, STRING_LENGTH_PREFIX, VEC_LENGTH_PREFIX},
+    errors::SquirclErrorCode,
+    state::{Address, Chain},
+};
+
+/// Reverse index from an address to the DIDs it belongs to.
+///
+/// Lives at the PDA of `SEED_PREFIX`, the chain and the hash of the raw address key, so a wallet
+/// finds its DIDs with a single fetch. It is kept up to date by `create_did`, `add_address`,
+/// `remove_address`, `prune_addresses`, `execute_recovery` and `deactivate_did`, and closed once
+/// its last DID is removed. With the `one-did-per-address` feature an address can only belong to a single DID
+#[account]
+pub struct AddressLookup {
+    pub dids: Vec<String>,
+}
+
+impl AddressLookup {
+    pub const SEED_PREFIX: &'static str = "address_lookup";
+
+    pub const LEN_WITHOUT_DIDS: usize = DISCRIMINATOR_LENGTH + VEC_LENGTH_PREFIX;
+
+    pub fn len_with(did: &str) -> usize {
+        Self::LEN_WITHOUT_DIDS + STRING_LENGTH_PREFIX + did.len()
+    }
+
+    pub fn space(&self) -> usize {
+        Self::LEN_WITHOUT_DIDS
+            + self
+                .dids
+                .iter()
+                .map(|did| STRING_LENGTH_PREFIX + did.len())
+                .sum::<usize>()
+    }
+
+    // seeds are at most 32 bytes and passkey keys are 33, so the key is hashed
+    pub fn address_seed(chain: &Chain, address: &str) -> [u8; 32] {
+        // an undecodable address still gets a seed, the instruction rejects it later on
+        let key = chain
+            .decode_address(address)
+            .unwrap_or_else(|_| address.as_bytes().to_vec());
+
+        hash::hash(&key).to_bytes()
+    }
+
+    // address of the lookup of a raw address key
+    pub fn address_for(chain: &Chain, key: &[u8]) -> Pubkey {
+        let (address, _) = Pubkey::find_program_address(
+            &[
+                Self::SEED_PREFIX.as_bytes(),
+                &[chain.clone() as u8],
+                &hash::hash(key).to_bytes(),
+            ],
+            &crate::ID,
+        );
+
+        address
+    }
+
+    // chain and hashed key seeds of the lookup of a stored address. Without an address the seeds
+    // of an empty key are returned, the instruction then fails on the missing address
+    pub fn seeds_of(address: Option<&Address>) -> ([u8; 1], [u8; 32]) {
+        match address {
+            Some(a) => ([a.chain.clone() as u8], hash::hash(&a.address).to_bytes()),
+            None => ([0], hash::hash(&[]).to_bytes()),
+        }
+    }
+
+    pub fn add_did(&mut self, did: &str) -> Result<()> {
+        #[cfg(feature = "one-did-per-address")]
+        require!(
+            self.dids.iter().all(|d| d == did),
+            SquirclErrorCode::AddressInAnotherDid
+        );
+
+        if !self.dids.iter().any(|d| d == did) {
+            self.dids.push(did.to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_did(&mut self, did: &str) {
+        self.dids.retain(|d| d != did);
+    }
+
+    /// Removes `did` from the lookups of `addresses`, which must all be among `accounts`, and
+    /// closes the ones left without DIDs. Returns the keys of the lookups
+    pub fn remove_did_from_all<'info>(
+        did: &str,
+        addresses: &[Address],
+        accounts: &[AccountInfo<'info>],
+        payer: &Signer<'info>,
+        system_program: &Program<'info, System>,
+    ) -> Result<Vec<Pubkey>> {
+        let mut lookup_keys = Vec::with_capacity(addresses.len());
+
+        for address in addresses {
+            let lookup_key = Self::address_for(&address.chain, &address.address);
+
+            let lookup_info = accounts
+                .iter()
+                .find(|a| a.key() == lookup_key)
+                .ok_or(SquirclErrorCode::MissingAddressLookup)?;
+
+            lookup_keys.push(lookup_key);
+
+            // addresses added before lookups existed have none, there is nothing to remove then
+            if lookup_info.owner != &crate::ID {
+                continue;
+            }
+
+            let mut lookup = Account::<Self>::try_from(lookup_info)?;
+
+            lookup.remove_did(did);
+
+            if lookup.dids.is_empty() {
+                lookup.close(payer.to_account_info())?;
+            } else {
+                Self::fit(&lookup, payer, system_program)?;
+                lookup.exit(&crate::ID)?;
+            }
+        }
+
+        Ok(lookup_keys)
+    }
+
+    /// Resizes the lookup to fit its DIDs, the payer covers any extra rent and gets back the excess
+    pub fn fit<'info>(
+        lookup: &Account<'info, Self>,
+        payer: &Signer<'info>,
+        system_program: &Program<'info, System>,
+    ) -> Result<()> {
+        let lookup_info = lookup.to_account_info();
+        let len = lookup.space();
+        let rent_exempt = Rent::get()?.minimum_balance(len);
+        let lamports = lookup_info.lamports();
+
+        if rent_exempt > lamports {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: lookup_info.clone(),
+                    },
+                ),
+                rent_exempt - lamports,
+            )?;
+        } else {
+            **lookup_info.try_borrow_mut_lamports()? -= lamports - rent_exempt;
+            **payer.try_borrow_mut_lamports()? += lamports - rent_exempt;
+        }
+
+        lookup_info.realloc(len, false)?;
+
+        Ok(())
+    }
+}
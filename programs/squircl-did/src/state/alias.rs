@@ -0,0 +1,48 @@
+use anchor_lang::{prelude::*, solana_program::hash};
+
+use crate::constants::{
+    ALIAS_MAX_CHARS, DISCRIMINATOR_LENGTH, I64_LENGTH, PUBLIC_KEY_LENGTH, STRING_LENGTH_PREFIX,
+    U8_LENGTH,
+};
+
+/// A unique human-readable handle of a DID.
+///
+/// Lives at the PDA of `SEED_PREFIX` and the handle, and points at the DID through the
+/// `hash(did_str)` seed of its PDA. The controller of the DID can transfer or release it, or
+/// give it an expiry after which anyone can claim it again
+#[account]
+pub struct Alias {
+    pub handle: String,
+    pub did_seed: [u8; 32],
+    pub claimed_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl Alias {
+    pub const SEED_PREFIX: &'static str = "alias";
+
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + STRING_LENGTH_PREFIX
+        + ALIAS_MAX_CHARS
+        + PUBLIC_KEY_LENGTH // did_seed
+        + I64_LENGTH // claimed_at
+        + U8_LENGTH
+        + I64_LENGTH; // expires_at
+
+    pub fn did_seed(did: &str) -> [u8; 32] {
+        hash::hash(did.as_bytes()).to_bytes()
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+
+    // a freshly created alias account has no handle yet
+    pub fn is_claimed(&self, now: i64) -> bool {
+        !self.handle.is_empty() && !self.is_expired(now)
+    }
+
+    pub fn is_owned_by(&self, did: &str) -> bool {
+        self.did_seed == Self::did_seed(did)
+    }
+}
@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 
-use crate::constants::{BOOL_LENGTH, DISCRIMINATOR_LENGTH, I64_LENGTH, PUBLIC_KEY_LENGTH};
+use crate::{
+    constants::{BOOL_LENGTH, DISCRIMINATOR_LENGTH, I64_LENGTH, PUBLIC_KEY_LENGTH},
+    state::Did,
+};
 
 #[account]
 pub struct Credential {
@@ -15,6 +18,24 @@ pub struct Credential {
     pub credential_hash: String,
 }
 
+/// The fields of a credential set by its issuer, when issuing and updating it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CredentialParams {
+    pub uri: String,
+    pub credential_hash: String,
+    pub is_mutable: bool,
+    pub is_revokable: bool,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum CredentialStatus {
+    Valid = 0,
+    Expired = 1,
+    IssuerDeactivated = 2,
+    SubjectDeactivated = 3,
+}
+
 impl Credential {
     pub const SEED_PREFIX: &'static str = "credential";
 
@@ -25,4 +46,17 @@ impl Credential {
                                 + BOOL_LENGTH // is_mutable
                                 + BOOL_LENGTH // is_revokable
                                 + I64_LENGTH; // expires_at
+
+    pub fn status(&self, issuer_did: &Did, subject_did: &Did, clock: &Clock) -> CredentialStatus {
+        if issuer_did.is_deactivated() {
+            CredentialStatus::IssuerDeactivated
+        } else if subject_did.is_deactivated() {
+            CredentialStatus::SubjectDeactivated
+        } else if matches!(self.expires_at, Some(expires_at) if expires_at <= clock.unix_timestamp)
+        {
+            CredentialStatus::Expired
+        } else {
+            CredentialStatus::Valid
+        }
+    }
 }
@@ -1,15 +1,29 @@
-use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        hash, instruction::Instruction, keccak, secp256k1_recover::secp256k1_recover,
+        sysvar::instructions::load_instruction_at_checked,
+    },
+    Discriminator,
+};
 
 use crate::{
     constants::{
-        DID_LENGTH, DISCRIMINATOR_LENGTH, ETH_ADDRESS_LENGTH, ETH_SIGNATURE_LENGTH, I64_LENGTH,
-        SOL_ADDRESS_LENGTH, SOL_SIGNATURE_LENGTH, U8_LENGTH,
+        BTC_ADDRESS_BYTES, DISCRIMINATOR_LENGTH, ETH_ADDRESS_BYTES, HASH_LENGTH, I64_LENGTH,
+        PASSKEY_ADDRESS_BYTES, SERVICE_ENDPOINT_MAX_CHARS, SERVICE_ID_MAX_CHARS,
+        SERVICE_TYPE_MAX_CHARS, SOL_ADDRESS_BYTES, STRING_LENGTH_PREFIX, U64_LENGTH, U8_LENGTH,
+        VEC_LENGTH_PREFIX,
     },
+    eip712::{get_typed_data_message, OperationMessage},
     errors::SquirclErrorCode,
-    utils::{get_ethereum_message_hash, verify_ed25519_ix, verify_secp256k1_ix},
+    utils::{
+        decode_base58, decode_btc_address, encode_btc_address, get_bitcoin_message_hash,
+        get_btc_address, get_eth_address, get_ethereum_message_hash, get_webauthn_message,
+        verify_ed25519_ix, verify_secp256k1_ix, verify_secp256r1_ix, BtcAddressType,
+    },
 };
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub enum Role {
     Controller = 0,     // Controller, can add/remove addresses, cannot be removed
     Admin = 1,          // Controller, can add/remove addresses, can be removed
@@ -19,62 +33,202 @@ pub enum Role {
 
 impl Role {
     pub const LEN: usize = U8_LENGTH;
+
+    pub fn is_controller(&self) -> bool {
+        matches!(self, Role::Controller)
+    }
+
+    // roles allowed to co-sign address and role management
+    pub fn can_manage(&self) -> bool {
+        matches!(self, Role::Controller | Role::Admin)
+    }
+
+    // roles allowed to co-sign credential issuance, updates and revocations
+    pub fn can_assert(&self) -> bool {
+        matches!(self, Role::Controller | Role::Admin | Role::Assertion)
+    }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Controller => write!(f, "controller"),
+            Role::Admin => write!(f, "admin"),
+            Role::Assertion => write!(f, "assertion"),
+            Role::Authentication => write!(f, "authentication"),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub enum Chain {
     EVM = 0,
     SOL = 1,
+    BTC = 2,
+    PASSKEY = 3,
 }
 
 impl Chain {
     pub const LEN: usize = U8_LENGTH;
+
+    // length of the raw key an address of the chain is stored as
+    pub fn key_len(&self) -> usize {
+        match self {
+            Chain::EVM => ETH_ADDRESS_BYTES,
+            Chain::SOL => SOL_ADDRESS_BYTES,
+            Chain::BTC => BTC_ADDRESS_BYTES,
+            Chain::PASSKEY => PASSKEY_ADDRESS_BYTES,
+        }
+    }
+
+    // raw key of an address as it appears in messages and signatures: 0x prefixed hex for EVM,
+    // base58 for Solana and passkeys, and the type byte and pubkey hash of a Bitcoin address
+    pub fn decode_address(&self, address: &str) -> Result<Vec<u8>> {
+        let key = match self {
+            Chain::EVM => address
+                .strip_prefix("0x")
+                .and_then(|hex_address| hex::decode(hex_address).ok()),
+            Chain::SOL | Chain::PASSKEY => bs58::decode(address).into_vec().ok(),
+            Chain::BTC => decode_btc_address(address).map(|(address_type, pubkey_hash)| {
+                [&[address_type as u8], pubkey_hash.as_slice()].concat()
+            }),
+        };
+
+        match key {
+            Some(key) if key.len() == self.key_len() => Ok(key),
+            _ => Err(SquirclErrorCode::InvalidAddress.into()),
+        }
+    }
+
+    pub fn encode_address(&self, key: &[u8]) -> String {
+        match self {
+            Chain::EVM => format!("0x{}", hex::encode(key)),
+            Chain::SOL | Chain::PASSKEY => bs58::encode(key).into_string(),
+            Chain::BTC => {
+                let address_type = match key.first() {
+                    Some(1) => BtcAddressType::P2WPKH,
+                    _ => BtcAddressType::P2PKH,
+                };
+
+                encode_btc_address(address_type, key.get(1..).unwrap_or_default())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chain::EVM => write!(f, "evm"),
+            Chain::SOL => write!(f, "sol"),
+            Chain::BTC => write!(f, "btc"),
+            Chain::PASSKEY => write!(f, "passkey"),
+        }
+    }
+}
+
+/// Window in which an added address can authorise, unbounded on the sides left as None
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct AddressValidity {
+    pub valid_from: Option<i64>,
+    pub valid_until: Option<i64>,
+}
+
+impl AddressValidity {
+    pub fn is_unbounded(&self) -> bool {
+        self.valid_from.is_none() && self.valid_until.is_none()
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct Address {
-    pub address: String,
+    pub address: Vec<u8>, // raw key, `Chain::key_len` bytes
     pub added_at: i64,
     pub chain: Chain,
     pub role: Role,
-    // pub nonce: [u8; 32],
+    pub valid_from: Option<i64>, // can't authorise anything before, unbounded when None
+    pub valid_until: Option<i64>, // expires at, unbounded when None
 }
 
 impl Address {
-    pub const ETH_LEN: usize = ETH_ADDRESS_LENGTH + I64_LENGTH + ETH_SIGNATURE_LENGTH + Role::LEN;
-    // + U8_LENGTH * 32;
-
-    pub const SOL_LEN: usize = SOL_ADDRESS_LENGTH + I64_LENGTH + SOL_SIGNATURE_LENGTH + Role::LEN;
-    // + U8_LENGTH * 32;
-
-    pub fn new_eth(
-        address: String,
-        added_at: i64,
-        role: Role,
-        // nonce: [u8; 32],
-    ) -> Self {
-        Self {
-            address,
-            added_at,
-            chain: Chain::EVM,
-            role,
-            // nonce,
-        }
+    // PASSKEY has the longest key
+    pub const MAX_LEN: usize = Self::LEN_WITHOUT_KEY + PASSKEY_ADDRESS_BYTES;
+
+    const LEN_WITHOUT_KEY: usize = VEC_LENGTH_PREFIX
+        + I64_LENGTH // added_at
+        + Chain::LEN
+        + Role::LEN
+        + U8_LENGTH
+        + I64_LENGTH // valid_from
+        + U8_LENGTH
+        + I64_LENGTH; // valid_until
+
+    pub fn len_for(chain: &Chain) -> usize {
+        Self::LEN_WITHOUT_KEY + chain.key_len()
     }
 
-    pub fn new_sol(
-        address: String,
-        added_at: i64,
-        role: Role,
-        // nonce: [u8; 32],
-    ) -> Self {
-        Self {
-            address,
+    pub fn new(chain: Chain, address: &str, added_at: i64, role: Role) -> Result<Self> {
+        Ok(Self {
+            address: chain.decode_address(address)?,
             added_at,
-            chain: Chain::SOL,
+            chain,
             role,
-            // nonce,
-        }
+            valid_from: None,
+            valid_until: None,
+        })
+    }
+
+    // the address as it appears in messages and signatures
+    pub fn get_address_string(&self) -> String {
+        self.chain.encode_address(&self.address)
+    }
+
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        let has_started = match self.valid_from {
+            Some(valid_from) => valid_from <= now,
+            None => true,
+        };
+
+        has_started && !self.has_expired(now)
+    }
+
+    pub fn has_expired(&self, now: i64) -> bool {
+        matches!(self.valid_until, Some(valid_until) if valid_until <= now)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Service {
+    pub id: String, // fragment, resolved as <did>#<id>
+    pub service_type: String,
+    pub endpoint: String,
+}
+
+impl Service {
+    pub fn space(&self) -> usize {
+        STRING_LENGTH_PREFIX * 3 + self.id.len() + self.service_type.len() + self.endpoint.len()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.id.is_empty()
+                && self.id.len() <= SERVICE_ID_MAX_CHARS
+                && self
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')),
+            SquirclErrorCode::InvalidService
+        );
+        require!(
+            !self.service_type.is_empty() && self.service_type.len() <= SERVICE_TYPE_MAX_CHARS,
+            SquirclErrorCode::InvalidService
+        );
+        require!(
+            !self.endpoint.is_empty() && self.endpoint.len() <= SERVICE_ENDPOINT_MAX_CHARS,
+            SquirclErrorCode::InvalidService
+        );
+
+        Ok(())
     }
 }
 
@@ -85,53 +239,399 @@ pub struct Did {
     pub updated_at: i64,
     pub eth_addresses: Vec<Address>,
     pub sol_addresses: Vec<Address>,
+    pub deactivated_at: Option<i64>,
+    pub threshold: u8,
+    pub services: Vec<Service>,
+    pub btc_addresses: Vec<Address>,
+    pub passkey_addresses: Vec<Address>,
+    pub version: u64,              // 1 when created, bumped by every mutation
+    pub prev_state_hash: [u8; 32], // hash of the state the current version replaced
 }
 
 impl Did {
-    pub const LEN_WITHOUT_ADDRESS: usize =
-        DISCRIMINATOR_LENGTH + DID_LENGTH + I64_LENGTH + I64_LENGTH + U8_LENGTH;
+    const LEN_WITHOUT_DID_AND_ADDRESS: usize = DISCRIMINATOR_LENGTH
+        + I64_LENGTH // created_at
+        + I64_LENGTH // updated_at
+        + VEC_LENGTH_PREFIX // eth_addresses
+        + VEC_LENGTH_PREFIX // sol_addresses
+        + U8_LENGTH
+        + I64_LENGTH // deactivated_at
+        + U8_LENGTH // threshold
+        + VEC_LENGTH_PREFIX // services
+        + VEC_LENGTH_PREFIX // btc_addresses
+        + VEC_LENGTH_PREFIX // passkey_addresses
+        + U64_LENGTH // version
+        + HASH_LENGTH; // prev_state_hash
+
+    pub const MAX_SERVICES: usize = 8;
+
+    // size of a did without addresses and services, which is all a deactivated DID keeps as a
+    // tombstone
+    pub fn len_without_address(did: &str) -> usize {
+        STRING_LENGTH_PREFIX + did.len() + Self::LEN_WITHOUT_DID_AND_ADDRESS
+    }
 
-    pub fn new_eth(did: String, clock: Clock, controller: Address) -> Self {
-        Self {
+    pub fn new(did: String, clock: Clock, controller: Address) -> Self {
+        let mut did = Self {
             did,
             created_at: clock.unix_timestamp,
             updated_at: clock.unix_timestamp,
-            eth_addresses: vec![controller],
+            eth_addresses: vec![],
             sol_addresses: vec![],
+            deactivated_at: None,
+            threshold: 1,
+            services: vec![],
+            btc_addresses: vec![],
+            passkey_addresses: vec![],
+            version: 1,
+            prev_state_hash: [0; 32],
+        };
+
+        did.addresses_mut(&controller.chain).push(controller);
+
+        did
+    }
+
+    // borsh encoding of the did, as emitted with every version
+    pub fn state(&self) -> Vec<u8> {
+        // serializing into a Vec can't fail
+        self.try_to_vec().unwrap()
+    }
+
+    pub fn state_hash(&self) -> [u8; 32] {
+        hash::hash(&self.state()).to_bytes()
+    }
+
+    // every mutation starts a new version which commits to the state it replaces, chaining the
+    // versions of the did together
+    fn commit(&mut self, clock: &Clock) {
+        self.prev_state_hash = self.state_hash();
+        self.version += 1;
+        self.updated_at = clock.unix_timestamp;
+    }
+
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated_at.is_some()
+    }
+
+    // account size needed to hold the did as it currently is
+    pub fn space(&self) -> usize {
+        Self::len_without_address(&self.did)
+            + self
+                .all_addresses()
+                .map(|a| Address::len_for(&a.chain))
+                .sum::<usize>()
+            + self.services.iter().map(Service::space).sum::<usize>()
+    }
+
+    // number of distinct signatures needed for sensitive operations, 0 is treated as 1
+    pub fn required_signatures(&self) -> usize {
+        self.threshold.max(1) as usize
+    }
+
+    pub fn manager_count(&self) -> usize {
+        self.all_addresses().filter(|a| a.role.can_manage()).count()
+    }
+
+    pub fn controller(&self) -> Option<&Address> {
+        self.all_addresses().find(|a| a.role.is_controller())
+    }
+
+    pub fn addresses(&self, chain: &Chain) -> &Vec<Address> {
+        match chain {
+            Chain::EVM => &self.eth_addresses,
+            Chain::SOL => &self.sol_addresses,
+            Chain::BTC => &self.btc_addresses,
+            Chain::PASSKEY => &self.passkey_addresses,
         }
     }
 
-    pub fn new_sol(did: String, clock: Clock, controller: Address) -> Self {
-        Self {
-            did,
-            created_at: clock.unix_timestamp,
-            updated_at: clock.unix_timestamp,
-            eth_addresses: vec![],
-            sol_addresses: vec![controller],
+    pub fn addresses_mut(&mut self, chain: &Chain) -> &mut Vec<Address> {
+        match chain {
+            Chain::EVM => &mut self.eth_addresses,
+            Chain::SOL => &mut self.sol_addresses,
+            Chain::BTC => &mut self.btc_addresses,
+            Chain::PASSKEY => &mut self.passkey_addresses,
         }
     }
 
-    pub fn add_address_sol(&mut self, clock: Clock, address: Address) {
-        self.updated_at = clock.unix_timestamp;
-        self.sol_addresses.push(address);
+    pub fn all_addresses(&self) -> impl Iterator<Item = &Address> {
+        self.eth_addresses
+            .iter()
+            .chain(self.sol_addresses.iter())
+            .chain(self.btc_addresses.iter())
+            .chain(self.passkey_addresses.iter())
     }
 
-    pub fn add_address_eth(&mut self, clock: Clock, address: Address) {
-        self.updated_at = clock.unix_timestamp;
-        self.eth_addresses.push(address);
+    pub fn find_address(&self, chain: &Chain, address: &str) -> Option<&Address> {
+        let key = chain.decode_address(address).ok()?;
+
+        self.addresses(chain).iter().find(|a| a.address == key)
     }
 
-    pub fn remove_address_sol(&mut self, clock: Clock, address: String) {
-        self.updated_at = clock.unix_timestamp;
-        self.sol_addresses.retain(|a| a.address != address);
+    // the address if it can authorise an operation at `now`, an address outside of its validity
+    // window is treated as if it wasn't in the did
+    pub fn find_valid_address(&self, chain: &Chain, address: &str, now: i64) -> Result<&Address> {
+        let found_address = self
+            .find_address(chain, address)
+            .ok_or(SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+        require!(
+            found_address.is_valid_at(now),
+            SquirclErrorCode::AddressNotValid
+        );
+
+        Ok(found_address)
     }
 
-    pub fn remove_address_eth(&mut self, clock: Clock, address: String) {
-        self.updated_at = clock.unix_timestamp;
-        self.eth_addresses.retain(|a| a.address != address);
+    // verifies the co-signers of an operation already authorised by `authorizer`, and makes sure
+    // the number of distinct signers with a sufficient role meets the threshold of the DID
+    pub fn check_threshold<F, M>(
+        &self,
+        ix_sysvar: &AccountInfo,
+        authorizer: &Sig,
+        co_signer_sigs: &[Sig],
+        is_sufficient: fn(&Role) -> bool,
+        message_for: F,
+    ) -> Result<()>
+    where
+        F: Fn(String, i64) -> M,
+        M: Into<OperationMessage>,
+    {
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut signers: Vec<(Chain, String)> = vec![(authorizer.chain(), authorizer.signer())];
+
+        for sig in co_signer_sigs {
+            let signer = sig.signer();
+
+            sig.verify_at(ix_sysvar, message_for(signer.clone(), sig.nonce()))?;
+
+            let found_address = self.find_valid_address(&sig.chain(), &signer, now)?;
+
+            require!(
+                is_sufficient(&found_address.role),
+                SquirclErrorCode::AddressDoesntHaveEnoughPermissions
+            );
+
+            require!(
+                !signers
+                    .iter()
+                    .any(|(chain, address)| *chain == sig.chain() && *address == signer),
+                SquirclErrorCode::DuplicateSigner
+            );
+
+            signers.push((sig.chain(), signer));
+        }
+
+        require!(
+            signers.len() >= self.required_signatures(),
+            SquirclErrorCode::ThresholdNotMet
+        );
+
+        Ok(())
+    }
+
+    // swaps whichever address holds the controller role for `new_controller`, which may
+    // already be in the did under another role
+    pub fn replace_controller(&mut self, clock: Clock, new_controller: Address) {
+        self.commit(&clock);
+
+        let is_replaced = |a: &Address| {
+            a.role.is_controller()
+                || (a.chain == new_controller.chain && a.address == new_controller.address)
+        };
+
+        self.eth_addresses.retain(|a| !is_replaced(a));
+        self.sol_addresses.retain(|a| !is_replaced(a));
+        self.btc_addresses.retain(|a| !is_replaced(a));
+        self.passkey_addresses.retain(|a| !is_replaced(a));
+
+        self.addresses_mut(&new_controller.chain)
+            .push(new_controller);
+
+        // the recovered did keeps its policy, as far as its remaining signers can meet it
+        self.threshold = self.threshold.min(self.manager_count() as u8);
+    }
+
+    pub fn find_service(&self, id: &str) -> Option<&Service> {
+        self.services.iter().find(|s| s.id == id)
+    }
+
+    pub fn add_service(&mut self, clock: Clock, service: Service) {
+        self.commit(&clock);
+        self.services.push(service);
+    }
+
+    pub fn remove_service(&mut self, clock: Clock, id: String) {
+        self.commit(&clock);
+        self.services.retain(|s| s.id != id);
+    }
+
+    pub fn set_threshold(&mut self, clock: Clock, threshold: u8) {
+        self.commit(&clock);
+        self.threshold = threshold;
+    }
+
+    pub fn deactivate(&mut self, clock: Clock) {
+        self.commit(&clock);
+        self.deactivated_at = Some(clock.unix_timestamp);
+        self.eth_addresses.clear();
+        self.sol_addresses.clear();
+        self.btc_addresses.clear();
+        self.passkey_addresses.clear();
+        self.services.clear();
+    }
+
+    pub fn add_address(&mut self, clock: Clock, address: Address) {
+        self.commit(&clock);
+        self.addresses_mut(&address.chain.clone()).push(address);
+    }
+
+    pub fn remove_address(&mut self, clock: Clock, chain: &Chain, key: &[u8]) {
+        self.commit(&clock);
+        self.addresses_mut(chain).retain(|a| a.address != key);
+    }
+
+    pub fn change_role(&mut self, clock: Clock, chain: &Chain, key: &[u8], role: Role) {
+        self.commit(&clock);
+        self.set_role(chain, key, role);
+    }
+
+    // hands the controller role to an address already in the did and demotes the current
+    // controller to admin, as a single version
+    pub fn transfer_controller(
+        &mut self,
+        clock: Clock,
+        controller_chain: &Chain,
+        controller_key: &[u8],
+        new_controller_chain: &Chain,
+        new_controller_key: &[u8],
+    ) {
+        self.commit(&clock);
+        self.set_role(controller_chain, controller_key, Role::Admin);
+        self.set_role(new_controller_chain, new_controller_key, Role::Controller);
+
+        // the controller can't lapse
+        self.addresses_mut(new_controller_chain)
+            .iter_mut()
+            .filter(|a| a.address == new_controller_key)
+            .for_each(|a| {
+                a.valid_from = None;
+                a.valid_until = None;
+            });
+    }
+
+    // drops the addresses whose validity has ended and returns them, the did is only updated
+    // when there are any
+    pub fn prune_expired(&mut self, clock: Clock) -> Vec<Address> {
+        let now = clock.unix_timestamp;
+
+        let expired: Vec<Address> = self
+            .all_addresses()
+            .filter(|a| a.has_expired(now))
+            .cloned()
+            .collect();
+
+        if expired.is_empty() {
+            return expired;
+        }
+
+        self.commit(&clock);
+
+        self.eth_addresses.retain(|a| !a.has_expired(now));
+        self.sol_addresses.retain(|a| !a.has_expired(now));
+        self.btc_addresses.retain(|a| !a.has_expired(now));
+        self.passkey_addresses.retain(|a| !a.has_expired(now));
+
+        // as with recovery, the did keeps its policy as far as its remaining signers can meet it
+        self.threshold = self.threshold.min(self.manager_count() as u8);
+
+        expired
+    }
+
+    fn set_role(&mut self, chain: &Chain, key: &[u8], role: Role) {
+        self.addresses_mut(chain)
+            .iter_mut()
+            .filter(|a| a.address == key)
+            .for_each(|a| a.role = role.clone());
     }
 }
 
+/// `Address` as stored before addresses were kept as raw keys
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LegacyAddress {
+    pub address: String,
+    pub added_at: i64,
+    pub chain: Chain,
+    pub role: Role,
+}
+
+impl LegacyAddress {
+    pub fn into_address(self) -> Result<Address> {
+        Address::new(self.chain, &self.address, self.added_at, self.role)
+            .map_err(|_| SquirclErrorCode::NotALegacyAccount.into())
+    }
+}
+
+/// `Did` as deployed before addresses were kept as raw keys, only read by `migrate_did`. The
+/// account was sized for a string address and a signature, its tail is zero padding
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LegacyDid {
+    pub did: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub eth_addresses: Vec<LegacyAddress>,
+    pub sol_addresses: Vec<LegacyAddress>,
+}
+
+impl LegacyDid {
+    // a did already in the current layout doesn't decode, its raw keys aren't address strings.
+    // One without evm or sol addresses can, but a legacy did always has its controller in them
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= DISCRIMINATOR_LENGTH
+                && data[..DISCRIMINATOR_LENGTH] == Did::discriminator(),
+            SquirclErrorCode::NotALegacyAccount
+        );
+
+        let did = Self::deserialize(&mut &data[DISCRIMINATOR_LENGTH..])
+            .map_err(|_| SquirclErrorCode::NotALegacyAccount)?;
+
+        require!(
+            !did.eth_addresses.is_empty() || !did.sol_addresses.is_empty(),
+            SquirclErrorCode::NotALegacyAccount
+        );
+
+        Ok(did)
+    }
+
+    pub fn into_did(self) -> Result<Did> {
+        Ok(Did {
+            did: self.did,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            eth_addresses: into_addresses(self.eth_addresses)?,
+            sol_addresses: into_addresses(self.sol_addresses)?,
+            deactivated_at: None,
+            threshold: 1,
+            services: vec![],
+            btc_addresses: vec![],
+            passkey_addresses: vec![],
+            // the history of a migrated did starts at its migration
+            version: 1,
+            prev_state_hash: [0; 32],
+        })
+    }
+}
+
+fn into_addresses(addresses: Vec<LegacyAddress>) -> Result<Vec<Address>> {
+    addresses
+        .into_iter()
+        .map(LegacyAddress::into_address)
+        .collect()
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct EthSig {
     pub address_base58: String,
@@ -140,36 +640,127 @@ pub struct EthSig {
 }
 
 impl EthSig {
-    pub fn get_eth_address_vec(&self) -> Vec<u8> {
-        bs58::decode(self.address_base58.clone())
-            .into_vec()
-            .unwrap()
+    pub fn get_eth_address_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.address_base58, SquirclErrorCode::InvalidAddress)
     }
 
-    pub fn get_sig_vec(&self) -> Vec<u8> {
-        bs58::decode(self.sig_base58.clone()).into_vec().unwrap()
+    pub fn get_sig_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.sig_base58, SquirclErrorCode::InvalidSignature)
     }
 
-    pub fn get_eth_address_hex(&self) -> String {
-        format!("0x{}", hex::encode(self.get_eth_address_vec().as_slice()))
+    pub fn get_eth_address_hex(&self) -> Result<String> {
+        Ok(format!("0x{}", hex::encode(self.get_eth_address_vec()?)))
     }
 
-    pub fn get_sig_hex(&self) -> String {
-        format!(
+    pub fn get_sig_hex(&self) -> Result<String> {
+        Ok(format!(
             "0x{}",
-            hex::encode([self.get_sig_vec().as_slice(), &[self.recovery_id + 27]].concat())
-        )
+            hex::encode([self.get_sig_vec()?.as_slice(), &[self.recovery_id + 27]].concat())
+        ))
     }
 
-    pub fn verify(&self, ix: &Instruction, msg_string: String) -> Result<()> {
-        let eth_address_binding = self.get_eth_address_vec();
-        let eth_address = eth_address_binding.as_slice();
-        let sig_binding = self.get_sig_vec();
-        let sig = sig_binding.as_slice();
-        let msg_binding = get_ethereum_message_hash(msg_string);
-        let msg = msg_binding.as_slice();
+    pub fn to_bytes(&self) -> Result<EthSigBytes> {
+        Ok(EthSigBytes {
+            address: self
+                .get_eth_address_vec()?
+                .try_into()
+                .map_err(|_| SquirclErrorCode::InvalidAddress)?,
+            sig: self
+                .get_sig_vec()?
+                .try_into()
+                .map_err(|_| SquirclErrorCode::InvalidSignatureLength)?,
+            recovery_id: self.recovery_id,
+        })
+    }
+}
+
+/// `EthSig` with the address and signature as raw bytes, their lengths are checked when the
+/// instruction is deserialized so there is nothing left to decode
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EthSigBytes {
+    pub address: [u8; 20],
+    pub sig: [u8; 64],
+    pub recovery_id: u8,
+}
+
+impl EthSigBytes {
+    pub fn get_eth_address_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.address))
+    }
+
+    // accepts a signature over the operation message or over its digest
+    pub fn verify(
+        &self,
+        ix_sysvar: &AccountInfo,
+        ix: &Instruction,
+        slot: u8,
+        message: &OperationMessage,
+    ) -> Result<()> {
+        let verifies = |msg_string: String| {
+            let msg = get_ethereum_message_hash(msg_string);
+            verify_secp256k1_ix(
+                ix_sysvar,
+                ix,
+                slot,
+                &self.address,
+                &msg,
+                &self.sig,
+                self.recovery_id,
+            )
+            .is_ok()
+        };
+
+        if verifies(message.text.clone()) || verifies(message.digest()) {
+            msg!("signature verified");
+        } else {
+            msg!("signature not verified root");
+            return Err(SquirclErrorCode::InvalidSignature.into());
+        }
+
+        Ok(())
+    }
+
+    // syscall mode, recovers the signer instead of relying on a Secp256k1Program instruction
+    pub fn verify_recover(&self, msg_string: String) -> Result<()> {
+        let msg = keccak::hash(&get_ethereum_message_hash(msg_string)).to_bytes();
+
+        let pubkey = match secp256k1_recover(&msg, self.recovery_id, &self.sig) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                msg!("signature not verified root");
+                return Err(SquirclErrorCode::InvalidSignature.into());
+            }
+        };
+
+        if get_eth_address(&pubkey.to_bytes()) != self.address {
+            msg!("signature not verified root");
+            return Err(SquirclErrorCode::InvalidSignature.into());
+        }
+
+        msg!("signature verified");
+
+        Ok(())
+    }
 
-        match verify_secp256k1_ix(&ix, eth_address, &msg, sig, self.recovery_id) {
+    // EIP-712 mode, the signature is over the typed data of the operation instead of its text
+    pub fn verify_typed(
+        &self,
+        ix_sysvar: &AccountInfo,
+        ix: &Instruction,
+        slot: u8,
+        struct_hash: &[u8; 32],
+    ) -> Result<()> {
+        let msg = get_typed_data_message(struct_hash);
+
+        match verify_secp256k1_ix(
+            ix_sysvar,
+            ix,
+            slot,
+            &self.address,
+            &msg,
+            &self.sig,
+            self.recovery_id,
+        ) {
             Ok(()) => {
                 msg!("signature verified");
             }
@@ -183,6 +774,8 @@ impl EthSig {
     }
 }
 
+/// ed25519 signature over either the raw message or the message in the Solana off-chain
+/// message envelope, whichever the ed25519 instruction carries
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct SolSig {
     pub address_base58: String,
@@ -190,22 +783,157 @@ pub struct SolSig {
 }
 
 impl SolSig {
-    pub fn get_sol_address_vec(&self) -> Vec<u8> {
-        bs58::decode(self.address_base58.clone())
-            .into_vec()
-            .unwrap()
-    }
-    pub fn get_sig_vec(&self) -> Vec<u8> {
-        bs58::decode(self.sig_base58.clone()).into_vec().unwrap()
-    }
-    pub fn verify(&self, ix: &Instruction, msg_string: String) -> Result<()> {
-        let msg = msg_string.as_bytes();
-        let sol_address_binding = self.get_sol_address_vec();
-        let sol_address = sol_address_binding.as_slice();
-        let sig_binding = self.get_sig_vec();
+    pub fn get_sol_address_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.address_base58, SquirclErrorCode::InvalidAddress)
+    }
+
+    pub fn get_sig_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.sig_base58, SquirclErrorCode::InvalidSignature)
+    }
+
+    pub fn to_bytes(&self) -> Result<SolSigBytes> {
+        Ok(SolSigBytes {
+            address: self
+                .get_sol_address_vec()?
+                .try_into()
+                .map_err(|_| SquirclErrorCode::InvalidAddress)?,
+            sig: self
+                .get_sig_vec()?
+                .try_into()
+                .map_err(|_| SquirclErrorCode::InvalidSignatureLength)?,
+        })
+    }
+}
+
+/// `SolSig` with the address and signature as raw bytes, their lengths are checked when the
+/// instruction is deserialized so there is nothing left to decode
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SolSigBytes {
+    pub address: [u8; 32],
+    pub sig: [u8; 64],
+}
+
+impl SolSigBytes {
+    pub fn get_sol_address_base58(&self) -> String {
+        bs58::encode(self.address).into_string()
+    }
+
+    // accepts a signature over the operation message or over its digest
+    pub fn verify(
+        &self,
+        ix_sysvar: &AccountInfo,
+        ix: &Instruction,
+        slot: u8,
+        message: &OperationMessage,
+    ) -> Result<()> {
+        let verifies = |msg: &[u8]| {
+            verify_ed25519_ix(ix_sysvar, ix, slot, &self.address, msg, &self.sig).is_ok()
+        };
+
+        if verifies(message.text.as_bytes()) || verifies(message.digest().as_bytes()) {
+            msg!("signature verified");
+        } else {
+            msg!("signature not verified root");
+            return Err(SquirclErrorCode::InvalidSignature.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// BIP-137 "Bitcoin Signed Message" signature, checked by recovering the public key through the
+/// secp256k1_recover syscall, so no precompile instruction is needed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BtcSig {
+    pub address: String, // P2PKH (1...) or P2WPKH (bc1q...) mainnet address
+    pub sig_base58: String,
+}
+
+impl BtcSig {
+    pub fn get_sig_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.sig_base58, SquirclErrorCode::InvalidSignature)
+    }
+
+    pub fn verify(&self, msg_string: String) -> Result<()> {
+        let sig = self.get_sig_vec()?;
+
+        require!(sig.len() == 65, SquirclErrorCode::InvalidSignature);
+
+        // the header byte encodes both the recovery id and the kind of address that signed
+        let (address_type, compressed) = match sig[0] {
+            27..=30 => (BtcAddressType::P2PKH, false),
+            31..=34 => (BtcAddressType::P2PKH, true),
+            39..=42 => (BtcAddressType::P2WPKH, true),
+            _ => return Err(SquirclErrorCode::InvalidSignature.into()),
+        };
+        let recovery_id = (sig[0] - 27) & 3;
+
+        let msg = get_bitcoin_message_hash(msg_string);
+
+        let pubkey = match secp256k1_recover(&msg, recovery_id, &sig[1..]) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                msg!("signature not verified root");
+                return Err(SquirclErrorCode::InvalidSignature.into());
+            }
+        };
+
+        if get_btc_address(&pubkey.to_bytes(), compressed, address_type) != self.address {
+            msg!("signature not verified root");
+            return Err(SquirclErrorCode::InvalidSignature.into());
+        }
+
+        msg!("signature verified");
+
+        Ok(())
+    }
+}
+
+/// WebAuthn assertion of a passkey, verified by the secp256r1 precompile. The challenge of
+/// `client_data_json` must be the base64url encoded operation message
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PasskeySig {
+    pub address_base58: String, // compressed secp256r1 public key
+    pub sig_base58: String,     // r || s with a low s
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: String,
+}
+
+impl PasskeySig {
+    pub fn get_pubkey_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.address_base58, SquirclErrorCode::InvalidAddress)
+    }
+
+    pub fn get_sig_vec(&self) -> Result<Vec<u8>> {
+        decode_base58(&self.sig_base58, SquirclErrorCode::InvalidSignature)
+    }
+
+    // the challenge is the operation message or its digest
+    pub fn verify(
+        &self,
+        ix_sysvar: &AccountInfo,
+        ix: &Instruction,
+        slot: u8,
+        message: &OperationMessage,
+    ) -> Result<()> {
+        let msg = match get_webauthn_message(
+            &self.authenticator_data,
+            &self.client_data_json,
+            &message.text,
+        ) {
+            Ok(msg) => msg,
+            Err(_) => get_webauthn_message(
+                &self.authenticator_data,
+                &self.client_data_json,
+                &message.digest(),
+            )?,
+        };
+        let pubkey_binding = self.get_pubkey_vec()?;
+        let pubkey = pubkey_binding.as_slice();
+        let sig_binding = self.get_sig_vec()?;
         let sig = sig_binding.as_slice();
 
-        match verify_ed25519_ix(&ix, sol_address, msg, sig) {
+        match verify_secp256r1_ix(ix_sysvar, ix, slot, pubkey, &msg, sig) {
             Ok(()) => {
                 msg!("signature verified");
             }
@@ -219,16 +947,174 @@ impl SolSig {
     }
 }
 
+/// A signature of an operation. Precompile verified signatures point at the precompile
+/// instruction (`index`) and the signature within it (`slot`), whose data may live in any
+/// instruction of the transaction
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub enum Sig {
     Eth {
         eth_sig: EthSig,
         index: u8,
+        slot: u8,
         nonce: i64,
     },
     Sol {
         sol_sig: SolSig,
         index: u8,
+        slot: u8,
+        nonce: i64,
+    },
+    Btc {
+        btc_sig: BtcSig,
         nonce: i64,
     },
+    Passkey {
+        passkey_sig: PasskeySig,
+        index: u8,
+        slot: u8,
+        nonce: i64,
+    },
+    // an EVM personal_sign signature checked with the secp256k1_recover syscall, so no
+    // precompile instruction is needed
+    EthRecover {
+        eth_sig: EthSig,
+        nonce: i64,
+    },
+    // an EVM signature over EIP-712 typed data rather than a personal_sign message
+    Eip712 {
+        eth_sig: EthSig,
+        index: u8,
+        slot: u8,
+        nonce: i64,
+    },
+    // `Eth` and `Sol` with raw byte addresses and signatures, smaller and cheaper to verify
+    EthBytes {
+        eth_sig: EthSigBytes,
+        index: u8,
+        slot: u8,
+        nonce: i64,
+    },
+    SolBytes {
+        sol_sig: SolSigBytes,
+        index: u8,
+        slot: u8,
+        nonce: i64,
+    },
+}
+
+impl Sig {
+    pub fn nonce(&self) -> i64 {
+        match self {
+            Sig::Eth { nonce, .. } => *nonce,
+            Sig::Sol { nonce, .. } => *nonce,
+            Sig::Btc { nonce, .. } => *nonce,
+            Sig::Passkey { nonce, .. } => *nonce,
+            Sig::Eip712 { nonce, .. } => *nonce,
+            Sig::EthRecover { nonce, .. } => *nonce,
+            Sig::EthBytes { nonce, .. } => *nonce,
+            Sig::SolBytes { nonce, .. } => *nonce,
+        }
+    }
+
+    pub fn chain(&self) -> Chain {
+        match self {
+            Sig::Eth { .. } => Chain::EVM,
+            Sig::Sol { .. } => Chain::SOL,
+            Sig::Btc { .. } => Chain::BTC,
+            Sig::Passkey { .. } => Chain::PASSKEY,
+            Sig::Eip712 { .. } => Chain::EVM,
+            Sig::EthRecover { .. } => Chain::EVM,
+            Sig::EthBytes { .. } => Chain::EVM,
+            Sig::SolBytes { .. } => Chain::SOL,
+        }
+    }
+
+    // an address that isn't valid base58 can't match any stored address, it is rejected once
+    // the signature is verified
+    pub fn signer(&self) -> String {
+        match self {
+            Sig::Eth { eth_sig, .. }
+            | Sig::Eip712 { eth_sig, .. }
+            | Sig::EthRecover { eth_sig, .. } => eth_sig
+                .get_eth_address_hex()
+                .unwrap_or_else(|_| eth_sig.address_base58.clone()),
+            Sig::Sol { sol_sig, .. } => sol_sig.address_base58.clone(),
+            Sig::Btc { btc_sig, .. } => btc_sig.address.clone(),
+            Sig::Passkey { passkey_sig, .. } => passkey_sig.address_base58.clone(),
+            Sig::EthBytes { eth_sig, .. } => eth_sig.get_eth_address_hex(),
+            Sig::SolBytes { sol_sig, .. } => sol_sig.get_sol_address_base58(),
+        }
+    }
+
+    pub fn verify_at(
+        &self,
+        ix_sysvar: &AccountInfo,
+        message: impl Into<OperationMessage>,
+    ) -> Result<()> {
+        let message = message.into();
+
+        match self {
+            Sig::Eth {
+                eth_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                eth_sig.to_bytes()?.verify(ix_sysvar, &ix, *slot, &message)
+            }
+            Sig::Sol {
+                sol_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                sol_sig.to_bytes()?.verify(ix_sysvar, &ix, *slot, &message)
+            }
+            Sig::Btc { btc_sig, .. } => btc_sig.verify(message.text),
+            Sig::Passkey {
+                passkey_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                passkey_sig.verify(ix_sysvar, &ix, *slot, &message)
+            }
+            Sig::Eip712 {
+                eth_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let struct_hash = message
+                    .struct_hash
+                    .ok_or(SquirclErrorCode::TypedDataNotSupported)?;
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                eth_sig
+                    .to_bytes()?
+                    .verify_typed(ix_sysvar, &ix, *slot, &struct_hash)
+            }
+            Sig::EthRecover { eth_sig, .. } => eth_sig.to_bytes()?.verify_recover(message.text),
+            Sig::EthBytes {
+                eth_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                eth_sig.verify(ix_sysvar, &ix, *slot, &message)
+            }
+            Sig::SolBytes {
+                sol_sig,
+                index,
+                slot,
+                ..
+            } => {
+                let ix = load_instruction_at_checked(*index as usize, ix_sysvar)?;
+                sol_sig.verify(ix_sysvar, &ix, *slot, &message)
+            }
+        }
+    }
 }
@@ -0,0 +1,173 @@
+use anchor_lang::{prelude::*, solana_program::hash};
+
+use crate::{
+    constants::{
+        DISCRIMINATOR_LENGTH, I64_LENGTH, MAX_NONCE_FUTURE_DRIFT, NONCE_VALIDITY_WINDOW,
+        PUBLIC_KEY_LENGTH, VEC_LENGTH_PREFIX,
+    },
+    errors::SquirclErrorCode,
+    state::Sig,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UsedNonce {
+    pub signer: [u8; 32], // hash of the signing address
+    pub nonce: i64,
+}
+
+impl UsedNonce {
+    pub const LEN: usize = PUBLIC_KEY_LENGTH + I64_LENGTH;
+}
+
+/// The nonces recently consumed by a DID's signers.
+///
+/// Once the registry is full the lowest nonce is evicted, and becomes the new `floor`: anything
+/// at or below it is rejected, so evicted entries can't be replayed. Evicting the lowest rather
+/// than the oldest keeps the floor below every nonce still held, so one signer's future-dated
+/// nonce can't push it past the current nonces of the others.
+#[account]
+pub struct NonceRegistry {
+    pub floor: i64,
+    pub used: Vec<UsedNonce>,
+}
+
+impl NonceRegistry {
+    pub const SEED_PREFIX: &'static str = "nonce_registry";
+
+    pub const CAPACITY: usize = 32;
+
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + I64_LENGTH // floor
+        + VEC_LENGTH_PREFIX
+        + Self::CAPACITY * UsedNonce::LEN;
+
+    pub fn use_nonce(&mut self, sig: &Sig, clock: &Clock) -> Result<()> {
+        let nonce = sig.nonce();
+
+        require!(
+            nonce > clock.unix_timestamp - NONCE_VALIDITY_WINDOW && nonce > self.floor,
+            SquirclErrorCode::NonceExpired
+        );
+        require!(
+            nonce <= clock.unix_timestamp + MAX_NONCE_FUTURE_DRIFT,
+            SquirclErrorCode::NonceTooFarInFuture
+        );
+
+        let signer = hash::hash(sig.signer().as_bytes()).to_bytes();
+
+        require!(
+            !self
+                .used
+                .iter()
+                .any(|used| used.signer == signer && used.nonce == nonce),
+            SquirclErrorCode::NonceAlreadyUsed
+        );
+
+        let entry = UsedNonce { signer, nonce };
+
+        if self.used.len() < Self::CAPACITY {
+            self.used.push(entry);
+            return Ok(());
+        }
+
+        let lowest = (0..self.used.len())
+            .min_by_key(|&i| self.used[i].nonce)
+            .unwrap_or_default();
+
+        // a nonce below everything held would be evicted right away, the floor covers it
+        if nonce < self.used[lowest].nonce {
+            self.floor = nonce;
+        } else {
+            self.floor = self.used[lowest].nonce;
+            self.used[lowest] = entry;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SolSig;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn sig(signer: &str, nonce: i64) -> Sig {
+        Sig::Sol {
+            sol_sig: SolSig {
+                address_base58: signer.to_string(),
+                sig_base58: String::new(),
+            },
+            index: 0,
+            slot: 0,
+            nonce,
+        }
+    }
+
+    fn clock() -> Clock {
+        Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        }
+    }
+
+    fn registry() -> NonceRegistry {
+        NonceRegistry {
+            floor: 0,
+            used: vec![],
+        }
+    }
+
+    #[test]
+    fn future_dated_nonce_doesnt_block_other_signers() {
+        let mut registry = registry();
+
+        registry
+            .use_nonce(&sig("a", NOW + MAX_NONCE_FUTURE_DRIFT), &clock())
+            .unwrap();
+
+        // the future-dated nonce is the oldest entry by the time the registry overflows
+        for nonce in NOW - 2 * NonceRegistry::CAPACITY as i64..NOW {
+            registry.use_nonce(&sig("b", nonce), &clock()).unwrap();
+        }
+
+        assert!(registry.floor < NOW);
+        registry.use_nonce(&sig("b", NOW), &clock()).unwrap();
+
+        assert_eq!(
+            registry.use_nonce(&sig("a", NOW + MAX_NONCE_FUTURE_DRIFT), &clock()),
+            Err(SquirclErrorCode::NonceAlreadyUsed.into())
+        );
+    }
+
+    #[test]
+    fn evicted_nonces_cant_be_replayed() {
+        let mut registry = registry();
+        let capacity = NonceRegistry::CAPACITY as i64;
+
+        for i in 0..=capacity {
+            registry
+                .use_nonce(&sig("a", NOW - 2 * i), &clock())
+                .unwrap();
+        }
+
+        assert_eq!(registry.used.len(), NonceRegistry::CAPACITY);
+        assert_eq!(registry.floor, NOW - 2 * capacity);
+        assert_eq!(
+            registry.use_nonce(&sig("a", NOW - 2 * capacity), &clock()),
+            Err(SquirclErrorCode::NonceExpired.into())
+        );
+
+        // below every nonce held, so it only raises the floor
+        registry
+            .use_nonce(&sig("b", NOW - 2 * capacity + 1), &clock())
+            .unwrap();
+
+        assert_eq!(registry.floor, NOW - 2 * capacity + 1);
+        assert_eq!(
+            registry.use_nonce(&sig("b", NOW - 2 * capacity + 1), &clock()),
+            Err(SquirclErrorCode::NonceExpired.into())
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::{DID_LENGTH, DISCRIMINATOR_LENGTH, I64_LENGTH, U8_LENGTH, VEC_LENGTH_PREFIX},
+    state::{Address, Chain, Did},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum Guardian {
+    Address { chain: Chain, address: String },
+    Did { did: String }, // any controller or admin of the guardian DID can approve for it
+}
+
+impl Guardian {
+    // a did string is longer than any address, so it bounds both variants
+    pub const LEN: usize = U8_LENGTH + DID_LENGTH;
+
+    // the guardian with its address in the form signers are recovered as, so an address entered
+    // with another casing still matches. Fails on an address that isn't valid for its chain
+    pub fn canonicalize(&self) -> Result<Self> {
+        match self {
+            Guardian::Address { chain, address } => Ok(Guardian::Address {
+                chain: chain.clone(),
+                address: chain.encode_address(&chain.decode_address(address)?),
+            }),
+            Guardian::Did { .. } => Ok(self.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for Guardian {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Guardian::Address {
+                chain: Chain::EVM,
+                address,
+            } => write!(f, "evm:{}", address),
+            Guardian::Address {
+                chain: Chain::SOL,
+                address,
+            } => write!(f, "sol:{}", address),
+            Guardian::Address {
+                chain: Chain::BTC,
+                address,
+            } => write!(f, "btc:{}", address),
+            Guardian::Address {
+                chain: Chain::PASSKEY,
+                address,
+            } => write!(f, "passkey:{}", address),
+            Guardian::Did { did } => write!(f, "{}", did),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PendingRecovery {
+    pub new_controller: Address,
+    pub started_at: i64,
+    pub executable_at: i64,
+}
+
+impl PendingRecovery {
+    pub const LEN: usize = Address::MAX_LEN + I64_LENGTH + I64_LENGTH;
+}
+
+/// Social recovery settings of a DID.
+///
+/// A quorum of guardians can start a recovery that replaces the controller once `delay`
+/// seconds have passed, the current controller can cancel it until then.
+#[account]
+pub struct Recovery {
+    pub guardians: Vec<Guardian>,
+    pub quorum: u8,
+    pub delay: i64,
+    pub pending: Option<PendingRecovery>,
+}
+
+impl Recovery {
+    pub const SEED_PREFIX: &'static str = "recovery";
+
+    pub const MAX_GUARDIANS: usize = 8;
+
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + VEC_LENGTH_PREFIX
+        + Self::MAX_GUARDIANS * Guardian::LEN
+        + U8_LENGTH // quorum
+        + I64_LENGTH // delay
+        + U8_LENGTH
+        + PendingRecovery::LEN;
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn new_controller(&self) -> Option<&Address> {
+        self.pending.as_ref().map(|p| &p.new_controller)
+    }
+
+    // size of the did once the pending recovery swaps its controller for the new one
+    pub fn did_len_after_execution(&self, did: &Did) -> usize {
+        let mut len = did.space();
+
+        if let Some(pending) = &self.pending {
+            let new_controller = &pending.new_controller;
+
+            len += Address::len_for(&new_controller.chain);
+            len -= did
+                .all_addresses()
+                .filter(|a| {
+                    a.role.is_controller()
+                        || (a.chain == new_controller.chain && a.address == new_controller.address)
+                })
+                .map(|a| Address::len_for(&a.chain))
+                .sum::<usize>();
+        }
+
+        len
+    }
+}
@@ -0,0 +1,198 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    constants::{DISCRIMINATOR_LENGTH, PASSKEY_ADDRESS_BYTES},
+    errors::SquirclErrorCode,
+    state::{Chain, Role},
+};
+
+/// An address of a `ZeroCopyDid`, with its raw key padded with zeros to the longest key.
+///
+/// Packed, the explicit padding keeps slots a multiple of 8 bytes long
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+pub struct AddressSlot {
+    pub added_at: i64,
+    pub key: [u8; 33],
+    pub chain: u8,
+    pub role: u8,
+    pub _padding: [u8; 5],
+}
+
+// SAFETY: packed and made of integers only, so it has no padding bytes and any bit pattern is
+// valid
+unsafe impl Pod for AddressSlot {}
+unsafe impl Zeroable for AddressSlot {}
+
+impl AddressSlot {
+    pub const LEN: usize = size_of::<Self>();
+
+    pub fn new(chain: &Chain, key: &[u8], added_at: i64, role: &Role) -> Self {
+        let mut padded_key = [0; PASSKEY_ADDRESS_BYTES];
+        padded_key[..key.len()].copy_from_slice(key);
+
+        Self {
+            added_at,
+            key: padded_key,
+            chain: chain.clone() as u8,
+            role: role.clone() as u8,
+            _padding: [0; 5],
+        }
+    }
+
+    // slots are only ever written by the program, so the role is always valid
+    pub fn role(&self) -> Role {
+        match self.role {
+            0 => Role::Controller,
+            1 => Role::Admin,
+            2 => Role::Assertion,
+            _ => Role::Authentication,
+        }
+    }
+
+    fn sort_key(&self) -> (u8, [u8; 33]) {
+        (self.chain, self.key)
+    }
+}
+
+/// Zero-copy layout of a DID, for DIDs with many addresses.
+///
+/// The fixed header is followed in the account by `address_capacity` address slots, the first
+/// `address_count` of which are in use and kept sorted by chain and key. Instructions binary
+/// search the slots in place instead of deserializing the whole DID, and the account grows
+/// `SLOT_CHUNK` slots at a time instead of on every added address. It shares its PDA with `Did`,
+/// so a DID lives in one layout or the other. Thresholds, services, recovery, deactivation,
+/// address lookups, versions and address validity windows are only supported by `Did`, so its
+/// instructions are only enabled with the `zero-copy-bench` feature, to benchmark the layout
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+pub struct ZeroCopyDid {
+    pub did: [u8; 64], // `did_len` bytes of did string, padded with zeros
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub address_count: u16,
+    pub address_capacity: u16,
+    pub did_len: u8,
+    pub _padding: [u8; 3],
+}
+
+impl ZeroCopyDid {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH + size_of::<Self>();
+
+    pub const SLOT_CHUNK: u16 = 8;
+
+    pub fn len_for(address_capacity: u16) -> usize {
+        Self::LEN + address_capacity as usize * AddressSlot::LEN
+    }
+
+    pub fn init(&mut self, did: &str, clock: &Clock, address_capacity: u16) {
+        self.did[..did.len()].copy_from_slice(did.as_bytes());
+        self.did_len = did.len() as u8;
+        self.created_at = clock.unix_timestamp;
+        self.updated_at = clock.unix_timestamp;
+        self.address_capacity = address_capacity;
+    }
+
+    pub fn did(&self) -> String {
+        String::from_utf8_lossy(&self.did[..self.did_len as usize]).into_owned()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.address_count >= self.address_capacity
+    }
+
+    /// Splits the data of a zero-copy DID account into its header and its address slots
+    pub fn split(data: &mut [u8]) -> Result<(&mut Self, &mut [AddressSlot])> {
+        let (header, slots) = data[DISCRIMINATOR_LENGTH..].split_at_mut(size_of::<Self>());
+
+        let header: &mut Self = bytemuck::try_from_bytes_mut(header)
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+        let slots = bytemuck::try_cast_slice_mut(
+            &mut slots[..header.address_capacity as usize * AddressSlot::LEN],
+        )
+        .map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+
+        Ok((header, slots))
+    }
+
+    pub fn addresses<'a>(&self, slots: &'a [AddressSlot]) -> &'a [AddressSlot] {
+        &slots[..self.address_count as usize]
+    }
+
+    // index of the slot holding the address, or where it belongs if there is none
+    fn search(
+        &self,
+        slots: &[AddressSlot],
+        chain: &Chain,
+        key: &[u8],
+    ) -> std::result::Result<usize, usize> {
+        let target = AddressSlot::new(chain, key, 0, &Role::Authentication).sort_key();
+
+        self.addresses(slots)
+            .binary_search_by(|slot| slot.sort_key().cmp(&target))
+    }
+
+    pub fn find_address<'a>(
+        &self,
+        slots: &'a [AddressSlot],
+        chain: &Chain,
+        address: &str,
+    ) -> Option<&'a AddressSlot> {
+        let key = chain.decode_address(address).ok()?;
+
+        self.search(slots, chain, &key)
+            .ok()
+            .map(|index| &slots[index])
+    }
+
+    pub fn add_address(
+        &mut self,
+        slots: &mut [AddressSlot],
+        clock: &Clock,
+        chain: &Chain,
+        key: &[u8],
+        role: &Role,
+    ) -> Result<()> {
+        require!(!self.is_full(), SquirclErrorCode::TooManyAddresses);
+
+        let index = match self.search(slots, chain, key) {
+            Ok(_) => return Err(SquirclErrorCode::AddressAlreadyExists.into()),
+            Err(index) => index,
+        };
+
+        let count = self.address_count as usize;
+
+        slots.copy_within(index..count, index + 1);
+        slots[index] = AddressSlot::new(chain, key, clock.unix_timestamp, role);
+
+        self.address_count += 1;
+        self.updated_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn remove_address(
+        &mut self,
+        slots: &mut [AddressSlot],
+        clock: &Clock,
+        chain: &Chain,
+        key: &[u8],
+    ) -> Result<()> {
+        let index = self
+            .search(slots, chain, key)
+            .map_err(|_| SquirclErrorCode::AddressDoesNotExistInDID)?;
+
+        let count = self.address_count as usize;
+
+        slots.copy_within(index + 1..count, index);
+        slots[count - 1] = AddressSlot::zeroed();
+
+        self.address_count -= 1;
+        self.updated_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+}
@@ -3,12 +3,92 @@
 use anchor_lang::{
     prelude::*,
     solana_program::{
-        ed25519_program::ID as ED25519_ID, instruction::Instruction, keccak,
-        secp256k1_program::ID as SECP256K1_ID,
+        ed25519_program::ID as ED25519_ID, hash, instruction::Instruction, keccak,
+        secp256k1_program::ID as SECP256K1_ID, sysvar::instructions::load_instruction_at_checked,
     },
 };
 
-use crate::errors::SquirclErrorCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ripemd::{Digest, Ripemd160};
+
+use crate::{
+    constants::{
+        ALIAS_MAX_CHARS, ALIAS_MIN_CHARS, BITCOIN_MSG_PREFIX, CLUSTER, DERIVED_DID_HASH_BYTES,
+        DID_ID_MAX_CHARS, DID_PREFIX, MESSAGE_HEADER, MESSAGE_VERSION,
+        OFFCHAIN_MESSAGE_MAX_LEDGER_LENGTH, OFFCHAIN_MESSAGE_SIGNING_DOMAIN,
+        OFFCHAIN_MESSAGE_VERSION, SECP256R1_PROGRAM_ID, WEBAUTHN_AUTH_DATA_MIN_LENGTH,
+        WEBAUTHN_FLAG_USER_PRESENT, WEBAUTHN_GET_TYPE,
+    },
+    errors::SquirclErrorCode,
+    state::{AddressValidity, Chain, CredentialParams, Guardian, Role, Service},
+};
+
+/// Lowercase the DID so that case variants of the same id can't be registered as separate PDAs
+pub fn canonicalize_did(did_str: &str) -> String {
+    did_str.trim().to_ascii_lowercase()
+}
+
+/// An alias is a handle of `[a-z0-9_-]`, short enough to be a PDA seed
+pub fn validate_alias(handle: &str) -> Result<()> {
+    require!(
+        (ALIAS_MIN_CHARS..=ALIAS_MAX_CHARS).contains(&handle.len())
+            && handle
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'),
+        SquirclErrorCode::InvalidAlias
+    );
+
+    Ok(())
+}
+
+/// A DID is `did:squircl:<id>`, where `<id>` is either a free-form id made of `[a-z0-9._-]`,
+/// or a derived id `<chain>:<hex>` reserved to the address it was derived from
+pub fn validate_did(did_str: &str) -> Result<()> {
+    require!(
+        did_str == canonicalize_did(did_str),
+        SquirclErrorCode::DidNotCanonical
+    );
+
+    let id = did_str
+        .strip_prefix(DID_PREFIX)
+        .ok_or(SquirclErrorCode::InvalidDid)?;
+
+    require!(
+        !id.is_empty() && id.len() <= DID_ID_MAX_CHARS,
+        SquirclErrorCode::InvalidDid
+    );
+
+    let valid_id = match id.split_once(':') {
+        Some((chain, hash)) => {
+            matches!(chain, "evm" | "sol" | "btc" | "passkey")
+                && hash.len() == DERIVED_DID_HASH_BYTES * 2
+                && hash.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_'),
+    };
+
+    require!(valid_id, SquirclErrorCode::InvalidDid);
+
+    Ok(())
+}
+
+pub fn is_derived_did(did_str: &str) -> bool {
+    matches!(did_str.strip_prefix(DID_PREFIX), Some(id) if id.contains(':'))
+}
+
+/// Deterministic DID of a controller address, only that address can create it
+pub fn get_derived_did(chain: &Chain, address: &str) -> String {
+    let digest = hash::hash(address.as_bytes()).to_bytes();
+
+    format!(
+        "{}{}:{}",
+        DID_PREFIX,
+        chain,
+        hex::encode(&digest[..DERIVED_DID_HASH_BYTES])
+    )
+}
 
 pub fn get_ethereum_message_hash(message: String) -> Vec<u8> {
     let msg_data = [
@@ -27,274 +107,746 @@ pub fn get_ethereum_message_hash(message: String) -> Vec<u8> {
     .concat()
 }
 
-pub fn get_default_create_message(address: String, nonce: i64) -> String {
+pub fn decode_base58(value: &str, error: SquirclErrorCode) -> Result<Vec<u8>> {
+    bs58::decode(value).into_vec().map_err(|_| error.into())
+}
+
+/// Address of a public key recovered by secp256k1_recover (64 bytes, x || y)
+pub fn get_eth_address(pubkey: &[u8; 64]) -> Vec<u8> {
+    keccak::hash(pubkey).to_bytes()[12..].to_vec()
+}
+
+/// Double sha256 of the BIP-137 "Bitcoin Signed Message" serialization
+pub fn get_bitcoin_message_hash(message: String) -> [u8; 32] {
+    let msg_data = [
+        BITCOIN_MSG_PREFIX.as_bytes(),
+        &get_bitcoin_varint(message.len()),
+        message.as_ref(),
+    ]
+    .concat();
+
+    hash::hash(&hash::hash(&msg_data).to_bytes()).to_bytes()
+}
+
+fn get_bitcoin_varint(n: usize) -> Vec<u8> {
+    match n {
+        0..=0xfc => vec![n as u8],
+        0xfd..=0xffff => [&[0xfd], &(n as u16).to_le_bytes()[..]].concat(),
+        _ => [&[0xfe], &(n as u32).to_le_bytes()[..]].concat(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BtcAddressType {
+    P2PKH = 0,
+    P2WPKH = 1,
+}
+
+/// Mainnet address of a public key recovered by secp256k1_recover (64 bytes, x || y)
+pub fn get_btc_address(
+    pubkey: &[u8; 64],
+    compressed: bool,
+    address_type: BtcAddressType,
+) -> String {
+    encode_btc_address(address_type, &get_btc_pubkey_hash(pubkey, compressed))
+}
+
+/// hash160 of the serialized public key, the payload of P2PKH and P2WPKH addresses
+pub fn get_btc_pubkey_hash(pubkey: &[u8; 64], compressed: bool) -> Vec<u8> {
+    let serialized_pubkey = if compressed {
+        [&[2 + (pubkey[63] & 1)], &pubkey[..32]].concat()
+    } else {
+        [&[4], &pubkey[..]].concat()
+    };
+
+    Ripemd160::digest(hash::hash(&serialized_pubkey).to_bytes()).to_vec()
+}
+
+pub fn encode_btc_address(address_type: BtcAddressType, pubkey_hash: &[u8]) -> String {
+    match address_type {
+        BtcAddressType::P2PKH => {
+            let payload = [&[0x00], pubkey_hash].concat();
+            let checksum = hash::hash(&hash::hash(&payload).to_bytes()).to_bytes();
+
+            bs58::encode([payload.as_slice(), &checksum[..4]].concat()).into_string()
+        }
+        BtcAddressType::P2WPKH => bech32::segwit::encode_v0(bech32::hrp::BC, pubkey_hash).unwrap(),
+    }
+}
+
+/// Type and public key hash of a mainnet P2PKH or P2WPKH address, the inverse of
+/// `encode_btc_address`
+pub fn decode_btc_address(address: &str) -> Option<(BtcAddressType, Vec<u8>)> {
+    if let Ok((hrp, version, program)) = bech32::segwit::decode(address) {
+        return (hrp == bech32::hrp::BC
+            && version == bech32::segwit::VERSION_0
+            && program.len() == 20)
+            .then_some((BtcAddressType::P2WPKH, program));
+    }
+
+    let decoded = bs58::decode(address).into_vec().ok()?;
+
+    if decoded.len() != 25 || decoded[0] != 0x00 {
+        return None;
+    }
+
+    let checksum = hash::hash(&hash::hash(&decoded[..21]).to_bytes()).to_bytes();
+
+    (decoded[21..] == checksum[..4]).then(|| (BtcAddressType::P2PKH, decoded[1..21].to_vec()))
+}
+
+/// Solana off-chain message envelope (version 0) of a message, as signed by Ledger and the
+/// `solana sign-offchain-message` command
+/// https://github.com/solana-labs/solana/blob/master/sdk/src/offchain_message.rs
+pub fn get_offchain_message(message: &[u8]) -> Vec<u8> {
+    let format = if message.len() > OFFCHAIN_MESSAGE_MAX_LEDGER_LENGTH {
+        OffchainMessageFormat::ExtendedUtf8
+    } else if message.iter().all(|c| (0x20..=0x7e).contains(c)) {
+        OffchainMessageFormat::RestrictedAscii
+    } else {
+        OffchainMessageFormat::LimitedUtf8
+    };
+
+    [
+        OFFCHAIN_MESSAGE_SIGNING_DOMAIN,
+        &[OFFCHAIN_MESSAGE_VERSION, format as u8],
+        &(message.len() as u16).to_le_bytes(),
+        message,
+    ]
+    .concat()
+}
+
+pub enum OffchainMessageFormat {
+    RestrictedAscii = 0,
+    LimitedUtf8 = 1,
+    ExtendedUtf8 = 2,
+}
+
+/// Data a passkey signs for a Squircl operation, `authenticatorData || sha256(clientDataJSON)`,
+/// once the WebAuthn challenge is checked to be the base64url encoded operation message
+pub fn get_webauthn_message(
+    authenticator_data: &[u8],
+    client_data_json: &str,
+    message: &str,
+) -> Result<Vec<u8>> {
+    require!(
+        authenticator_data.len() >= WEBAUTHN_AUTH_DATA_MIN_LENGTH
+            && authenticator_data[32] & WEBAUTHN_FLAG_USER_PRESENT != 0,
+        SquirclErrorCode::InvalidWebAuthnData
+    );
+
+    let challenge = URL_SAFE_NO_PAD.encode(message);
+
+    require!(
+        get_client_data_field(client_data_json, "type") == Some(WEBAUTHN_GET_TYPE)
+            && get_client_data_field(client_data_json, "challenge") == Some(challenge.as_str()),
+        SquirclErrorCode::InvalidWebAuthnData
+    );
+
+    Ok([
+        authenticator_data,
+        &hash::hash(client_data_json.as_bytes()).to_bytes(),
+    ]
+    .concat())
+}
+
+// browsers serialize clientDataJSON without whitespace and escape quotes inside values, so the
+// first `"key":"` is always the member itself
+fn get_client_data_field<'a>(client_data_json: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = format!("\"{}\":\"", key);
+    let start = client_data_json.find(&pattern)? + pattern.len();
+    let len = client_data_json[start..].find('"')?;
+
+    Some(&client_data_json[start..start + len])
+}
+
+/// Canonical, versioned text every operation is signed as. It binds the signature to this
+/// program and cluster, to the DID it is meant for and to every parameter of the operation
+pub fn get_operation_message(
+    did: &str,
+    operation: &str,
+    signer: &str,
+    params: &[(&str, String)],
+    nonce: i64,
+) -> String {
+    let mut message = format!(
+        "{}\nVersion: {}\nProgram: {}\nCluster: {}\nDID: {}\nOperation: {}\nSigner: {}\n",
+        MESSAGE_HEADER,
+        MESSAGE_VERSION,
+        crate::ID,
+        CLUSTER,
+        did,
+        operation,
+        signer
+    );
+
+    for (key, value) in params {
+        message.push_str(&format!("{}: {}\n", key, escape_message_value(value)));
+    }
+
+    message.push_str(&format!("Nonce: {}", nonce));
+
+    message
+}
+
+/// Fixed-size commitment to an operation message, wallets can sign it instead of the full text
+/// so the precompile data stays the same size however long the parameters are. The program
+/// recomputes it from the instruction arguments like the full text
+pub fn get_operation_digest_message(message: &str) -> String {
     format!(
-        "I am creating a new Squircl DID with the address {}. Nonce: {}",
-        address, nonce
+        "{} digest\nVersion: {}\nDigest: {}",
+        MESSAGE_HEADER,
+        MESSAGE_VERSION,
+        hex::encode(hash::hash(message.as_bytes()).to_bytes())
     )
 }
 
-pub fn get_default_add_message_as_controller(
-    controller: String,
-    new_address: String,
+// one parameter per line, so a value can't pass itself off as another parameter
+fn escape_message_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+pub fn get_default_create_message(did: &str, address: String, nonce: i64) -> String {
+    get_operation_message(did, "create_did", &address, &[], nonce)
+}
+
+// the validity window is only part of the message when the address has one, so messages adding
+// permanent addresses are unchanged
+pub fn get_default_add_address_message(
+    did: &str,
+    signer: String,
+    chain: &Chain,
+    address: &str,
+    role: &Role,
+    validity: AddressValidity,
     nonce: i64,
 ) -> String {
-    format!(
-        "I am adding {} to the Squircl DID with the address {}. Nonce: {}",
-        new_address, controller, nonce
+    let mut params = vec![
+        ("Chain", chain.to_string()),
+        ("Address", address.to_string()),
+        ("Role", role.to_string()),
+    ];
+
+    if let Some(valid_from) = validity.valid_from {
+        params.push(("Valid from", valid_from.to_string()));
+    }
+
+    if let Some(valid_until) = validity.valid_until {
+        params.push(("Valid until", valid_until.to_string()));
+    }
+
+    get_operation_message(did, "add_address", &signer, &params, nonce)
+}
+
+pub fn get_default_remove_address_message(
+    did: &str,
+    signer: String,
+    chain: &Chain,
+    address: &str,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "remove_address",
+        &signer,
+        &[
+            ("Chain", chain.to_string()),
+            ("Address", address.to_string()),
+        ],
+        nonce,
     )
 }
 
-pub fn get_default_add_message_as_new_address(new_address: String, nonce: i64) -> String {
-    format!(
-        "I am adding myself to the Squircl DID with the address {}. Nonce: {}",
-        new_address, nonce
+pub fn get_default_change_role_message(
+    did: &str,
+    signer: String,
+    chain: &Chain,
+    address: &str,
+    role: &Role,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "change_role",
+        &signer,
+        &[
+            ("Chain", chain.to_string()),
+            ("Address", address.to_string()),
+            ("Role", role.to_string()),
+        ],
+        nonce,
     )
 }
 
-pub fn get_default_remove_message_as_address(address: String, nonce: i64) -> String {
-    format!(
-        "I am removing myself from the Squircl DID with the address {}. Nonce: {}",
-        address, nonce
+pub fn get_default_transfer_controller_message(
+    did: &str,
+    signer: String,
+    chain: &Chain,
+    new_controller: &str,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "transfer_controller",
+        &signer,
+        &[
+            ("Chain", chain.to_string()),
+            ("New controller", new_controller.to_string()),
+        ],
+        nonce,
     )
 }
 
-pub fn get_default_remove_message_as_controller(
-    controller: String,
-    address: String,
+pub fn get_default_set_threshold_message(
+    did: &str,
+    signer: String,
+    threshold: u8,
     nonce: i64,
 ) -> String {
-    format!(
-        "I am removing {} from the Squircl DID with the address {}. Nonce: {}",
-        address, controller, nonce
+    get_operation_message(
+        did,
+        "set_threshold",
+        &signer,
+        &[("Threshold", threshold.to_string())],
+        nonce,
+    )
+}
+
+pub fn get_default_set_guardians_message(
+    did: &str,
+    signer: String,
+    guardians: &[Guardian],
+    quorum: u8,
+    delay: i64,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "set_guardians",
+        &signer,
+        &[
+            (
+                "Guardians",
+                guardians
+                    .iter()
+                    .map(|g| g.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("Quorum", quorum.to_string()),
+            ("Delay", delay.to_string()),
+        ],
+        nonce,
     )
 }
 
+// signed by the approving guardians and by the new controller alike
+pub fn get_default_start_recovery_message(
+    did: &str,
+    signer: String,
+    chain: &Chain,
+    new_controller: &str,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "start_recovery",
+        &signer,
+        &[
+            ("Chain", chain.to_string()),
+            ("New controller", new_controller.to_string()),
+        ],
+        nonce,
+    )
+}
+
+pub fn get_default_cancel_recovery_message(did: &str, signer: String, nonce: i64) -> String {
+    get_operation_message(did, "cancel_recovery", &signer, &[], nonce)
+}
+
+pub fn get_default_add_service_message(
+    did: &str,
+    signer: String,
+    service: &Service,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "add_service",
+        &signer,
+        &[
+            ("Service", service.id.clone()),
+            ("Type", service.service_type.clone()),
+            ("Endpoint", service.endpoint.clone()),
+        ],
+        nonce,
+    )
+}
+
+pub fn get_default_remove_service_message(
+    did: &str,
+    signer: String,
+    id: &str,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "remove_service",
+        &signer,
+        &[("Service", id.to_string())],
+        nonce,
+    )
+}
+
+pub fn get_default_deactivate_message(
+    did: &str,
+    signer: String,
+    recipient: &Pubkey,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "deactivate_did",
+        &signer,
+        &[("Rent recipient", recipient.to_string())],
+        nonce,
+    )
+}
+
+fn expiry_param(expires_at: Option<i64>) -> (&'static str, String) {
+    (
+        "Expires at",
+        expires_at.map_or("never".to_string(), |t| t.to_string()),
+    )
+}
+
+pub fn get_default_claim_alias_message(
+    did: &str,
+    signer: String,
+    handle: &str,
+    expires_at: Option<i64>,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "claim_alias",
+        &signer,
+        &[("Alias", handle.to_string()), expiry_param(expires_at)],
+        nonce,
+    )
+}
+
+pub fn get_default_transfer_alias_message(
+    did: &str,
+    signer: String,
+    handle: &str,
+    new_did: &str,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "transfer_alias",
+        &signer,
+        &[
+            ("Alias", handle.to_string()),
+            ("New DID", new_did.to_string()),
+        ],
+        nonce,
+    )
+}
+
+pub fn get_default_release_alias_message(
+    did: &str,
+    signer: String,
+    handle: &str,
+    recipient: &Pubkey,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "release_alias",
+        &signer,
+        &[
+            ("Alias", handle.to_string()),
+            ("Rent recipient", recipient.to_string()),
+        ],
+        nonce,
+    )
+}
+
+pub fn get_default_set_alias_expiry_message(
+    did: &str,
+    signer: String,
+    handle: &str,
+    expires_at: Option<i64>,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "set_alias_expiry",
+        &signer,
+        &[("Alias", handle.to_string()), expiry_param(expires_at)],
+        nonce,
+    )
+}
+
+pub fn get_default_migrate_message(
+    did: &str,
+    signer: String,
+    recipient: &Pubkey,
+    nonce: i64,
+) -> String {
+    get_operation_message(
+        did,
+        "migrate_did",
+        &signer,
+        &[("Rent recipient", recipient.to_string())],
+        nonce,
+    )
+}
+
+fn credential_params(
+    credential_id: &str,
+    subject_did: &str,
+    params: &CredentialParams,
+) -> [(&'static str, String); 7] {
+    [
+        ("Credential", credential_id.to_string()),
+        ("Subject", subject_did.to_string()),
+        ("Uri", params.uri.clone()),
+        ("Hash", params.credential_hash.clone()),
+        ("Mutable", params.is_mutable.to_string()),
+        ("Revokable", params.is_revokable.to_string()),
+        expiry_param(params.expires_at),
+    ]
+}
+
 pub fn get_default_issue_credential_message(
-    credential_id: &String,
-    issuer_did: &String,
-    subject_did: &String,
-    uri: &String,
-    hash: &String,
+    issuer_did: &str,
+    signer: String,
+    credential_id: &str,
+    subject_did: &str,
+    params: &CredentialParams,
     nonce: i64,
 ) -> String {
-    format!("I am issuing a {} credential to {} for the Squircl DID with the DID {}. Uri: {}. Hash: {}. Nonce: {}", credential_id, subject_did, issuer_did, uri, hash, nonce)
+    get_operation_message(
+        issuer_did,
+        "issue_credential",
+        &signer,
+        &credential_params(credential_id, subject_did, params),
+        nonce,
+    )
 }
 
 pub fn get_default_update_credential_message(
-    credential_id: &String,
-    issuer_did: &String,
-    subject_did: &String,
-    uri: &String,
-    hash: &String,
+    issuer_did: &str,
+    signer: String,
+    credential_id: &str,
+    subject_did: &str,
+    params: &CredentialParams,
     nonce: i64,
 ) -> String {
-    format!(
-        "I am updating the {} credential issued to {} by {}. New uri: {}. New hash: {}. Nonce: {}",
-        credential_id, subject_did, issuer_did, uri, hash, nonce
+    get_operation_message(
+        issuer_did,
+        "update_credential",
+        &signer,
+        &credential_params(credential_id, subject_did, params),
+        nonce,
     )
 }
 
 pub fn get_default_revoke_credential_message(
-    credential_id: &String,
-    issuer_did: &String,
-    subject_did: &String,
+    issuer_did: &str,
+    signer: String,
+    credential_id: &str,
+    subject_did: &str,
     nonce: i64,
 ) -> String {
-    format!(
-        "I am revoking the {} credential issued to {} by {}. Nonce: {}",
-        credential_id, subject_did, issuer_did, nonce
+    get_operation_message(
+        issuer_did,
+        "revoke_credential",
+        &signer,
+        &[
+            ("Credential", credential_id.to_string()),
+            ("Subject", subject_did.to_string()),
+        ],
+        nonce,
     )
 }
 
 /// Verify Secp256k1Program instruction fields
 pub fn verify_secp256k1_ix(
+    ix_sysvar: &AccountInfo,
     ix: &Instruction,
+    slot: u8,
     eth_address: &[u8],
     msg: &[u8],
     sig: &[u8],
     recovery_id: u8,
 ) -> Result<()> {
     if ix.program_id       != SECP256K1_ID                 ||  // The program id we expect
-    ix.accounts.len()   != 0                            ||  // With no context accounts
-    ix.data.len()       != (12 + 20 + 64 + 1 + msg.len())
-    // And data of this size
+    ix.accounts.len()   != 0
+    // With no context accounts
     {
         msg!("Invalid Secp256k1 instruction");
         return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
     }
 
-    check_secp256k1_data(&ix.data, eth_address, msg, sig, recovery_id)?; // If that's not the case, check data
+    check_secp256k1_data(
+        ix_sysvar,
+        &ix.data,
+        slot,
+        eth_address,
+        msg,
+        sig,
+        recovery_id,
+    )?; // If that's not the case, check data
 
     Ok(())
 }
 
-pub fn verify_ed25519_ix(ix: &Instruction, pubkey: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
+pub fn verify_ed25519_ix(
+    ix_sysvar: &AccountInfo,
+    ix: &Instruction,
+    slot: u8,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
     if ix.program_id       != ED25519_ID                   ||  // The program id we expect
-        ix.accounts.len()   != 0                            ||  // With no context accounts
-        ix.data.len()       != (16 + 64 + 32 + msg.len())
-    // And data of this size
+        ix.accounts.len()   != 0
+    // With no context accounts
     {
         msg!("Invalid Ed25519 instruction");
         return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
     }
 
-    check_ed25519_data(&ix.data, pubkey, msg, sig)?; // If that's not the case, check data
+    check_ed25519_data(ix_sysvar, &ix.data, slot, pubkey, msg, sig)?; // If that's not the case, check data
+
+    Ok(())
+}
+
+pub fn verify_secp256r1_ix(
+    ix_sysvar: &AccountInfo,
+    ix: &Instruction,
+    slot: u8,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    if ix.program_id       != SECP256R1_PROGRAM_ID         ||  // The program id we expect
+        ix.accounts.len()   != 0
+    // With no context accounts
+    {
+        msg!("Invalid Secp256r1 instruction");
+        return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
+    }
+
+    check_secp256r1_data(ix_sysvar, &ix.data, slot, pubkey, msg, sig)?; // If that's not the case, check data
 
     Ok(())
 }
 
+fn read_u8(data: &[u8], at: usize) -> Result<u8> {
+    data.get(at)
+        .copied()
+        .ok_or(SquirclErrorCode::InvalidSignature.into())
+}
+
+fn read_u16(data: &[u8], at: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes([
+        read_u8(data, at)?,
+        read_u8(data, at + 1)?,
+    ]))
+}
+
+/// Bytes `offset..offset + len` of the instruction an offsets entry points at. `u16::MAX` is the
+/// precompile instruction itself, whose data is `current`
+fn get_precompile_data(
+    ix_sysvar: &AccountInfo,
+    current: &[u8],
+    instruction_index: u16,
+    offset: u16,
+    len: usize,
+) -> Result<Vec<u8>> {
+    let start = offset as usize;
+
+    let slice = |data: &[u8]| -> Result<Vec<u8>> {
+        data.get(start..start + len)
+            .map(|bytes| bytes.to_vec())
+            .ok_or(SquirclErrorCode::InvalidSignature.into())
+    };
+
+    if instruction_index == u16::MAX {
+        slice(current)
+    } else {
+        slice(&load_instruction_at_checked(instruction_index as usize, ix_sysvar)?.data)
+    }
+}
+
 /// Verify serialized Secp256k1Program instruction data
 pub fn check_secp256k1_data(
+    ix_sysvar: &AccountInfo,
     data: &[u8],
+    slot: u8,
     eth_address: &[u8],
     msg: &[u8],
     sig: &[u8],
     recovery_id: u8,
 ) -> Result<()> {
-    msg!("data: {:?}", data);
-
     // According to this layout used by the Secp256k1Program
     // https://github.com/solana-labs/solana-web3.js/blob/master/src/secp256k1-program.ts#L49
+    // a count byte, then one 11 byte offsets entry per signature, each pointing at data in any
+    // instruction of the transaction
 
-    // "Deserializing" byte slices
-
-    let num_signatures = &[data[0]]; // Byte  0
-    let signature_offset = &data[1..=2]; // Bytes 1,2
-    let signature_instruction_index = &[data[3]]; // Byte  3
-    let eth_address_offset = &data[4..=5]; // Bytes 4,5
-    let eth_address_instruction_index = &[data[6]]; // Byte  6
-    let message_data_offset = &data[7..=8]; // Bytes 7,8
-    let message_data_size = &data[9..=10]; // Bytes 9,10
-    let message_instruction_index = &[data[11]]; // Byte  11
-
-    let data_eth_address = &data[12..12 + 20]; // Bytes 12..12+20
-    let data_sig = &data[32..32 + 64]; // Bytes 32..32+64
-    let data_recovery_id = &[data[96]]; // Byte  96
-    let data_msg = &data[97..]; // Bytes 97..end
-
-    // Expected values
-
-    const SIGNATURE_OFFSETS_SERIALIZED_SIZE: u16 = 11;
-    const DATA_START: u16 = 1 + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
-
-    let msg_len: u16 = msg.len().try_into().unwrap();
-    let eth_address_len: u16 = eth_address.len().try_into().unwrap();
-    let sig_len: u16 = sig.len().try_into().unwrap();
-
-    let exp_eth_address_offset: u16 = DATA_START;
-    let exp_signature_offset: u16 = DATA_START + eth_address_len;
-    let exp_message_data_offset: u16 = exp_signature_offset + sig_len + 1;
-    let exp_num_signatures: u8 = 1;
-
-    // Header and Arg Checks
-
-    // Header
-    if num_signatures != &exp_num_signatures.to_le_bytes()
-        || signature_offset != &exp_signature_offset.to_le_bytes()
-        || signature_instruction_index != &[0]
-        || eth_address_offset != &exp_eth_address_offset.to_le_bytes()
-        || eth_address_instruction_index != &[0]
-        || message_data_offset != &exp_message_data_offset.to_le_bytes()
-        || message_data_size != &msg_len.to_le_bytes()
-        || message_instruction_index != &[0]
-    {
-        msg!("Invalid Secp256k1 instruction data (header)");
-        return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
+    const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 11;
+
+    let num_signatures = read_u8(data, 0)?; // Byte  0
+
+    if slot >= num_signatures {
+        msg!("Invalid Secp256k1 instruction data (slot)");
+        return Err(SquirclErrorCode::InvalidSignature.into());
     }
 
-    // the above check is failing, figure out by logging which check is actually failing
-
-    // if num_signatures != &exp_num_signatures.to_le_bytes() {
-    //     msg!("num_signatures: {:?}", num_signatures);
-    //     msg!("exp_num_signatures: {:?}", exp_num_signatures.to_le_bytes());
-    //     msg!("num_signatures != exp_num_signatures");
-    // }
-
-    // if signature_offset != &exp_signature_offset.to_le_bytes() {
-    //     msg!("signature_offset: {:?}", signature_offset);
-    //     msg!(
-    //         "exp_signature_offset: {:?}",
-    //         exp_signature_offset.to_le_bytes()
-    //     );
-    //     msg!("signature_offset != exp_signature_offset");
-    // }
-
-    // if signature_instruction_index != &[0] {
-    //     msg!(
-    //         "signature_instruction_index: {:?}",
-    //         signature_instruction_index
-    //     );
-    //     msg!("signature_instruction_index != &[0]");
-    // }
-
-    // if eth_address_offset != &exp_eth_address_offset.to_le_bytes() {
-    //     msg!("eth_address_offset: {:?}", eth_address_offset);
-    //     msg!(
-    //         "exp_eth_address_offset: {:?}",
-    //         exp_eth_address_offset.to_le_bytes()
-    //     );
-    //     msg!("eth_address_offset != exp_eth_address_offset");
-    // }
-
-    // if eth_address_instruction_index != &[0] {
-    //     msg!(
-    //         "eth_address_instruction_index: {:?}",
-    //         eth_address_instruction_index
-    //     );
-    //     msg!("eth_address_instruction_index != &[0]");
-    // }
-
-    // if message_data_offset != &exp_message_data_offset.to_le_bytes() {
-    //     msg!("message_data_offset: {:?}", message_data_offset);
-    //     msg!(
-    //         "exp_message_data_offset: {:?}",
-    //         exp_message_data_offset.to_le_bytes()
-    //     );
-    //     msg!("message_data_offset != exp_message_data_offset");
-    // }
-
-    // if message_data_size != &msg_len.to_le_bytes() {
-    //     msg!("message_data_size: {:?}", message_data_size);
-    //     msg!("msg_len: {:?}", msg_len);
-    //     msg!("message_data_size != msg_len");
-    // }
-
-    // if message_instruction_index != &[0] {
-    //     msg!("message_instruction_index: {:?}", message_instruction_index);
-    //     msg!("message_instruction_index != &[0]");
-    // }
-
-    // the above check is failing, figure out by logging which check is actually failing
-
-    // if data_eth_address != eth_address {
-    //     msg!("data_eth_address: {:?}", data_eth_address);
-    //     msg!("eth_address: {:?}", eth_address);
-    //     msg!("data_eth_address != eth_address");
-    // }
-
-    // if data_sig != sig {
-    //     msg!("data_sig: {:?}", data_sig);
-    //     msg!("sig: {:?}", sig);
-    //     msg!("data_sig != sig");
-    // }
-
-    // if data_recovery_id != &[recovery_id] {
-    //     msg!("data_recovery_id: {:?}", data_recovery_id);
-    //     msg!("recovery_id: {:?}", recovery_id);
-    //     msg!("data_recovery_id != &[recovery_id]");
-    // }
-
-    // if data_msg != msg {
-    //     msg!("data_msg: {:?}", data_msg);
-    //     msg!("msg: {:?}", msg);
-    //     msg!("data_msg != msg");
-    // }
+    // "Deserializing" the offsets entry of the slot
+
+    let entry = 1 + slot as usize * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+
+    let signature_offset = read_u16(data, entry)?; // Bytes 0,1
+    let signature_instruction_index = read_u8(data, entry + 2)?; // Byte  2
+    let eth_address_offset = read_u16(data, entry + 3)?; // Bytes 3,4
+    let eth_address_instruction_index = read_u8(data, entry + 5)?; // Byte  5
+    let message_data_offset = read_u16(data, entry + 6)?; // Bytes 6,7
+    let message_data_size = read_u16(data, entry + 8)?; // Bytes 8,9
+    let message_instruction_index = read_u8(data, entry + 10)?; // Byte  10
+
+    // the recovery id is the byte after the signature
+    let data_sig = get_precompile_data(
+        ix_sysvar,
+        data,
+        signature_instruction_index.into(),
+        signature_offset,
+        64 + 1,
+    )?;
+    let data_eth_address = get_precompile_data(
+        ix_sysvar,
+        data,
+        eth_address_instruction_index.into(),
+        eth_address_offset,
+        20,
+    )?;
+    let data_msg = get_precompile_data(
+        ix_sysvar,
+        data,
+        message_instruction_index.into(),
+        message_data_offset,
+        message_data_size as usize,
+    )?;
 
     // Arguments
     if data_eth_address != eth_address
-        || data_sig != sig
-        || data_recovery_id != &[recovery_id]
+        || data_sig[..64] != *sig
+        || data_sig[64] != recovery_id
         || data_msg != msg
     {
         msg!("Invalid Secp256k1 instruction data (arguments)");
@@ -304,55 +856,102 @@ pub fn check_secp256k1_data(
     Ok(())
 }
 
+/// Public key, signature and message of a slot of an Ed25519Program or Secp256r1Program
+/// instruction, both share this layout
+/// https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
+fn get_ed25519_layout_data(
+    ix_sysvar: &AccountInfo,
+    data: &[u8],
+    slot: u8,
+    pubkey_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    // a count byte and a padding byte, then one 14 byte offsets entry per signature
+
+    const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+
+    let num_signatures = read_u8(data, 0)?; // Byte  0
+
+    if slot >= num_signatures {
+        msg!("Invalid precompile instruction data (slot)");
+        return Err(SquirclErrorCode::InvalidSignature.into());
+    }
+
+    // "Deserializing" the offsets entry of the slot
+
+    let entry = 2 + slot as usize * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+
+    let signature_offset = read_u16(data, entry)?; // Bytes 0,1
+    let signature_instruction_index = read_u16(data, entry + 2)?; // Bytes 2,3
+    let public_key_offset = read_u16(data, entry + 4)?; // Bytes 4,5
+    let public_key_instruction_index = read_u16(data, entry + 6)?; // Bytes 6,7
+    let message_data_offset = read_u16(data, entry + 8)?; // Bytes 8,9
+    let message_data_size = read_u16(data, entry + 10)?; // Bytes 10,11
+    let message_instruction_index = read_u16(data, entry + 12)?; // Bytes 12,13
+
+    Ok((
+        get_precompile_data(
+            ix_sysvar,
+            data,
+            public_key_instruction_index,
+            public_key_offset,
+            pubkey_len,
+        )?,
+        get_precompile_data(
+            ix_sysvar,
+            data,
+            signature_instruction_index,
+            signature_offset,
+            64,
+        )?,
+        get_precompile_data(
+            ix_sysvar,
+            data,
+            message_instruction_index,
+            message_data_offset,
+            message_data_size as usize,
+        )?,
+    ))
+}
+
 /// Verify serialized Ed25519Program instruction data
-pub fn check_ed25519_data(data: &[u8], pubkey: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
-    // According to this layout used by the Ed25519Program
-    // https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
-
-    // "Deserializing" byte slices
-
-    let num_signatures = &[data[0]]; // Byte  0
-    let padding = &[data[1]]; // Byte  1
-    let signature_offset = &data[2..=3]; // Bytes 2,3
-    let signature_instruction_index = &data[4..=5]; // Bytes 4,5
-    let public_key_offset = &data[6..=7]; // Bytes 6,7
-    let public_key_instruction_index = &data[8..=9]; // Bytes 8,9
-    let message_data_offset = &data[10..=11]; // Bytes 10,11
-    let message_data_size = &data[12..=13]; // Bytes 12,13
-    let message_instruction_index = &data[14..=15]; // Bytes 14,15
-
-    let data_pubkey = &data[16..16 + 32]; // Bytes 16..16+32
-    let data_sig = &data[48..48 + 64]; // Bytes 48..48+64
-    let data_msg = &data[112..]; // Bytes 112..end
-
-    // Expected values
-
-    let exp_public_key_offset: u16 = 16; // 2*u8 + 7*u16
-    let exp_signature_offset: u16 = exp_public_key_offset + pubkey.len() as u16;
-    let exp_message_data_offset: u16 = exp_signature_offset + sig.len() as u16;
-    let exp_num_signatures: u8 = 1;
-    let exp_message_data_size: u16 = msg.len().try_into().unwrap();
-
-    // Header and Arg Checks
-
-    // Header
-    if num_signatures != &exp_num_signatures.to_le_bytes()
-        || padding != &[0]
-        || signature_offset != &exp_signature_offset.to_le_bytes()
-        || signature_instruction_index != &u16::MAX.to_le_bytes()
-        || public_key_offset != &exp_public_key_offset.to_le_bytes()
-        || public_key_instruction_index != &u16::MAX.to_le_bytes()
-        || message_data_offset != &exp_message_data_offset.to_le_bytes()
-        || message_data_size != &exp_message_data_size.to_le_bytes()
-        || message_instruction_index != &u16::MAX.to_le_bytes()
-    {
-        msg!("Invalid Ed25519 instruction data (header)");
+pub fn check_ed25519_data(
+    ix_sysvar: &AccountInfo,
+    data: &[u8],
+    slot: u8,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    let (data_pubkey, data_sig, data_msg) = get_ed25519_layout_data(ix_sysvar, data, slot, 32)?;
+
+    // Hardware wallets sign the message wrapped in the off-chain message envelope instead
+    let is_signed_msg = data_msg == msg || data_msg == get_offchain_message(msg);
+
+    // Arguments
+    if data_pubkey != pubkey || !is_signed_msg || data_sig != sig {
+        msg!("Invalid Ed25519 instruction data (arguments)");
         return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
     }
 
+    Ok(())
+}
+
+/// Verify serialized Secp256r1Program instruction data
+pub fn check_secp256r1_data(
+    ix_sysvar: &AccountInfo,
+    data: &[u8],
+    slot: u8,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    // The Secp256r1Program uses the Ed25519Program layout, with a 33 byte compressed public key
+    // https://github.com/solana-foundation/solana-improvement-documents/blob/main/proposals/0075-precompile-for-secp256r1-sigverify.md
+    let (data_pubkey, data_sig, data_msg) = get_ed25519_layout_data(ix_sysvar, data, slot, 33)?;
+
     // Arguments
     if data_pubkey != pubkey || data_msg != msg || data_sig != sig {
-        msg!("Invalid Ed25519 instruction data (arguments)");
+        msg!("Invalid Secp256r1 instruction data (arguments)");
         return Err(SquirclErrorCode::InvalidSignature.into()); // Otherwise, we can already throw err
     }
 
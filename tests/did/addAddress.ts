@@ -11,6 +11,11 @@ import {
   createDIDEVM,
   createDIDSOL,
 } from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+} from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
 import { expect } from "chai";
 import nacl from "tweetnacl";
 import bs58 from "bs58";
@@ -28,7 +33,12 @@ export const addAddressEth = async (
 
   const ethSigner = ethers.Wallet.createRandom();
 
-  const message = `I am creating a new Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
 
   const { actual_message, signature, recoveryId } = await signEthMessage(
     message,
@@ -49,8 +59,24 @@ export const addAddressEth = async (
 
   const newEthSigner = ethers.Wallet.createRandom();
 
-  const newAddressMessageAsNewAddress = `I am adding myself to the Squircl DID with the address ${newEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
-  const newAddressMessageAsController = `I am adding ${newEthSigner.address.toLowerCase()} to the Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
 
   const {
     actual_message: newAddressActualMessage,
@@ -78,7 +104,7 @@ export const addAddressEth = async (
     newAddressRecoveryId,
     controllerActualMessage,
     newAddressActualMessage,
-    nonce
+    nonce + 1
   );
 
   // console.log("add address sig", sig);
@@ -87,7 +113,7 @@ export const addAddressEth = async (
 
   expect(didAccountData.did).to.equal(didStr);
   expect(didAccountData.ethAddresses.length).to.equal(2);
-  expect(didAccountData.ethAddresses[1].address).to.equal(
+  expect(getAddressString(didAccountData.ethAddresses[1])).to.equal(
     newEthSigner.address.toLowerCase()
   );
 
@@ -98,8 +124,24 @@ export const addAddressEth = async (
 
   const keypair = anchor.web3.Keypair.generate();
 
-  const newAddressMessageAsNewAddressSOL = `I am adding myself to the Squircl DID with the address ${keypair.publicKey.toBase58()}. Nonce: ${nonce}`;
-  const newAddressMessageAsControllerSOL = `I am adding ${keypair.publicKey.toBase58()} to the Squircl DID with the address ${ethSigner.address.toLocaleLowerCase()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddressSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 2
+  );
+  const newAddressMessageAsControllerSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 2
+  );
 
   const newMessageEncoded = Uint8Array.from(
     Buffer.from(newAddressMessageAsNewAddressSOL)
@@ -128,7 +170,7 @@ export const addAddressEth = async (
     keypair.publicKey,
     newAddressSOLSignature,
     newMessageEncoded,
-    nonce
+    nonce + 2
   );
 
   // console.log("add address sig", sigSOL);
@@ -137,7 +179,7 @@ export const addAddressEth = async (
 
   expect(didAccountDataSOL.did).to.equal(didStr);
   expect(didAccountDataSOL.solAddresses.length).to.equal(1);
-  expect(didAccountDataSOL.solAddresses[0].address).to.equal(
+  expect(getAddressString(didAccountDataSOL.solAddresses[0])).to.equal(
     keypair.publicKey.toBase58()
   );
 
@@ -158,7 +200,12 @@ export const addAddressSol = async (
 
   const controllerKeypair = anchor.web3.Keypair.generate();
 
-  const message = `I am creating a new Squircl DID with the address ${controllerKeypair.publicKey.toBase58()}. Nonce: ${nonce}`;
+  const message = createMessage(
+    program.programId,
+    didStr,
+    controllerKeypair.publicKey.toBase58(),
+    nonce
+  );
 
   const messageEncoded = Uint8Array.from(Buffer.from(message));
 
@@ -180,8 +227,24 @@ export const addAddressSol = async (
 
   const newEthSigner = ethers.Wallet.createRandom();
 
-  const newAddressMessageAsNewAddress = `I am adding myself to the Squircl DID with the address ${newEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
-  const newAddressMessageAsController = `I am adding ${newEthSigner.address.toLowerCase()} to the Squircl DID with the address ${controllerKeypair.publicKey.toBase58()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    controllerKeypair.publicKey.toBase58(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
 
   const newAddressMessageAsControllerEncoded = Uint8Array.from(
     Buffer.from(newAddressMessageAsController)
@@ -211,7 +274,7 @@ export const addAddressSol = async (
     newAddressSignature,
     newAddressRecoveryId,
     newAddressActualMessage,
-    nonce
+    nonce + 1
   );
 
   // console.log("add address sig", sigEVM);
@@ -220,7 +283,7 @@ export const addAddressSol = async (
 
   expect(didAccountData.did).to.equal(didStr);
   expect(didAccountData.ethAddresses.length).to.equal(1);
-  expect(didAccountData.ethAddresses[0].address).to.equal(
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
     newEthSigner.address.toLowerCase()
   );
 
@@ -230,8 +293,24 @@ export const addAddressSol = async (
 
   const keypair = anchor.web3.Keypair.generate();
 
-  const newAddressMessageAsNewAddressSOL = `I am adding myself to the Squircl DID with the address ${keypair.publicKey.toBase58()}. Nonce: ${nonce}`;
-  const newAddressMessageAsControllerSOL = `I am adding ${keypair.publicKey.toBase58()} to the Squircl DID with the address ${controllerKeypair.publicKey.toBase58()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddressSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 2
+  );
+  const newAddressMessageAsControllerSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    controllerKeypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 2
+  );
 
   const newMessageEncoded = Uint8Array.from(
     Buffer.from(newAddressMessageAsNewAddressSOL)
@@ -262,7 +341,7 @@ export const addAddressSol = async (
     keypair.publicKey,
     newAddressSOLSignature,
     newMessageEncoded,
-    nonce
+    nonce + 2
   );
 
   // console.log("add address sig", sigSOL);
@@ -271,7 +350,7 @@ export const addAddressSol = async (
 
   expect(didAccountDataSOL.did).to.equal(didStr);
   expect(didAccountDataSOL.solAddresses.length).to.equal(2);
-  expect(didAccountDataSOL.solAddresses[1].address).to.equal(
+  expect(getAddressString(didAccountDataSOL.solAddresses[1])).to.equal(
     keypair.publicKey.toBase58()
   );
 
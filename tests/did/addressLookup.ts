@@ -0,0 +1,171 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getAddressLookupAccount,
+  getDIDAccount,
+} from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  createDIDEVM,
+  removeAddressEVMRemover,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  removeAddressMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+
+const createDID = async (
+  program: Program<SquirclDid>,
+  payer: any,
+  controller: HDNodeWallet,
+  nonce: number
+) => {
+  const didStr = generateRandomDID();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      nonce
+    ),
+    controller
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    controller,
+    signature,
+    recoveryId,
+    getDIDAccount(didStr, program),
+    actual_message,
+    payer,
+    nonce
+  );
+
+  return didStr;
+};
+
+export const addressLookupTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const controllerLookup = getAddressLookupAccount(
+    "evm",
+    ethSigner.address.toLowerCase(),
+    program
+  );
+
+  // the controller of a new did can find it with a single fetch
+
+  const didStr = await createDID(program, payer, ethSigner, nonce);
+
+  expect(
+    (await program.account.addressLookup.fetch(controllerLookup)).dids
+  ).to.deep.equal([didStr]);
+
+  // without the one-did-per-address feature an address can be in several dids
+
+  const otherDidStr = await createDID(program, payer, ethSigner, nonce);
+
+  expect(
+    (await program.account.addressLookup.fetch(controllerLookup)).dids
+  ).to.deep.equal([didStr, otherDidStr]);
+
+  // added addresses get a lookup too
+
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const newAddressLookup = getAddressLookupAccount(
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    program
+  );
+
+  const signAddMessage = (signer: HDNodeWallet) =>
+    signEthMessage(
+      addAddressMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        newEthSigner.address.toLowerCase(),
+        "admin",
+        nonce + 1
+      ),
+      signer
+    );
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signAddMessage(ethSigner);
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signAddMessage(newEthSigner);
+
+  await addAddressEVMwithEVMController(
+    program,
+    didStr,
+    getDIDAccount(didStr, program),
+    payer,
+    ethSigner,
+    newEthSigner,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1
+  );
+
+  expect(
+    (await program.account.addressLookup.fetch(newAddressLookup)).dids
+  ).to.deep.equal([didStr]);
+
+  // removing the address from its only did closes its lookup
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    removeAddressMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      "evm",
+      newEthSigner.address.toLowerCase(),
+      nonce + 2
+    ),
+    ethSigner
+  );
+
+  await removeAddressEVMRemover(
+    program,
+    didStr,
+    getDIDAccount(didStr, program),
+    payer,
+    ethSigner,
+    signature,
+    recoveryId,
+    actual_message,
+    newEthSigner.address.toLowerCase(),
+    { evm: {} },
+    nonce + 2
+  );
+
+  expect(await program.account.addressLookup.fetchNullable(newAddressLookup))
+    .to.be.null;
+};
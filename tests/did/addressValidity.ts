@@ -0,0 +1,246 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getAddressLookupAccount,
+  getDIDAccount,
+} from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage, signEthMessageWithNonce } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  AddressValidity,
+  addServiceEVM,
+  createDIDEVM,
+  pruneAddresses,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  addServiceMessage,
+  createMessage,
+} from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
+import { expect } from "chai";
+
+// long enough for the temporary address to be added before it expires
+const TEMPORARY_ADDRESS_LIFETIME = 5;
+
+// vec prefix, 20 byte key, added_at, chain, role, valid_from and valid_until
+const EVM_ADDRESS_LEN = 52;
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+// the cluster clock may lag behind the local one, waits for it to pass `time`
+const waitForClock = async (program: Program<SquirclDid>, time: number) => {
+  const connection = program.provider.connection;
+
+  while ((await connection.getBlockTime(await connection.getSlot())) <= time) {
+    await sleep(500);
+  }
+};
+
+export const addressValidityTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+  const temporarySigner = ethers.Wallet.createRandom();
+  const futureSigner = ethers.Wallet.createRandom();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const addAddress = async (
+    newSigner: HDNodeWallet,
+    validity: AddressValidity,
+    addNonce: number
+  ) => {
+    const signAddMessage = (signer: HDNodeWallet) =>
+      signEthMessage(
+        addAddressMessage(
+          program.programId,
+          didStr,
+          signer.address.toLowerCase(),
+          "evm",
+          newSigner.address.toLowerCase(),
+          "admin",
+          addNonce,
+          validity.validFrom,
+          validity.validUntil
+        ),
+        signer
+      );
+
+    const {
+      actual_message: controllerActualMessage,
+      signature: controllerSignature,
+      recoveryId: controllerRecoveryId,
+    } = await signAddMessage(ethSigner);
+
+    const {
+      actual_message: newAddressActualMessage,
+      signature: newAddressSignature,
+      recoveryId: newAddressRecoveryId,
+    } = await signAddMessage(newSigner);
+
+    await addAddressEVMwithEVMController(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      ethSigner,
+      newSigner,
+      controllerSignature,
+      controllerRecoveryId,
+      newAddressSignature,
+      newAddressRecoveryId,
+      controllerActualMessage,
+      newAddressActualMessage,
+      addNonce,
+      { admin: {} },
+      validity
+    );
+  };
+
+  const addService = async (signer: HDNodeWallet, serviceNonce: number) => {
+    const service = {
+      id: `hub-${serviceNonce}`,
+      serviceType: "CredentialHub",
+      endpoint: "https://hub.squircl.xyz",
+    };
+
+    await addServiceEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      await signEthMessageWithNonce(
+        addServiceMessage(
+          program.programId,
+          didStr,
+          signer.address.toLowerCase(),
+          service,
+          serviceNonce
+        ),
+        signer,
+        serviceNonce
+      ),
+      service
+    );
+  };
+
+  // a validity window must end in the future
+
+  try {
+    await addAddress(temporarySigner, { validUntil: nonce - 1 }, nonce + 1);
+    expect.fail("an address was added already expired");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidAddressValidity");
+  }
+
+  // a temporary address can be used until it expires
+
+  const validUntil = Math.floor(Date.now() / 1000) + TEMPORARY_ADDRESS_LIFETIME;
+
+  await addAddress(temporarySigner, { validUntil }, nonce + 2);
+  await addService(temporarySigner, nonce + 3);
+
+  // an address can't be used before its window starts
+
+  const validFrom = nonce + 3600;
+
+  await addAddress(futureSigner, { validFrom }, nonce + 4);
+
+  let didAccountData = await program.account.did.fetch(didAccount);
+
+  const [, temporaryAddress, futureAddress] = didAccountData.ethAddresses;
+
+  expect(temporaryAddress.validUntil.toNumber()).to.equal(validUntil);
+  expect(temporaryAddress.validFrom).to.be.null;
+  expect(futureAddress.validFrom.toNumber()).to.equal(validFrom);
+  expect(futureAddress.validUntil).to.be.null;
+
+  try {
+    await addService(futureSigner, nonce + 5);
+    expect.fail("an address was used before it was valid");
+  } catch (e) {
+    expect(e.toString()).to.contain("AddressNotValid");
+  }
+
+  // nothing can be pruned before an address expires
+
+  try {
+    await pruneAddresses(program, didStr, didAccount, payer);
+    expect.fail("a did without expired addresses was pruned");
+  } catch (e) {
+    expect(e.toString()).to.contain("NoExpiredAddresses");
+  }
+
+  // once it expires the temporary address can't be used anymore
+
+  await waitForClock(program, validUntil);
+
+  try {
+    await addService(temporarySigner, nonce + 6);
+    expect.fail("an expired address was used");
+  } catch (e) {
+    expect(e.toString()).to.contain("AddressNotValid");
+  }
+
+  // and anyone can prune it, along with its lookup
+
+  const temporaryLookup = getAddressLookupAccount(
+    "evm",
+    temporarySigner.address.toLowerCase(),
+    program
+  );
+
+  const { data } = await program.provider.connection.getAccountInfo(
+    didAccount
+  );
+
+  try {
+    await pruneAddresses(program, didStr, didAccount, payer);
+    expect.fail("an address was pruned without its lookup");
+  } catch (e) {
+    expect(e.toString()).to.contain("MissingAddressLookup");
+  }
+
+  await pruneAddresses(program, didStr, didAccount, payer, [temporaryLookup]);
+
+  didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.ethAddresses.map(getAddressString)).to.deep.equal([
+    ethSigner.address.toLowerCase(),
+    futureSigner.address.toLowerCase(),
+  ]);
+  expect(
+    (await program.provider.connection.getAccountInfo(didAccount)).data.length
+  ).to.equal(data.length - EVM_ADDRESS_LEN);
+  expect(await program.account.addressLookup.fetchNullable(temporaryLookup)).to
+    .be.null;
+};
@@ -0,0 +1,267 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getAliasAccount,
+  getDIDAccount,
+} from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  claimAliasEVM,
+  createDIDEVM,
+  releaseAliasEVM,
+  setAliasExpiryEVM,
+  transferAliasEVM,
+} from "../utils/instructions";
+import {
+  claimAliasMessage,
+  createMessage,
+  releaseAliasMessage,
+  setAliasExpiryMessage,
+  transferAliasMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+import crypto from "crypto";
+import * as anchor from "@project-serum/anchor";
+
+const createDID = async (
+  program: Program<SquirclDid>,
+  payer: any,
+  controller: HDNodeWallet,
+  nonce: number
+) => {
+  const didStr = generateRandomDID();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      nonce
+    ),
+    controller
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    controller,
+    signature,
+    recoveryId,
+    getDIDAccount(didStr, program),
+    actual_message,
+    payer,
+    nonce
+  );
+
+  return didStr;
+};
+
+const claimAlias = async (
+  program: Program<SquirclDid>,
+  payer: any,
+  didStr: string,
+  controller: HDNodeWallet,
+  handle: string,
+  nonce: number
+) => {
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    claimAliasMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      handle,
+      null,
+      nonce
+    ),
+    controller
+  );
+
+  await claimAliasEVM(
+    program,
+    didStr,
+    getDIDAccount(didStr, program),
+    payer,
+    controller,
+    signature,
+    recoveryId,
+    actual_message,
+    handle,
+    null,
+    nonce
+  );
+};
+
+const didSeed = (didStr: string) =>
+  Array.from(crypto.createHash("sha256").update(didStr, "utf-8").digest());
+
+export const aliasTest = async (program: Program<SquirclDid>, payer: any) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const ethSigner = ethers.Wallet.createRandom();
+  const otherEthSigner = ethers.Wallet.createRandom();
+
+  const didStr = await createDID(program, payer, ethSigner, nonce);
+  const otherDidStr = await createDID(program, payer, otherEthSigner, nonce);
+
+  const handle = `alias-${crypto.randomBytes(4).toString("hex")}`;
+  const aliasAccount = getAliasAccount(handle, program);
+
+  // the controller claims a handle that points at its did
+
+  await claimAlias(program, payer, didStr, ethSigner, handle, nonce + 1);
+
+  let alias = await program.account.alias.fetch(aliasAccount);
+
+  expect(alias.handle).to.equal(handle);
+  expect(alias.didSeed).to.deep.equal(didSeed(didStr));
+  expect(alias.expiresAt).to.be.null;
+
+  // handles are unique and must be valid seeds
+
+  try {
+    await claimAlias(
+      program,
+      payer,
+      otherDidStr,
+      otherEthSigner,
+      handle,
+      nonce + 1
+    );
+    expect.fail("a claimed alias was claimed again");
+  } catch (e) {
+    expect(e.toString()).to.contain("AliasAlreadyClaimed");
+  }
+
+  try {
+    await claimAlias(program, payer, didStr, ethSigner, "Not-Valid", nonce + 2);
+    expect.fail("an invalid alias was claimed");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidAlias");
+  }
+
+  // the controller can set the alias to expire
+
+  const expiresAt = nonce + 3600;
+
+  const {
+    actual_message: expiryActualMessage,
+    signature: expirySignature,
+    recoveryId: expiryRecoveryId,
+  } = await signEthMessage(
+    setAliasExpiryMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      handle,
+      expiresAt,
+      nonce + 3
+    ),
+    ethSigner
+  );
+
+  await setAliasExpiryEVM(
+    program,
+    didStr,
+    getDIDAccount(didStr, program),
+    payer,
+    ethSigner,
+    expirySignature,
+    expiryRecoveryId,
+    expiryActualMessage,
+    handle,
+    expiresAt,
+    nonce + 3
+  );
+
+  alias = await program.account.alias.fetch(aliasAccount);
+
+  expect(alias.expiresAt.toNumber()).to.equal(expiresAt);
+
+  // and transfer it to another did
+
+  const {
+    actual_message: transferActualMessage,
+    signature: transferSignature,
+    recoveryId: transferRecoveryId,
+  } = await signEthMessage(
+    transferAliasMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      handle,
+      otherDidStr,
+      nonce + 4
+    ),
+    ethSigner
+  );
+
+  await transferAliasEVM(
+    program,
+    didStr,
+    getDIDAccount(didStr, program),
+    payer,
+    ethSigner,
+    transferSignature,
+    transferRecoveryId,
+    transferActualMessage,
+    handle,
+    otherDidStr,
+    nonce + 4
+  );
+
+  alias = await program.account.alias.fetch(aliasAccount);
+
+  expect(alias.didSeed).to.deep.equal(didSeed(otherDidStr));
+
+  // only the did it points at can release it
+
+  const recipient = anchor.web3.Keypair.generate().publicKey;
+
+  const release = async (
+    releaseDidStr: string,
+    controller: HDNodeWallet,
+    releaseNonce: number
+  ) => {
+    const { actual_message, signature, recoveryId } = await signEthMessage(
+      releaseAliasMessage(
+        program.programId,
+        releaseDidStr,
+        controller.address.toLowerCase(),
+        handle,
+        recipient,
+        releaseNonce
+      ),
+      controller
+    );
+
+    await releaseAliasEVM(
+      program,
+      releaseDidStr,
+      getDIDAccount(releaseDidStr, program),
+      payer,
+      controller,
+      signature,
+      recoveryId,
+      actual_message,
+      handle,
+      recipient,
+      releaseNonce
+    );
+  };
+
+  try {
+    await release(didStr, ethSigner, nonce + 5);
+    expect.fail("an alias was released by a did it doesn't point at");
+  } catch (e) {
+    expect(e.toString()).to.contain("AliasNotOwned");
+  }
+
+  await release(otherDidStr, otherEthSigner, nonce + 5);
+
+  expect(await program.account.alias.fetchNullable(aliasAccount)).to.be.null;
+  expect(
+    await program.provider.connection.getBalance(recipient)
+  ).to.be.greaterThan(0);
+};
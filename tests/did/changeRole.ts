@@ -0,0 +1,189 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  changeRoleEVMChanger,
+  createDIDEVM,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  changeRoleMessage,
+  createMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+
+export const changeRoleTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    message,
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "authentication",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "authentication",
+    nonce + 1
+  );
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signEthMessage(newAddressMessageAsNewAddress, newEthSigner);
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signEthMessage(newAddressMessageAsController, ethSigner);
+
+  await addAddressEVMwithEVMController(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    newEthSigner,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1,
+    { authentication: {} }
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.ethAddresses[1].role).to.deep.equal({
+    authentication: {},
+  });
+
+  // an authentication address cannot promote itself
+
+  const selfPromoteMessage = changeRoleMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 2
+  );
+
+  const {
+    actual_message: selfPromoteActualMessage,
+    signature: selfPromoteSignature,
+    recoveryId: selfPromoteRecoveryId,
+  } = await signEthMessage(selfPromoteMessage, newEthSigner);
+
+  try {
+    await changeRoleEVMChanger(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      newEthSigner,
+      selfPromoteSignature,
+      selfPromoteRecoveryId,
+      selfPromoteActualMessage,
+      newEthSigner.address.toLowerCase(),
+      { evm: {} },
+      { admin: {} },
+      nonce + 2
+    );
+    expect.fail("authentication address promoted itself");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: AddressDoesntHaveEnoughPermissions. Error Number: 6004. Error Message: Address doesn't have enough permissions."
+    );
+  }
+
+  const promoteMessage = changeRoleMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 3
+  );
+
+  const {
+    actual_message: promoteActualMessage,
+    signature: promoteSignature,
+    recoveryId: promoteRecoveryId,
+  } = await signEthMessage(promoteMessage, ethSigner);
+
+  await changeRoleEVMChanger(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    promoteSignature,
+    promoteRecoveryId,
+    promoteActualMessage,
+    newEthSigner.address.toLowerCase(),
+    { evm: {} },
+    { admin: {} },
+    nonce + 3
+  );
+
+  const didAccountData2 = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData2.ethAddresses[1].role).to.deep.equal({
+    admin: {},
+  });
+  expect(didAccountData2.ethAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+};
@@ -1,9 +1,35 @@
 import { Program } from "@project-serum/anchor";
-import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import {
+  generateRandomDID,
+  getAddressLookupAccount,
+  getDIDAccount,
+  getDerivedDID,
+  getEventAuthorityAccount,
+  getNonceRegistryAccount,
+} from "../utils/pda";
 import { SquirclDid } from "../../target/types/squircl_did";
 import { ethers, hexlify } from "ethers";
-import { signEthMessage } from "../utils/signatures";
-import { createDIDEVM, createDIDSOL } from "../utils/instructions";
+import {
+  createPasskey,
+  getBtcAddress,
+  signBtcMessage,
+  signEthMessage,
+  signEthTypedData,
+  signPasskeyMessage,
+  toOffchainMessage,
+} from "../utils/signatures";
+import {
+  createDIDBTC,
+  createDIDEIP712,
+  createDIDEVM,
+  createDIDEVMBytes,
+  createDIDEVMRecover,
+  createDIDPasskey,
+  createDIDSOL,
+  createDIDSOLBytes,
+} from "../utils/instructions";
+import { createMessage } from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
 import { expect } from "chai";
 import bs58 from "bs58";
 import nacl from "tweetnacl";
@@ -21,7 +47,12 @@ export const createDIDEthereumTest = async (
 
   const nonce = Math.floor(Date.now() / 1000);
 
-  const message = `I am creating a new Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
 
   const { actual_message, signature, recoveryId, full_sig_bytes } =
     await signEthMessage(message, ethSigner);
@@ -59,7 +90,7 @@ export const createDIDEthereumTest = async (
 
   expect(didAccountData.did).to.equal(didStr);
   expect(didAccountData.ethAddresses.length).to.equal(1);
-  expect(didAccountData.ethAddresses[0].address).to.equal(
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
     ethSigner.address.toLowerCase()
   );
 
@@ -80,7 +111,12 @@ export const createDIDSolTest = async (
   const nonce = Math.floor(Date.now() / 1000);
 
   const keypair = anchor.web3.Keypair.generate();
-  const message = `I am creating a new Squircl DID with the address ${keypair.publicKey.toBase58()}. Nonce: ${nonce}`;
+  const message = createMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    nonce
+  );
 
   const messageEncoded = Uint8Array.from(Buffer.from(message));
 
@@ -103,7 +139,7 @@ export const createDIDSolTest = async (
 
   expect(didAccountData.did).to.equal(didStr);
   expect(didAccountData.solAddresses.length).to.equal(1);
-  expect(didAccountData.solAddresses[0].address).to.equal(
+  expect(getAddressString(didAccountData.solAddresses[0])).to.equal(
     keypair.publicKey.toBase58()
   );
 
@@ -185,3 +221,466 @@ export const createDIDSolTestInvalidSig = async (
     );
   }
 };
+
+export const createDIDInvalidDidStrTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const keypair = anchor.web3.Keypair.generate();
+
+  for (const [didStr, errorCode] of [
+    ["not-a-did", "InvalidDid"],
+    ["did:squircl:Upper-Case", "DidNotCanonical"],
+    ["did:squircl:has spaces", "InvalidDid"],
+    ["did:squircl:" + "a".repeat(49), "InvalidDid"],
+    [getDerivedDID("sol", "someone-else"), "DerivedDidMismatch"],
+  ]) {
+    const message = createMessage(
+      program.programId,
+      didStr,
+      keypair.publicKey.toBase58(),
+      nonce
+    );
+
+    const messageEncoded = Uint8Array.from(Buffer.from(message));
+
+    const signature = nacl.sign.detached(messageEncoded, keypair.secretKey);
+
+    try {
+      await createDIDSOL(
+        program,
+        didStr,
+        keypair,
+        signature,
+        messageEncoded,
+        getDIDAccount(didStr, program),
+        payer,
+        nonce
+      );
+      expect.fail(`${didStr} was accepted`);
+    } catch (e) {
+      expect(e.toString()).to.contain(errorCode);
+    }
+  }
+};
+
+export const createDerivedDIDSolTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const keypair = anchor.web3.Keypair.generate();
+
+  const didStr = getDerivedDID("sol", keypair.publicKey.toBase58());
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    nonce
+  );
+
+  const messageEncoded = Uint8Array.from(Buffer.from(message));
+
+  const signature = nacl.sign.detached(messageEncoded, keypair.secretKey);
+
+  await createDIDSOL(
+    program,
+    didStr,
+    keypair,
+    signature,
+    messageEncoded,
+    didAccount,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.did).to.equal(didStr);
+  expect(getAddressString(didAccountData.solAddresses[0])).to.equal(
+    keypair.publicKey.toBase58()
+  );
+};
+
+export const createDIDBtcTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const { signingKey } = ethers.Wallet.createRandom();
+
+  const address = getBtcAddress(signingKey);
+
+  const { signature } = signBtcMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      address,
+      nonce
+    ),
+    signingKey
+  );
+
+  await createDIDBTC(
+    program,
+    didStr,
+    address,
+    signature,
+    didAccount,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.btcAddresses[0])).to.equal(address);
+  expect(didAccountData.btcAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+};
+
+export const createDIDPasskeyTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const passkey = createPasskey();
+
+  const address = bs58.encode(passkey.publicKey);
+
+  const signed = signPasskeyMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      address,
+      nonce
+    ),
+    passkey
+  );
+
+  await createDIDPasskey(
+    program,
+    didStr,
+    passkey.publicKey,
+    signed,
+    didAccount,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.passkeyAddresses[0])).to.equal(
+    address
+  );
+  expect(didAccountData.passkeyAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+
+  // a challenge other than the operation message is rejected
+  const otherDidStr = generateRandomDID();
+
+  try {
+    await createDIDPasskey(
+      program,
+      otherDidStr,
+      passkey.publicKey,
+      signPasskeyMessage("some other challenge", passkey),
+      getDIDAccount(otherDidStr, program),
+      payer,
+      nonce
+    );
+    expect.fail("a passkey signature over another challenge was accepted");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidWebAuthnData");
+  }
+};
+
+export const createDIDEip712Test = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const controller = ethSigner.address.toLowerCase();
+
+  const { signature, recoveryId, actual_message } = await signEthTypedData(
+    program.programId,
+    "CreateDid",
+    { did: didStr, controller, nonce },
+    ethSigner
+  );
+
+  await createDIDEIP712(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(controller);
+  expect(didAccountData.ethAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+
+  // the typed data names the did, so the signature can't create another one
+  const otherDidStr = generateRandomDID();
+
+  try {
+    await createDIDEIP712(
+      program,
+      otherDidStr,
+      ethSigner,
+      signature,
+      recoveryId,
+      getDIDAccount(otherDidStr, program),
+      actual_message,
+      payer,
+      nonce
+    );
+    expect.fail("an EIP-712 signature for another did was accepted");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidSignature");
+  }
+};
+
+export const createDIDSolOffchainMessageTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const keypair = anchor.web3.Keypair.generate();
+  const message = createMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    nonce
+  );
+
+  // hardware wallets only sign the message wrapped in the off-chain envelope
+  const messageEncoded = Uint8Array.from(toOffchainMessage(message));
+
+  const signature = nacl.sign.detached(messageEncoded, keypair.secretKey);
+
+  await createDIDSOL(
+    program,
+    didStr,
+    keypair,
+    signature,
+    messageEncoded,
+    didAccount,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.solAddresses[0])).to.equal(
+    keypair.publicKey.toBase58()
+  );
+};
+
+export const createDIDEthereumRecoverTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
+
+  const { signature, recoveryId } = await signEthMessage(message, ethSigner);
+
+  await createDIDEVMRecover(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    payer,
+    nonce
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
+    ethSigner.address.toLowerCase()
+  );
+
+  // a signature recovering to another address is rejected
+  const otherDidStr = generateRandomDID();
+
+  try {
+    await createDIDEVMRecover(
+      program,
+      otherDidStr,
+      ethers.Wallet.createRandom(),
+      signature,
+      recoveryId,
+      getDIDAccount(otherDidStr, program),
+      payer,
+      nonce
+    );
+    expect.fail("a signature of another address was accepted");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidSignature");
+  }
+};
+
+export const createDIDBytesTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const evmDidStr = generateRandomDID();
+  const evmDidAccount = getDIDAccount(evmDidStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      evmDidStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  await createDIDEVMBytes(
+    program,
+    evmDidStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    evmDidAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const evmDidAccountData = await program.account.did.fetch(evmDidAccount);
+
+  expect(getAddressString(evmDidAccountData.ethAddresses[0])).to.equal(
+    ethSigner.address.toLowerCase()
+  );
+
+  const solDidStr = generateRandomDID();
+  const solDidAccount = getDIDAccount(solDidStr, program);
+
+  const keypair = anchor.web3.Keypair.generate();
+
+  const messageEncoded = Uint8Array.from(
+    Buffer.from(
+      createMessage(
+        program.programId,
+        solDidStr,
+        keypair.publicKey.toBase58(),
+        nonce
+      )
+    )
+  );
+
+  await createDIDSOLBytes(
+    program,
+    solDidStr,
+    keypair,
+    nacl.sign.detached(messageEncoded, keypair.secretKey),
+    messageEncoded,
+    solDidAccount,
+    payer,
+    nonce
+  );
+
+  const solDidAccountData = await program.account.did.fetch(solDidAccount);
+
+  expect(getAddressString(solDidAccountData.solAddresses[0])).to.equal(
+    keypair.publicKey.toBase58()
+  );
+};
+
+// malformed base58 is an error, not a panic of the program
+export const createDIDMalformedSigTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const address = "0OIl is not base58";
+
+  try {
+    await program.methods
+      .createDid(didStr, {
+        sol: {
+          solSig: {
+            addressBase58: address,
+            sigBase58: bs58.encode(new Uint8Array(64)),
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      })
+      .accounts({
+        did: getDIDAccount(didStr, program),
+        nonceRegistry: getNonceRegistryAccount(didStr, program),
+        addressLookup: getAddressLookupAccount("sol", address, program),
+        ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        payer: payer.publicKey,
+        eventAuthority: getEventAuthorityAccount(program),
+        program: program.programId,
+      })
+      .rpc();
+    expect.fail("a malformed address was accepted");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidAddress");
+  }
+};
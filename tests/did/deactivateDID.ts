@@ -0,0 +1,286 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getAddressLookupAccount,
+  getAliasAccount,
+  getDIDAccount,
+  getRecoveryAccount,
+} from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  claimAliasEVM,
+  createDIDEVM,
+  deactivateDIDEVM,
+  setGuardiansEVM,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  claimAliasMessage,
+  createMessage,
+  deactivateMessage,
+  setGuardiansMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+import * as anchor from "@project-serum/anchor";
+import crypto from "crypto";
+
+export const deactivateDIDTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    message,
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  // an alias and guardians, which go with the did when it is deactivated
+
+  const handle = `alias-${crypto.randomBytes(4).toString("hex")}`;
+
+  const {
+    actual_message: claimActualMessage,
+    signature: claimSignature,
+    recoveryId: claimRecoveryId,
+  } = await signEthMessage(
+    claimAliasMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      handle,
+      null,
+      nonce + 4
+    ),
+    ethSigner
+  );
+
+  await claimAliasEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    claimSignature,
+    claimRecoveryId,
+    claimActualMessage,
+    handle,
+    null,
+    nonce + 4
+  );
+
+  const guardian = ethers.Wallet.createRandom();
+
+  const {
+    actual_message: guardiansActualMessage,
+    signature: guardiansSignature,
+    recoveryId: guardiansRecoveryId,
+  } = await signEthMessage(
+    setGuardiansMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      [`evm:${guardian.address.toLowerCase()}`],
+      1,
+      3600,
+      nonce + 5
+    ),
+    ethSigner
+  );
+
+  await setGuardiansEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    guardiansSignature,
+    guardiansRecoveryId,
+    guardiansActualMessage,
+    [
+      {
+        address: {
+          chain: { evm: {} },
+          address: guardian.address.toLowerCase(),
+        },
+      },
+    ],
+    1,
+    3600,
+    nonce + 5
+  );
+
+  const recipient = anchor.web3.Keypair.generate().publicKey;
+
+  const deactivateMessage = deactivateMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    recipient,
+    nonce + 1
+  );
+
+  const {
+    actual_message: deactivateActualMessage,
+    signature: deactivateSignature,
+    recoveryId: deactivateRecoveryId,
+  } = await signEthMessage(deactivateMessage, ethSigner);
+
+  await deactivateDIDEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    recipient,
+    ethSigner,
+    deactivateSignature,
+    deactivateRecoveryId,
+    deactivateActualMessage,
+    nonce + 1,
+    [],
+    [getAliasAccount(handle, program)]
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.did).to.equal(didStr);
+  expect(didAccountData.deactivatedAt).to.not.equal(null);
+  expect(didAccountData.ethAddresses).to.deep.equal([]);
+  expect(didAccountData.solAddresses).to.deep.equal([]);
+
+  const recipientBalance = await program.provider.connection.getBalance(
+    recipient
+  );
+
+  expect(recipientBalance).to.be.greaterThan(0);
+
+  // nothing is left pointing at the tombstone
+
+  expect(
+    await program.account.addressLookup.fetchNullable(
+      getAddressLookupAccount("evm", ethSigner.address.toLowerCase(), program)
+    )
+  ).to.be.null;
+  expect(
+    await program.account.alias.fetchNullable(getAliasAccount(handle, program))
+  ).to.be.null;
+  expect(
+    await program.account.recovery.fetchNullable(
+      getRecoveryAccount(didStr, program)
+    )
+  ).to.be.null;
+
+  // the tombstone keeps anyone from mutating or re-registering the did
+
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 2
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 2
+  );
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signEthMessage(newAddressMessageAsNewAddress, newEthSigner);
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signEthMessage(newAddressMessageAsController, ethSigner);
+
+  try {
+    await addAddressEVMwithEVMController(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      ethSigner,
+      newEthSigner,
+      controllerSignature,
+      controllerRecoveryId,
+      newAddressSignature,
+      newAddressRecoveryId,
+      controllerActualMessage,
+      newAddressActualMessage,
+      nonce + 2
+    );
+    expect.fail("address was added to a deactivated did");
+  } catch (e) {
+    expect(e.toString()).to.contain("DidDeactivated");
+  }
+
+  const recreateMessage = createMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    nonce + 3
+  );
+
+  const {
+    actual_message: recreateActualMessage,
+    signature: recreateSignature,
+    recoveryId: recreateRecoveryId,
+  } = await signEthMessage(recreateMessage, newEthSigner);
+
+  try {
+    await createDIDEVM(
+      program,
+      didStr,
+      newEthSigner,
+      recreateSignature,
+      recreateRecoveryId,
+      didAccount,
+      recreateActualMessage,
+      payer,
+      nonce + 3
+    );
+    expect.fail("deactivated did was re-registered");
+  } catch (e) {
+    expect(e.toString()).to.not.contain("re-registered");
+  }
+};
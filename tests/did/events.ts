@@ -0,0 +1,111 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  createDIDEVM,
+} from "../utils/instructions";
+import { addAddressMessage, createMessage } from "../utils/messages";
+import { expect } from "chai";
+import { getEvent } from "../utils/events";
+
+export const eventsTest = async (program: Program<SquirclDid>, payer: any) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const ethSigner = ethers.Wallet.createRandom();
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const didStr = generateRandomDID();
+  const didAccount = getDIDAccount(didStr, program);
+
+  // creating a did emits the controller it was created with
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  const createEvent = await getEvent(
+    program,
+    await createDIDEVM(
+      program,
+      didStr,
+      ethSigner,
+      signature,
+      recoveryId,
+      didAccount,
+      actual_message,
+      payer,
+      nonce
+    ),
+    "DidCreated"
+  );
+
+  expect(createEvent.data.did).to.equal(didStr);
+  expect(createEvent.data.chain).to.deep.equal({ evm: {} });
+  expect(createEvent.data.address).to.equal(ethSigner.address.toLowerCase());
+
+  // adding an address emits it with the controller that authorised it
+
+  const signAddMessage = (signer: HDNodeWallet) =>
+    signEthMessage(
+      addAddressMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        newEthSigner.address.toLowerCase(),
+        "admin",
+        nonce + 1
+      ),
+      signer
+    );
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signAddMessage(ethSigner);
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signAddMessage(newEthSigner);
+
+  const addEvent = await getEvent(
+    program,
+    await addAddressEVMwithEVMController(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      ethSigner,
+      newEthSigner,
+      controllerSignature,
+      controllerRecoveryId,
+      newAddressSignature,
+      newAddressRecoveryId,
+      controllerActualMessage,
+      newAddressActualMessage,
+      nonce + 1
+    ),
+    "AddressAdded"
+  );
+
+  expect(addEvent.data).to.deep.include({
+    did: didStr,
+    chain: { evm: {} },
+    address: newEthSigner.address.toLowerCase(),
+    role: { admin: {} },
+    signerChain: { evm: {} },
+    signer: ethSigner.address.toLowerCase(),
+  });
+};
@@ -4,10 +4,12 @@ import {
   generateRandomDID,
   getCredentialAccount,
   getDIDAccount,
+  getNonceRegistryAccount,
 } from "../utils/pda";
-import { ethers } from "ethers";
+import { ethers, HDNodeWallet } from "ethers";
 import { signEthMessage } from "../utils/signatures";
 import {
+  addAddressEVMwithEVMController,
   createDIDEVM,
   createDIDSOL,
   issueCredentialEth,
@@ -17,6 +19,14 @@ import {
   updateCredentialEth,
   updateCredentialSol,
 } from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  getOperationDigestMessage,
+  issueCredentialMessage,
+  revokeCredentialMessage,
+  updateCredentialMessage,
+} from "../utils/messages";
 import nacl from "tweetnacl";
 import { expect } from "chai";
 import * as anchor from "@project-serum/anchor";
@@ -36,8 +46,18 @@ export const issueCredentialEvmTest = async (
   const issuerEthSigner = ethers.Wallet.createRandom();
   const subjectEthSigner = ethers.Wallet.createRandom();
 
-  const issuerCreateMessage = `I am creating a new Squircl DID with the address ${issuerEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
-  const subjectCreateMessage = `I am creating a new Squircl DID with the address ${subjectEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const issuerCreateMessage = createMessage(
+    program.programId,
+    issuerDidStr,
+    issuerEthSigner.address.toLowerCase(),
+    nonce
+  );
+  const subjectCreateMessage = createMessage(
+    program.programId,
+    subjectDidStr,
+    subjectEthSigner.address.toLowerCase(),
+    nonce
+  );
 
   const {
     actual_message: issuerCreateAcutalMessage,
@@ -86,10 +106,24 @@ export const issueCredentialEvmTest = async (
     program
   );
 
-  const issueMessage = `I am issuing a ${credentialId} credential to ${subjectDidStr} for the Squircl DID with the DID ${issuerDidStr}. Uri: ${randomUri}. Hash: ${randomHash}. Nonce: ${nonce}`;
+  const nonceRegistryAccount = getNonceRegistryAccount(issuerDidStr, program);
 
   const expiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 10; // 10 years
 
+  const issueMessage = issueCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerEthSigner.address.toLowerCase(),
+    credentialId,
+    subjectDidStr,
+    randomUri,
+    randomHash,
+    expiresAt,
+    true,
+    true,
+    nonce + 1
+  );
+
   const {
     actual_message: issueActualMessage,
     signature: issueSignature,
@@ -101,6 +135,7 @@ export const issueCredentialEvmTest = async (
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerEthSigner,
     issueSignature,
@@ -112,7 +147,7 @@ export const issueCredentialEvmTest = async (
     expiresAt,
     true,
     true,
-    nonce
+    nonce + 1
   );
 
   const credentialAccountData = await program.account.credential.fetch(
@@ -131,10 +166,22 @@ export const issueCredentialEvmTest = async (
   const updatedRandomUri = "https://example.com/credentials/456";
   const updatedRandomHash = "0x" + nacl.randomBytes(32).toString();
 
-  const updatedMessage = `I am updating the ${credentialId} credential issued to ${subjectDidStr} by ${issuerDidStr}. New uri: ${updatedRandomUri}. New hash: ${updatedRandomHash}. Nonce: ${nonce}`;
-
   const updatedExpiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 5; // 5 years
 
+  const updatedMessage = updateCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerEthSigner.address.toLowerCase(),
+    credentialId,
+    subjectDidStr,
+    updatedRandomUri,
+    updatedRandomHash,
+    updatedExpiresAt,
+    true,
+    true,
+    nonce + 2
+  );
+
   const {
     actual_message: updatedActualMessage,
     signature: updatedSignature,
@@ -146,6 +193,7 @@ export const issueCredentialEvmTest = async (
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerEthSigner,
     updatedSignature,
@@ -157,7 +205,7 @@ export const issueCredentialEvmTest = async (
     updatedExpiresAt,
     true,
     true,
-    nonce
+    nonce + 2
   );
 
   const updatedAccountData = await program.account.credential.fetch(
@@ -173,7 +221,14 @@ export const issueCredentialEvmTest = async (
   expect(updatedAccountData.isMutable).to.equal(true);
   expect(updatedAccountData.isRevokable).to.equal(true);
 
-  const revokeMessage = `I am revoking the ${credentialId} credential issued to ${subjectDidStr} by ${issuerDidStr}. Nonce: ${nonce}`;
+  const revokeMessage = revokeCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerEthSigner.address.toLowerCase(),
+    credentialId,
+    subjectDidStr,
+    nonce + 3
+  );
 
   const {
     actual_message: revokeActualMessage,
@@ -186,13 +241,14 @@ export const issueCredentialEvmTest = async (
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerEthSigner,
     revokeSignature,
     revokeRecoveryId,
     revokeActualMessage,
     credentialId,
-    nonce
+    nonce + 3
   );
 
   try {
@@ -217,8 +273,18 @@ export const issueCredentialSolTest = async (
   const issuerKeypair = anchor.web3.Keypair.generate();
   const subjectKeypair = anchor.web3.Keypair.generate();
 
-  const issuerCreateMessage = `I am creating a new Squircl DID with the address ${issuerKeypair.publicKey.toString()}. Nonce: ${nonce}`;
-  const subjectCreateMessage = `I am creating a new Squircl DID with the address ${subjectKeypair.publicKey.toString()}. Nonce: ${nonce}`;
+  const issuerCreateMessage = createMessage(
+    program.programId,
+    issuerDidStr,
+    issuerKeypair.publicKey.toString(),
+    nonce
+  );
+  const subjectCreateMessage = createMessage(
+    program.programId,
+    subjectDidStr,
+    subjectKeypair.publicKey.toString(),
+    nonce
+  );
 
   const issuerCreateMessageEncoded = Uint8Array.from(
     Buffer.from(issuerCreateMessage)
@@ -269,20 +335,35 @@ export const issueCredentialSolTest = async (
     program
   );
 
-  const issueMessage = `I am issuing a ${credentialId} credential to ${subjectDidStr} for the Squircl DID with the DID ${issuerDidStr}. Uri: ${randomUri}. Hash: ${randomHash}. Nonce: ${nonce}`;
+  const nonceRegistryAccount = getNonceRegistryAccount(issuerDidStr, program);
+
+  const expiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 10; // 10 years
+
+  const issueMessage = issueCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerKeypair.publicKey.toBase58(),
+    credentialId,
+    subjectDidStr,
+    randomUri,
+    randomHash,
+    expiresAt,
+    true,
+    true,
+    nonce + 1
+  );
   const issueMessageEncoded = Uint8Array.from(Buffer.from(issueMessage));
   const issueSignature = nacl.sign.detached(
     issueMessageEncoded,
     issuerKeypair.secretKey
   );
 
-  const expiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 10; // 10 years
-
   await issueCredentialSol(
     program,
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerKeypair.publicKey,
     issueSignature,
@@ -293,7 +374,7 @@ export const issueCredentialSolTest = async (
     expiresAt,
     true,
     true,
-    nonce
+    nonce + 1
   );
 
   const credentialAccountData = await program.account.credential.fetch(
@@ -312,7 +393,21 @@ export const issueCredentialSolTest = async (
   const updatedRandomUri = "https://example.com/credentials/456";
   const updatedRandomHash = "0x" + nacl.randomBytes(32).toString();
 
-  const updatedMessage = `I am updating the ${credentialId} credential issued to ${subjectDidStr} by ${issuerDidStr}. New uri: ${updatedRandomUri}. New hash: ${updatedRandomHash}. Nonce: ${nonce}`;
+  const updatedExpiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 5; // 5 years
+
+  const updatedMessage = updateCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerKeypair.publicKey.toBase58(),
+    credentialId,
+    subjectDidStr,
+    updatedRandomUri,
+    updatedRandomHash,
+    updatedExpiresAt,
+    true,
+    true,
+    nonce + 2
+  );
   const updatedMessageEncoded = Uint8Array.from(Buffer.from(updatedMessage));
 
   const updatedSignature = nacl.sign.detached(
@@ -320,13 +415,12 @@ export const issueCredentialSolTest = async (
     issuerKeypair.secretKey
   );
 
-  const updatedExpiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365 * 5; // 5 years
-
   await updateCredentialSol(
     program,
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerKeypair.publicKey,
     updatedSignature,
@@ -337,7 +431,7 @@ export const issueCredentialSolTest = async (
     updatedExpiresAt,
     true,
     true,
-    nonce
+    nonce + 2
   );
 
   const updatedAccountData = await program.account.credential.fetch(
@@ -353,7 +447,14 @@ export const issueCredentialSolTest = async (
   expect(updatedAccountData.isMutable).to.equal(true);
   expect(updatedAccountData.isRevokable).to.equal(true);
 
-  const revokeMessage = `I am revoking the ${credentialId} credential issued to ${subjectDidStr} by ${issuerDidStr}. Nonce: ${nonce}`;
+  const revokeMessage = revokeCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerKeypair.publicKey.toBase58(),
+    credentialId,
+    subjectDidStr,
+    nonce + 3
+  );
   const revokeMessageEncoded = Uint8Array.from(Buffer.from(revokeMessage));
 
   const revokeSignature = nacl.sign.detached(
@@ -366,12 +467,13 @@ export const issueCredentialSolTest = async (
     issuerDidAccount,
     subjectDidAccount,
     credentialAccount,
+    nonceRegistryAccount,
     payer,
     issuerKeypair.publicKey,
     revokeSignature,
     revokeMessageEncoded,
     credentialId,
-    nonce
+    nonce + 3
   );
 
   try {
@@ -380,3 +482,245 @@ export const issueCredentialSolTest = async (
     expect(e.toString()).to.contain("Account does not exist");
   }
 };
+
+// a uri this long only fits in the transaction when the issuer signs the digest
+// of the operation message instead of the message itself
+export const issueCredentialSolDigestTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const issuerDidStr = generateRandomDID();
+  const subjectDidStr = generateRandomDID();
+
+  const issuerDidAccount = getDIDAccount(issuerDidStr, program);
+  const subjectDidAccount = getDIDAccount(subjectDidStr, program);
+
+  const issuerKeypair = anchor.web3.Keypair.generate();
+  const subjectKeypair = anchor.web3.Keypair.generate();
+
+  for (const [didStr, didAccount, keypair] of [
+    [issuerDidStr, issuerDidAccount, issuerKeypair],
+    [subjectDidStr, subjectDidAccount, subjectKeypair],
+  ] as const) {
+    const messageEncoded = Uint8Array.from(
+      Buffer.from(
+        createMessage(
+          program.programId,
+          didStr,
+          keypair.publicKey.toBase58(),
+          nonce
+        )
+      )
+    );
+
+    await createDIDSOL(
+      program,
+      didStr,
+      keypair,
+      nacl.sign.detached(messageEncoded, keypair.secretKey),
+      messageEncoded,
+      didAccount,
+      payer,
+      nonce
+    );
+  }
+
+  const credentialId = "long-uri";
+  const randomHash = "0x" + Buffer.from(nacl.randomBytes(32)).toString("hex");
+  const longUri = "https://example.com/credentials/" + "a".repeat(200);
+
+  const credentialAccount = getCredentialAccount(
+    issuerDidStr,
+    subjectDidStr,
+    credentialId,
+    program
+  );
+
+  const expiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365; // 1 year
+
+  const issueMessage = issueCredentialMessage(
+    program.programId,
+    issuerDidStr,
+    issuerKeypair.publicKey.toBase58(),
+    credentialId,
+    subjectDidStr,
+    longUri,
+    randomHash,
+    expiresAt,
+    true,
+    true,
+    nonce + 1
+  );
+  const digestEncoded = Uint8Array.from(
+    Buffer.from(getOperationDigestMessage(issueMessage))
+  );
+
+  await issueCredentialSol(
+    program,
+    issuerDidAccount,
+    subjectDidAccount,
+    credentialAccount,
+    getNonceRegistryAccount(issuerDidStr, program),
+    payer,
+    issuerKeypair.publicKey,
+    nacl.sign.detached(digestEncoded, issuerKeypair.secretKey),
+    digestEncoded,
+    credentialId,
+    longUri,
+    randomHash,
+    expiresAt,
+    true,
+    true,
+    nonce + 1
+  );
+
+  const credentialAccountData = await program.account.credential.fetch(
+    credentialAccount
+  );
+
+  expect(credentialAccountData.uri).to.equal(longUri);
+  expect(credentialAccountData.credentialHash).to.equal(randomHash);
+};
+
+export const issueCredentialAuthenticationTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const issuerDidStr = generateRandomDID();
+  const subjectDidStr = generateRandomDID();
+
+  const issuerDidAccount = getDIDAccount(issuerDidStr, program);
+  const subjectDidAccount = getDIDAccount(subjectDidStr, program);
+
+  const issuerEthSigner = ethers.Wallet.createRandom();
+  const subjectEthSigner = ethers.Wallet.createRandom();
+  const authenticationSigner = ethers.Wallet.createRandom();
+
+  for (const [didStr, didAccount, ethSigner] of [
+    [issuerDidStr, issuerDidAccount, issuerEthSigner],
+    [subjectDidStr, subjectDidAccount, subjectEthSigner],
+  ] as const) {
+    const { actual_message, signature, recoveryId } = await signEthMessage(
+      createMessage(
+        program.programId,
+        didStr,
+        ethSigner.address.toLowerCase(),
+        nonce
+      ),
+      ethSigner
+    );
+
+    await createDIDEVM(
+      program,
+      didStr,
+      ethSigner,
+      signature,
+      recoveryId,
+      didAccount,
+      actual_message,
+      payer,
+      nonce
+    );
+  }
+
+  // an authentication key is part of the issuer did, but can't assert for it
+
+  const signAddMessage = (signer: HDNodeWallet) =>
+    signEthMessage(
+      addAddressMessage(
+        program.programId,
+        issuerDidStr,
+        signer.address.toLowerCase(),
+        "evm",
+        authenticationSigner.address.toLowerCase(),
+        "authentication",
+        nonce + 1
+      ),
+      signer
+    );
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signAddMessage(issuerEthSigner);
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signAddMessage(authenticationSigner);
+
+  await addAddressEVMwithEVMController(
+    program,
+    issuerDidStr,
+    issuerDidAccount,
+    payer,
+    issuerEthSigner,
+    authenticationSigner,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1,
+    { authentication: {} }
+  );
+
+  const credentialId = "not-asserted";
+  const randomHash = "0x" + Buffer.from(nacl.randomBytes(32)).toString("hex");
+  const randomUri = "https://example.com/credentials/789";
+
+  const expiresAt = new Date().getTime() + 1000 * 60 * 60 * 24 * 365; // 1 year
+
+  const {
+    actual_message: issueActualMessage,
+    signature: issueSignature,
+    recoveryId: issueRecoveryId,
+  } = await signEthMessage(
+    issueCredentialMessage(
+      program.programId,
+      issuerDidStr,
+      authenticationSigner.address.toLowerCase(),
+      credentialId,
+      subjectDidStr,
+      randomUri,
+      randomHash,
+      expiresAt,
+      true,
+      true,
+      nonce + 2
+    ),
+    authenticationSigner
+  );
+
+  try {
+    await issueCredentialEth(
+      program,
+      issuerDidAccount,
+      subjectDidAccount,
+      getCredentialAccount(issuerDidStr, subjectDidStr, credentialId, program),
+      getNonceRegistryAccount(issuerDidStr, program),
+      payer,
+      authenticationSigner,
+      issueSignature,
+      issueRecoveryId,
+      issueActualMessage,
+      credentialId,
+      randomUri,
+      randomHash,
+      expiresAt,
+      true,
+      true,
+      nonce + 2
+    );
+    expect.fail("an authentication key issued a credential");
+  } catch (e) {
+    expect(e.toString()).to.contain("AddressDoesntHaveEnoughPermissions");
+  }
+};
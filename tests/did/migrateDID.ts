@@ -0,0 +1,225 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import { createDIDEVM, migrateDIDEVM } from "../utils/instructions";
+import { createMessage, migrateMessage } from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
+import { expect } from "chai";
+import * as anchor from "@project-serum/anchor";
+
+// discriminator, timestamps, address and service vec prefixes, deactivated_at,
+// threshold, version and prev_state_hash
+const DID_LEN_WITHOUT_DID_AND_ADDRESS = 94;
+// vec prefix, 20 byte key, added_at, chain, role, valid_from and valid_until
+const EVM_ADDRESS_LEN = 52;
+
+// tests/fixtures/legacy_did.json, a did as the first release stored it: string addresses in an
+// account sized for a 42 char address and a 132 char signature at 4 bytes per char
+const LEGACY_DID = "did:squircl:6c65676163792d6469642d66697874757265303030303031";
+const LEGACY_DID_LEN = 934;
+const LEGACY_CREATED_AT = 1700000000;
+// the fixture's controller is the first account of the well known hardhat mnemonic
+const LEGACY_CONTROLLER_PHRASE =
+  "test test test test test test test test test test test junk";
+
+export const migrateDIDTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  // new dids store the raw address and are sized to fit exactly
+
+  const accountInfo = await program.provider.connection.getAccountInfo(
+    didAccount
+  );
+
+  expect(accountInfo.data.length).to.equal(
+    DID_LEN_WITHOUT_DID_AND_ADDRESS + 4 + didStr.length + EVM_ADDRESS_LEN
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.ethAddresses[0].address.length).to.equal(20);
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
+    ethSigner.address.toLowerCase()
+  );
+
+  // only dids in the legacy layout can be migrated
+
+  const recipient = anchor.web3.Keypair.generate().publicKey;
+
+  const {
+    actual_message: migrateActualMessage,
+    signature: migrateSignature,
+    recoveryId: migrateRecoveryId,
+  } = await signEthMessage(
+    migrateMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      recipient,
+      nonce + 1
+    ),
+    ethSigner
+  );
+
+  try {
+    await migrateDIDEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      recipient,
+      ethSigner,
+      migrateSignature,
+      migrateRecoveryId,
+      migrateActualMessage,
+      nonce + 1
+    );
+    expect.fail("a did in the current layout was migrated");
+  } catch (e) {
+    expect(e.toString()).to.contain("NotALegacyAccount");
+  }
+};
+
+export const migrateLegacyDIDTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didAccount = getDIDAccount(LEGACY_DID, program);
+
+  const ethSigner = ethers.HDNodeWallet.fromPhrase(LEGACY_CONTROLLER_PHRASE);
+
+  const connection = program.provider.connection;
+
+  const legacyAccountInfo = await connection.getAccountInfo(didAccount);
+
+  expect(legacyAccountInfo.data.length).to.equal(LEGACY_DID_LEN);
+
+  const recipient = anchor.web3.Keypair.generate().publicKey;
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    migrateMessage(
+      program.programId,
+      LEGACY_DID,
+      ethSigner.address.toLowerCase(),
+      recipient,
+      nonce
+    ),
+    ethSigner
+  );
+
+  await migrateDIDEVM(
+    program,
+    LEGACY_DID,
+    didAccount,
+    payer,
+    recipient,
+    ethSigner,
+    signature,
+    recoveryId,
+    actual_message,
+    nonce
+  );
+
+  // the account shrinks to the raw key layout and the recipient gets the rent it no longer needs
+
+  const accountInfo = await connection.getAccountInfo(didAccount);
+
+  const didLen =
+    DID_LEN_WITHOUT_DID_AND_ADDRESS + 4 + LEGACY_DID.length + EVM_ADDRESS_LEN;
+
+  expect(accountInfo.data.length).to.equal(didLen);
+  expect(accountInfo.lamports).to.equal(
+    await connection.getMinimumBalanceForRentExemption(didLen)
+  );
+  expect(await connection.getBalance(recipient)).to.equal(
+    legacyAccountInfo.lamports - accountInfo.lamports
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.did).to.equal(LEGACY_DID);
+  expect(didAccountData.createdAt.toNumber()).to.equal(LEGACY_CREATED_AT);
+  expect(didAccountData.threshold).to.equal(1);
+  expect(didAccountData.version.toNumber()).to.equal(1);
+  expect(didAccountData.solAddresses).to.deep.equal([]);
+  expect(didAccountData.ethAddresses.length).to.equal(1);
+  expect(Buffer.from(didAccountData.ethAddresses[0].address)).to.deep.equal(
+    Buffer.from(ethSigner.address.slice(2), "hex")
+  );
+  expect(didAccountData.ethAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
+    ethSigner.address.toLowerCase()
+  );
+
+  // a migrated did is in the current layout, so it can't be migrated again
+
+  const {
+    actual_message: againActualMessage,
+    signature: againSignature,
+    recoveryId: againRecoveryId,
+  } = await signEthMessage(
+    migrateMessage(
+      program.programId,
+      LEGACY_DID,
+      ethSigner.address.toLowerCase(),
+      recipient,
+      nonce + 1
+    ),
+    ethSigner
+  );
+
+  try {
+    await migrateDIDEVM(
+      program,
+      LEGACY_DID,
+      didAccount,
+      payer,
+      recipient,
+      ethSigner,
+      againSignature,
+      againRecoveryId,
+      againActualMessage,
+      nonce + 1
+    );
+    expect.fail("a migrated did was migrated again");
+  } catch (e) {
+    expect(e.toString()).to.contain("NotALegacyAccount");
+  }
+};
@@ -0,0 +1,160 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  createDIDEVM,
+  removeAddressEVMRemover,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  removeAddressMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+
+export const replayedSignatureTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    message,
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signEthMessage(newAddressMessageAsNewAddress, newEthSigner);
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signEthMessage(newAddressMessageAsController, ethSigner);
+
+  await addAddressEVMwithEVMController(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    newEthSigner,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1
+  );
+
+  const removeMessage = removeAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    nonce + 2
+  );
+
+  const {
+    actual_message: removeActualMessage,
+    signature: removeSignature,
+    recoveryId: removeRecoveryId,
+  } = await signEthMessage(removeMessage, ethSigner);
+
+  await removeAddressEVMRemover(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    removeSignature,
+    removeRecoveryId,
+    removeActualMessage,
+    newEthSigner.address.toLowerCase(),
+    { evm: {} },
+    nonce + 2
+  );
+
+  // replaying the exact same add_address signatures must fail
+
+  try {
+    await addAddressEVMwithEVMController(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      ethSigner,
+      newEthSigner,
+      controllerSignature,
+      controllerRecoveryId,
+      newAddressSignature,
+      newAddressRecoveryId,
+      controllerActualMessage,
+      newAddressActualMessage,
+      nonce + 1
+    );
+    expect.fail("replayed signature was accepted");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: NonceAlreadyUsed. Error Number: 6011. Error Message: Nonce has already been used."
+    );
+  }
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.ethAddresses.length).to.equal(1);
+};
@@ -0,0 +1,300 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getAddressLookupAccount,
+  getDIDAccount,
+  getRecoveryAccount,
+} from "../utils/pda";
+import { HDNodeWallet, ethers } from "ethers";
+import { signEthMessage, signEthMessageWithNonce } from "../utils/signatures";
+import {
+  cancelRecoveryEVM,
+  createDIDEVM,
+  executeRecoveryEVM,
+  setGuardiansEVM,
+  startRecoveryEVM,
+} from "../utils/instructions";
+import {
+  cancelRecoveryMessage,
+  createMessage,
+  setGuardiansMessage,
+  startRecoveryMessage,
+} from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
+import { expect } from "chai";
+
+const createDID = async (
+  program: Program<SquirclDid>,
+  payer: any,
+  controller: HDNodeWallet,
+  nonce: number
+) => {
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      nonce
+    ),
+    controller
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    controller,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  return { didStr, didAccount };
+};
+
+export const recoveryTest = async (program: Program<SquirclDid>, payer: any) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const controller = ethers.Wallet.createRandom();
+  const guardian = ethers.Wallet.createRandom();
+  const guardianDidController = ethers.Wallet.createRandom();
+  const newController = ethers.Wallet.createRandom();
+
+  const { didStr, didAccount } = await createDID(
+    program,
+    payer,
+    controller,
+    nonce
+  );
+
+  const { didStr: guardianDidStr, didAccount: guardianDidAccount } =
+    await createDID(program, payer, guardianDidController, nonce);
+
+  // guardian addresses are stored the way signers are recovered, the checksum
+  // casing of the address doesn't keep the guardian from approving
+  const guardianAddress = (address: string) => [
+    {
+      address: {
+        chain: { evm: {} },
+        address,
+      },
+    },
+    { did: { did: guardianDidStr } },
+  ];
+
+  const setGuardians = async (
+    delay: number,
+    nonce: number,
+    address: string = guardian.address
+  ) => {
+    const { actual_message, signature, recoveryId } = await signEthMessage(
+      setGuardiansMessage(
+        program.programId,
+        didStr,
+        controller.address.toLowerCase(),
+        [`evm:${address}`, guardianDidStr],
+        2,
+        delay,
+        nonce
+      ),
+      controller
+    );
+
+    await setGuardiansEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      signature,
+      recoveryId,
+      actual_message,
+      guardianAddress(address),
+      2,
+      delay,
+      nonce
+    );
+  };
+
+  const approve = (signer: HDNodeWallet, nonce: number) =>
+    signEthMessageWithNonce(
+      startRecoveryMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        newController.address.toLowerCase(),
+        nonce
+      ),
+      signer,
+      nonce
+    );
+
+  const recover = (nonce: number) =>
+    signEthMessageWithNonce(
+      startRecoveryMessage(
+        program.programId,
+        didStr,
+        newController.address.toLowerCase(),
+        "evm",
+        newController.address.toLowerCase(),
+        nonce
+      ),
+      newController,
+      nonce
+    );
+
+  // a guardian address that isn't valid for its chain is rejected
+
+  try {
+    await setGuardians(3600, nonce + 1, "0x1234");
+    expect.fail("an invalid guardian address was accepted");
+  } catch (e) {
+    expect(e.toString()).to.contain("InvalidAddress");
+  }
+
+  await setGuardians(3600, nonce + 1);
+
+  expect(
+    (await program.account.recovery.fetch(getRecoveryAccount(didStr, program)))
+      .guardians[0]
+  ).to.deep.equal({
+    address: {
+      chain: { evm: {} },
+      address: guardian.address.toLowerCase(),
+    },
+  });
+
+  // a single guardian is not a quorum
+
+  try {
+    await startRecoveryEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      await recover(nonce + 2),
+      [await approve(guardian, nonce + 2)]
+    );
+    expect.fail("recovery started without a quorum");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: RecoveryQuorumNotMet. Error Number: 6025. Error Message: Not enough guardians approved the recovery."
+    );
+  }
+
+  // the guardian did approves through its controller
+
+  await startRecoveryEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await recover(nonce + 2),
+    [
+      await approve(guardian, nonce + 2),
+      await approve(guardianDidController, nonce + 2),
+    ],
+    [guardianDidAccount]
+  );
+
+  const recoveryAccount = getRecoveryAccount(didStr, program);
+
+  const execute = () =>
+    executeRecoveryEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller.address.toLowerCase(),
+      newController.address.toLowerCase()
+    );
+
+  expect((await program.account.recovery.fetch(recoveryAccount)).pending).to
+    .not.be.null;
+
+  try {
+    await execute();
+    expect.fail("recovery executed before its delay");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: RecoveryTimelockActive. Error Number: 6028. Error Message: The recovery delay has not passed yet."
+    );
+  }
+
+  // the controller still holds its key and cancels
+
+  await cancelRecoveryEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await signEthMessageWithNonce(
+      cancelRecoveryMessage(
+        program.programId,
+        didStr,
+        controller.address.toLowerCase(),
+        nonce + 3
+      ),
+      controller,
+      nonce + 3
+    )
+  );
+
+  expect((await program.account.recovery.fetch(recoveryAccount)).pending).to.be
+    .null;
+
+  // without a delay the guardians can recover straight away
+
+  await setGuardians(0, nonce + 4);
+
+  await startRecoveryEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await recover(nonce + 5),
+    [
+      await approve(guardian, nonce + 5),
+      await approve(guardianDidController, nonce + 5),
+    ],
+    [guardianDidAccount]
+  );
+
+  await execute();
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.ethAddresses.length).to.equal(1);
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
+    newController.address.toLowerCase()
+  );
+  expect(didAccountData.ethAddresses[0].role).to.deep.equal({
+    controller: {},
+  });
+  // the lookups follow the controller
+
+  const controllerLookup = getAddressLookupAccount(
+    "evm",
+    controller.address.toLowerCase(),
+    program
+  );
+  const newControllerLookup = getAddressLookupAccount(
+    "evm",
+    newController.address.toLowerCase(),
+    program
+  );
+
+  expect(await program.account.addressLookup.fetchNullable(controllerLookup)).to
+    .be.null;
+  expect(
+    (await program.account.addressLookup.fetch(newControllerLookup)).dids
+  ).to.deep.equal([didStr]);
+};
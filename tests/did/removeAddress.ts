@@ -10,6 +10,11 @@ import {
   removeAddressEVMRemover,
   removeAddressSOLRemover,
 } from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  removeAddressMessage,
+} from "../utils/messages";
 import { expect } from "chai";
 import nacl from "tweetnacl";
 import * as anchor from "@project-serum/anchor";
@@ -26,7 +31,12 @@ export const removeAddressTest = async (
 
   const ethSigner = ethers.Wallet.createRandom();
 
-  const message = `I am creating a new Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
 
   const { actual_message, signature, recoveryId } = await signEthMessage(
     message,
@@ -47,8 +57,24 @@ export const removeAddressTest = async (
 
   const newEthSigner = ethers.Wallet.createRandom();
 
-  const newAddressMessageAsNewAddress = `I am adding myself to the Squircl DID with the address ${newEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
-  const newAddressMessageAsController = `I am adding ${newEthSigner.address.toLowerCase()} to the Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
 
   const {
     actual_message: newAddressActualMessage,
@@ -75,10 +101,17 @@ export const removeAddressTest = async (
     newAddressRecoveryId,
     controllerActualMessage,
     newAddressActualMessage,
-    nonce
+    nonce + 1
   );
 
-  const removeAddressEVMAsEVMControllerMessage = `I am removing ${newEthSigner.address.toLowerCase()} from the Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const removeAddressEVMAsEVMControllerMessage = removeAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    nonce + 2
+  );
 
   const {
     actual_message: removeAddressEVMAsEVMControllerActualMessage,
@@ -97,7 +130,7 @@ export const removeAddressTest = async (
     removeAddressEVMAsEVMControllerActualMessage,
     newEthSigner.address.toLowerCase(),
     { evm: {} },
-    nonce
+    nonce + 2
   );
 
   const didAccountData = await program.account.did.fetch(didAccount);
@@ -105,17 +138,36 @@ export const removeAddressTest = async (
   expect(didAccountData.did).to.equal(didStr);
   expect(didAccountData.ethAddresses.length).to.equal(1);
 
+  const newAddressMessageAsNewAddress2 = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 3
+  );
+  const newAddressMessageAsController2 = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 3
+  );
+
   const {
     actual_message: newAddressActualMessage2,
     signature: newAddressSignature2,
     recoveryId: newAddressRecoveryId2,
-  } = await signEthMessage(newAddressMessageAsNewAddress, newEthSigner);
+  } = await signEthMessage(newAddressMessageAsNewAddress2, newEthSigner);
 
   const {
     actual_message: controllerActualMessage2,
     signature: controllerSignature2,
     recoveryId: controllerRecoveryId2,
-  } = await signEthMessage(newAddressMessageAsController, ethSigner);
+  } = await signEthMessage(newAddressMessageAsController2, ethSigner);
 
   await addAddressEVMwithEVMController(
     program,
@@ -130,10 +182,17 @@ export const removeAddressTest = async (
     newAddressRecoveryId2,
     controllerActualMessage2,
     newAddressActualMessage2,
-    nonce
+    nonce + 3
   );
 
-  const removeAddressEVMAsSelfMessage = `I am removing myself from the Squircl DID with the address ${newEthSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const removeAddressEVMAsSelfMessage = removeAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    nonce + 4
+  );
 
   const {
     actual_message: removeAddressEVMAsSelfActualMessage,
@@ -152,7 +211,7 @@ export const removeAddressTest = async (
     removeAddressEVMAsSelfActualMessage,
     newEthSigner.address.toLowerCase(),
     { evm: {} },
-    nonce
+    nonce + 4
   );
 
   const didAccountData2 = await program.account.did.fetch(didAccount);
@@ -164,8 +223,24 @@ export const removeAddressTest = async (
 
   const keypair = anchor.web3.Keypair.generate();
 
-  const newAddressMessageAsNewAddressSOL = `I am adding myself to the Squircl DID with the address ${keypair.publicKey.toBase58()}. Nonce: ${nonce}`;
-  const newAddressMessageAsControllerSOL = `I am adding ${keypair.publicKey.toBase58()} to the Squircl DID with the address ${ethSigner.address.toLocaleLowerCase()}. Nonce: ${nonce}`;
+  const newAddressMessageAsNewAddressSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 5
+  );
+  const newAddressMessageAsControllerSOL = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 5
+  );
 
   const newMessageEncoded = Uint8Array.from(
     Buffer.from(newAddressMessageAsNewAddressSOL)
@@ -194,10 +269,17 @@ export const removeAddressTest = async (
     keypair.publicKey,
     newAddressSOLSignature,
     newMessageEncoded,
-    nonce
+    nonce + 5
   );
 
-  const removeAddressSOLAsEVMControllerMessage = `I am removing ${keypair.publicKey.toBase58()} from the Squircl DID with the address ${ethSigner.address.toLowerCase()}. Nonce: ${nonce}`;
+  const removeAddressSOLAsEVMControllerMessage = removeAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    nonce + 6
+  );
 
   const {
     actual_message: removeAddressSOLAsEVMControllerActualMessage,
@@ -216,7 +298,7 @@ export const removeAddressTest = async (
     removeAddressSOLAsEVMControllerActualMessage,
     keypair.publicKey.toBase58(),
     { sol: {} },
-    nonce
+    nonce + 6
   );
 
   const didAccountData3 = await program.account.did.fetch(didAccount);
@@ -225,18 +307,37 @@ export const removeAddressTest = async (
   expect(didAccountData3.ethAddresses.length).to.equal(1);
   expect(didAccountData3.solAddresses.length).to.equal(0);
 
+  const newAddressMessageAsNewAddressSOL2 = addAddressMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 7
+  );
+  const newAddressMessageAsControllerSOL2 = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    "admin",
+    nonce + 7
+  );
+
   const newMessageEncoded2 = Uint8Array.from(
-    Buffer.from(newAddressMessageAsNewAddressSOL)
+    Buffer.from(newAddressMessageAsNewAddressSOL2)
   );
 
   const {
     actual_message: controllerActualMessageSOL2,
     signature: controllerSignatureSOL2,
     recoveryId: controllerRecoveryIdSOL2,
-  } = await signEthMessage(newAddressMessageAsControllerSOL, ethSigner);
+  } = await signEthMessage(newAddressMessageAsControllerSOL2, ethSigner);
 
   const newAddressSOLSignature2 = nacl.sign.detached(
-    newMessageEncoded,
+    newMessageEncoded2,
     keypair.secretKey
   );
 
@@ -252,10 +353,17 @@ export const removeAddressTest = async (
     keypair.publicKey,
     newAddressSOLSignature2,
     newMessageEncoded2,
-    nonce
+    nonce + 7
   );
 
-  const removeAddressSOLAsSelfMessage = `I am removing myself from the Squircl DID with the address ${keypair.publicKey.toBase58()}. Nonce: ${nonce}`;
+  const removeAddressSOLAsSelfMessage = removeAddressMessage(
+    program.programId,
+    didStr,
+    keypair.publicKey.toBase58(),
+    "sol",
+    keypair.publicKey.toBase58(),
+    nonce + 8
+  );
 
   const removeAddressSOLAsSelfMessageEncoded = Uint8Array.from(
     Buffer.from(removeAddressSOLAsSelfMessage)
@@ -276,7 +384,7 @@ export const removeAddressTest = async (
     removeAddressSOLAsSelfMessageEncoded,
     keypair.publicKey.toBase58(),
     { sol: {} },
-    nonce
+    nonce + 8
   );
 
   const didAccountData4 = await program.account.did.fetch(didAccount);
@@ -0,0 +1,119 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage, signEthMessageWithNonce } from "../utils/signatures";
+import {
+  addServiceEVM,
+  createDIDEVM,
+  removeServiceEVM,
+} from "../utils/instructions";
+import {
+  addServiceMessage,
+  createMessage,
+  removeServiceMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+
+export const servicesTest = async (program: Program<SquirclDid>, payer: any) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const service = {
+    id: "hub",
+    serviceType: "CredentialHub",
+    endpoint: "https://hub.squircl.xyz",
+  };
+
+  const addMessage = (nonce: number) =>
+    addServiceMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      service,
+      nonce
+    );
+
+  await addServiceEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await signEthMessageWithNonce(addMessage(nonce + 1), ethSigner, nonce + 1),
+    service
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.services).to.deep.equal([service]);
+
+  try {
+    await addServiceEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      await signEthMessageWithNonce(
+        addMessage(nonce + 2),
+        ethSigner,
+        nonce + 2
+      ),
+      service
+    );
+    expect.fail("the same service id was added twice");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: ServiceAlreadyExists. Error Number: 6030. Error Message: Service already exists."
+    );
+  }
+
+  await removeServiceEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await signEthMessageWithNonce(
+      removeServiceMessage(
+        program.programId,
+        didStr,
+        ethSigner.address.toLowerCase(),
+        service.id,
+        nonce + 3
+      ),
+      ethSigner,
+      nonce + 3
+    ),
+    service.id
+  );
+
+  const didAccountData2 = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData2.services).to.be.empty;
+};
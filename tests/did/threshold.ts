@@ -0,0 +1,497 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import {
+  generateRandomDID,
+  getDIDAccount,
+  getRecoveryAccount,
+} from "../utils/pda";
+import { HDNodeWallet, ethers } from "ethers";
+import { signEthMessage, signEthMessageWithNonce } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  cancelRecoveryEVM,
+  changeRoleEVMChanger,
+  createDIDEVM,
+  deactivateDIDEVM,
+  setGuardiansEVM,
+  setThresholdEVM,
+  startRecoveryEVM,
+  transferControllerEVMtoEVM,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  cancelRecoveryMessage,
+  changeRoleMessage,
+  createMessage,
+  deactivateMessage,
+  setGuardiansMessage,
+  setThresholdMessage,
+  startRecoveryMessage,
+  transferControllerMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+import * as anchor from "@project-serum/anchor";
+
+export const thresholdTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const thresholdMessage = (
+    signer: HDNodeWallet,
+    threshold: number,
+    nonce: number
+  ) =>
+    setThresholdMessage(
+      program.programId,
+      didStr,
+      signer.address.toLowerCase(),
+      threshold,
+      nonce
+    );
+
+  const roleMessage = (
+    signer: HDNodeWallet,
+    address: string,
+    role: string,
+    nonce: number
+  ) =>
+    changeRoleMessage(
+      program.programId,
+      didStr,
+      signer.address.toLowerCase(),
+      "evm",
+      address,
+      role,
+      nonce
+    );
+
+  const controller = ethers.Wallet.createRandom();
+  const admin = ethers.Wallet.createRandom();
+  const guardian = ethers.Wallet.createRandom();
+  const recoveredController = ethers.Wallet.createRandom();
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      nonce
+    ),
+    controller
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    controller,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signEthMessage(
+    addAddressMessage(
+      program.programId,
+      didStr,
+      admin.address.toLowerCase(),
+      "evm",
+      admin.address.toLowerCase(),
+      "admin",
+      nonce + 1
+    ),
+    admin
+  );
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signEthMessage(
+    addAddressMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      "evm",
+      admin.address.toLowerCase(),
+      "admin",
+      nonce + 1
+    ),
+    controller
+  );
+
+  await addAddressEVMwithEVMController(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    controller,
+    admin,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1
+  );
+
+  // a recovery is pending while the threshold is raised, so that cancelling it
+  // can be checked against the threshold below
+
+  const guardiansMessage = await signEthMessage(
+    setGuardiansMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      [`evm:${guardian.address.toLowerCase()}`],
+      1,
+      3600,
+      nonce + 10
+    ),
+    controller
+  );
+
+  await setGuardiansEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    controller,
+    guardiansMessage.signature,
+    guardiansMessage.recoveryId,
+    guardiansMessage.actual_message,
+    [
+      {
+        address: {
+          chain: { evm: {} },
+          address: guardian.address.toLowerCase(),
+        },
+      },
+    ],
+    1,
+    3600,
+    nonce + 10
+  );
+
+  const recoveryMessage = (signer: HDNodeWallet) =>
+    signEthMessageWithNonce(
+      startRecoveryMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        recoveredController.address.toLowerCase(),
+        nonce + 10
+      ),
+      signer,
+      nonce + 10
+    );
+
+  await startRecoveryEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await recoveryMessage(recoveredController),
+    [await recoveryMessage(guardian)]
+  );
+
+  // a threshold above the number of controllers and admins would brick the DID
+
+  const tooHigh = await signEthMessageWithNonce(
+    thresholdMessage(controller, 3, nonce + 2),
+    controller,
+    nonce + 2
+  );
+
+  try {
+    await setThresholdEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      tooHigh.signature,
+      tooHigh.recoveryId,
+      tooHigh.actual_message,
+      3,
+      nonce + 2
+    );
+    expect.fail("threshold above the number of signers was accepted");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: InvalidThreshold. Error Number: 6022. Error Message: Threshold must be between 1 and the number of controller and admin addresses."
+    );
+  }
+
+  const setTwo = await signEthMessageWithNonce(
+    thresholdMessage(controller, 2, nonce + 3),
+    controller,
+    nonce + 3
+  );
+
+  await setThresholdEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    controller,
+    setTwo.signature,
+    setTwo.recoveryId,
+    setTwo.actual_message,
+    2,
+    nonce + 3
+  );
+
+  expect((await program.account.did.fetch(didAccount)).threshold).to.equal(2);
+
+  // the controller alone is no longer enough
+
+  const adminAddress = admin.address.toLowerCase();
+
+  const alone = await signEthMessageWithNonce(
+    roleMessage(controller, adminAddress, "assertion", nonce + 4),
+    controller,
+    nonce + 4
+  );
+
+  try {
+    await changeRoleEVMChanger(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      alone.signature,
+      alone.recoveryId,
+      alone.actual_message,
+      adminAddress,
+      { evm: {} },
+      { assertion: {} },
+      nonce + 4
+    );
+    expect.fail("single signature met a threshold of 2");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: ThresholdNotMet. Error Number: 6020. Error Message: Not enough distinct signatures to meet the DID threshold."
+    );
+  }
+
+  // signing twice with the same address does not count twice
+
+  try {
+    await changeRoleEVMChanger(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      alone.signature,
+      alone.recoveryId,
+      alone.actual_message,
+      adminAddress,
+      { evm: {} },
+      { assertion: {} },
+      nonce + 4,
+      [
+        await signEthMessageWithNonce(
+          roleMessage(controller, adminAddress, "assertion", nonce + 5),
+          controller,
+          nonce + 5
+        ),
+      ]
+    );
+    expect.fail("duplicate signer met a threshold of 2");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: DuplicateSigner. Error Number: 6021. Error Message: The same address signed more than once."
+    );
+  }
+
+  // demoting the admin would leave a single signer under a threshold of 2
+
+  try {
+    await changeRoleEVMChanger(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      alone.signature,
+      alone.recoveryId,
+      alone.actual_message,
+      adminAddress,
+      { evm: {} },
+      { assertion: {} },
+      nonce + 4,
+      [
+        await signEthMessageWithNonce(
+          roleMessage(admin, adminAddress, "assertion", nonce + 4),
+          admin,
+          nonce + 4
+        ),
+      ]
+    );
+    expect.fail("demotion left the threshold unreachable");
+  } catch (e) {
+    expect(e.toString()).to.equal(
+      "AnchorError occurred. Error Code: InvalidThreshold. Error Number: 6022. Error Message: Threshold must be between 1 and the number of controller and admin addresses."
+    );
+  }
+
+  // the controller alone can't deactivate the did either
+
+  const recipient = anchor.web3.Keypair.generate().publicKey;
+
+  const deactivateAlone = await signEthMessage(
+    deactivateMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      recipient,
+      nonce + 7
+    ),
+    controller
+  );
+
+  try {
+    await deactivateDIDEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      recipient,
+      controller,
+      deactivateAlone.signature,
+      deactivateAlone.recoveryId,
+      deactivateAlone.actual_message,
+      nonce + 7
+    );
+    expect.fail("single signature deactivated a did with a threshold of 2");
+  } catch (e) {
+    expect(e.toString()).to.contain("ThresholdNotMet");
+  }
+
+  // nor hand control to another address, even with its consent
+
+  const transferMessage = (signer: HDNodeWallet) =>
+    signEthMessage(
+      transferControllerMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        adminAddress,
+        nonce + 8
+      ),
+      signer
+    );
+
+  const controllerTransfer = await transferMessage(controller);
+  const adminTransfer = await transferMessage(admin);
+
+  try {
+    await transferControllerEVMtoEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      controller,
+      admin,
+      controllerTransfer.signature,
+      controllerTransfer.recoveryId,
+      adminTransfer.signature,
+      adminTransfer.recoveryId,
+      controllerTransfer.actual_message,
+      adminTransfer.actual_message,
+      nonce + 8
+    );
+    expect.fail("single signature transferred a did with a threshold of 2");
+  } catch (e) {
+    expect(e.toString()).to.contain("ThresholdNotMet");
+  }
+
+  // or cancel a recovery, which takes the admin co-signing
+
+  const cancelMessage = (signer: HDNodeWallet) =>
+    signEthMessageWithNonce(
+      cancelRecoveryMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        nonce + 9
+      ),
+      signer,
+      nonce + 9
+    );
+
+  try {
+    await cancelRecoveryEVM(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      await cancelMessage(controller)
+    );
+    expect.fail("single signature cancelled a recovery with a threshold of 2");
+  } catch (e) {
+    expect(e.toString()).to.contain("ThresholdNotMet");
+  }
+
+  await cancelRecoveryEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    await cancelMessage(controller),
+    [await cancelMessage(admin)]
+  );
+
+  expect(
+    (await program.account.recovery.fetch(getRecoveryAccount(didStr, program)))
+      .pending
+  ).to.be.null;
+
+  // both signers can lower the threshold again
+
+  const setOne = await signEthMessageWithNonce(
+    thresholdMessage(controller, 1, nonce + 6),
+    controller,
+    nonce + 6
+  );
+
+  await setThresholdEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    controller,
+    setOne.signature,
+    setOne.recoveryId,
+    setOne.actual_message,
+    1,
+    nonce + 6,
+    [
+      await signEthMessageWithNonce(
+        thresholdMessage(admin, 1, nonce + 6),
+        admin,
+        nonce + 6
+      ),
+    ]
+  );
+
+  expect((await program.account.did.fetch(didAccount)).threshold).to.equal(1);
+};
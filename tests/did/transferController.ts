@@ -0,0 +1,163 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  createDIDEVM,
+  transferControllerEVMtoEVM,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  transferControllerMessage,
+} from "../utils/messages";
+import { getAddressString } from "../utils/addresses";
+import { expect } from "chai";
+
+export const transferControllerTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const didStr = generateRandomDID();
+
+  const didAccount = getDIDAccount(didStr, program);
+
+  const ethSigner = ethers.Wallet.createRandom();
+
+  const message = createMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    nonce
+  );
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    message,
+    ethSigner
+  );
+
+  await createDIDEVM(
+    program,
+    didStr,
+    ethSigner,
+    signature,
+    recoveryId,
+    didAccount,
+    actual_message,
+    payer,
+    nonce
+  );
+
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const newAddressMessageAsNewAddress = addAddressMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+  const newAddressMessageAsController = addAddressMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    "admin",
+    nonce + 1
+  );
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signEthMessage(newAddressMessageAsNewAddress, newEthSigner);
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signEthMessage(newAddressMessageAsController, ethSigner);
+
+  await addAddressEVMwithEVMController(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    newEthSigner,
+    controllerSignature,
+    controllerRecoveryId,
+    newAddressSignature,
+    newAddressRecoveryId,
+    controllerActualMessage,
+    newAddressActualMessage,
+    nonce + 1
+  );
+
+  const transferMessageAsController = transferControllerMessage(
+    program.programId,
+    didStr,
+    ethSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    nonce + 2
+  );
+  const transferMessageAsNewController = transferControllerMessage(
+    program.programId,
+    didStr,
+    newEthSigner.address.toLowerCase(),
+    "evm",
+    newEthSigner.address.toLowerCase(),
+    nonce + 2
+  );
+
+  const {
+    actual_message: transferControllerActualMessage,
+    signature: transferControllerSignature,
+    recoveryId: transferControllerRecoveryId,
+  } = await signEthMessage(transferMessageAsController, ethSigner);
+
+  const {
+    actual_message: transferNewControllerActualMessage,
+    signature: transferNewControllerSignature,
+    recoveryId: transferNewControllerRecoveryId,
+  } = await signEthMessage(transferMessageAsNewController, newEthSigner);
+
+  await transferControllerEVMtoEVM(
+    program,
+    didStr,
+    didAccount,
+    payer,
+    ethSigner,
+    newEthSigner,
+    transferControllerSignature,
+    transferControllerRecoveryId,
+    transferNewControllerSignature,
+    transferNewControllerRecoveryId,
+    transferControllerActualMessage,
+    transferNewControllerActualMessage,
+    nonce + 2
+  );
+
+  const didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(getAddressString(didAccountData.ethAddresses[0])).to.equal(
+    ethSigner.address.toLowerCase()
+  );
+  expect(didAccountData.ethAddresses[0].role).to.deep.equal({
+    admin: {},
+  });
+  expect(getAddressString(didAccountData.ethAddresses[1])).to.equal(
+    newEthSigner.address.toLowerCase()
+  );
+  expect(didAccountData.ethAddresses[1].role).to.deep.equal({
+    controller: {},
+  });
+};
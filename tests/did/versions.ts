@@ -0,0 +1,132 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  createDIDEVM,
+} from "../utils/instructions";
+import { addAddressMessage, createMessage } from "../utils/messages";
+import { getEvent } from "../utils/events";
+import { expect } from "chai";
+import crypto from "crypto";
+
+const DISCRIMINATOR_LEN = 8;
+
+const stateHash = (state: Buffer) =>
+  Array.from(crypto.createHash("sha256").update(state).digest());
+
+export const versionsTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const ethSigner = ethers.Wallet.createRandom();
+  const newEthSigner = ethers.Wallet.createRandom();
+
+  const didStr = generateRandomDID();
+  const didAccount = getDIDAccount(didStr, program);
+
+  const getState = async () => {
+    const { data } = await program.provider.connection.getAccountInfo(
+      didAccount
+    );
+
+    return data.subarray(DISCRIMINATOR_LEN);
+  };
+
+  // a new did starts the history at version 1
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      ethSigner.address.toLowerCase(),
+      nonce
+    ),
+    ethSigner
+  );
+
+  const created = await getEvent(
+    program,
+    await createDIDEVM(
+      program,
+      didStr,
+      ethSigner,
+      signature,
+      recoveryId,
+      didAccount,
+      actual_message,
+      payer,
+      nonce
+    ),
+    "DidUpdated"
+  );
+
+  let didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.version.toNumber()).to.equal(1);
+  expect(didAccountData.prevStateHash).to.deep.equal(Array(32).fill(0));
+  expect(created.data.did).to.equal(didStr);
+  expect(created.data.version.toNumber()).to.equal(1);
+  expect(Buffer.from(created.data.state)).to.deep.equal(await getState());
+
+  // each mutation is a new version committing to the state it replaced
+
+  const signAddMessage = (signer: HDNodeWallet) =>
+    signEthMessage(
+      addAddressMessage(
+        program.programId,
+        didStr,
+        signer.address.toLowerCase(),
+        "evm",
+        newEthSigner.address.toLowerCase(),
+        "admin",
+        nonce + 1
+      ),
+      signer
+    );
+
+  const {
+    actual_message: controllerActualMessage,
+    signature: controllerSignature,
+    recoveryId: controllerRecoveryId,
+  } = await signAddMessage(ethSigner);
+
+  const {
+    actual_message: newAddressActualMessage,
+    signature: newAddressSignature,
+    recoveryId: newAddressRecoveryId,
+  } = await signAddMessage(newEthSigner);
+
+  const updated = await getEvent(
+    program,
+    await addAddressEVMwithEVMController(
+      program,
+      didStr,
+      didAccount,
+      payer,
+      ethSigner,
+      newEthSigner,
+      controllerSignature,
+      controllerRecoveryId,
+      newAddressSignature,
+      newAddressRecoveryId,
+      controllerActualMessage,
+      newAddressActualMessage,
+      nonce + 1
+    ),
+    "DidUpdated"
+  );
+
+  didAccountData = await program.account.did.fetch(didAccount);
+
+  expect(didAccountData.version.toNumber()).to.equal(2);
+  expect(didAccountData.prevStateHash).to.deep.equal(
+    stateHash(Buffer.from(created.data.state))
+  );
+  expect(updated.data.version.toNumber()).to.equal(2);
+  expect(Buffer.from(updated.data.state)).to.deep.equal(await getState());
+};
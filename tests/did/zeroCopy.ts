@@ -0,0 +1,276 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import { generateRandomDID, getDIDAccount } from "../utils/pda";
+import { ethers, HDNodeWallet } from "ethers";
+import { signEthMessage } from "../utils/signatures";
+import {
+  addAddressEVMwithEVMController,
+  addAddressZeroCopyEVM,
+  createDIDEVM,
+  createDIDZeroCopyEVM,
+  removeAddressZeroCopyEVM,
+} from "../utils/instructions";
+import {
+  addAddressMessage,
+  createMessage,
+  removeAddressMessage,
+} from "../utils/messages";
+import { expect } from "chai";
+
+// discriminator and header of ZeroCopyDid
+const ZERO_COPY_HEADER_LEN = 96;
+const ADDRESS_COUNT_OFFSET = 88;
+const ADDRESS_CAPACITY_OFFSET = 90;
+// added_at, 33 byte padded key, chain, role and padding
+const ADDRESS_SLOT_LEN = 48;
+const SLOT_KEY_OFFSET = 8;
+const SLOT_KEY_LEN = 33;
+const SLOT_CHUNK = 8;
+
+// enough addresses for the zero-copy DID to grow past its first chunk of slots
+const ADDED_ADDRESSES = 10;
+
+type CreateIx = typeof createDIDEVM;
+type AddIx = typeof addAddressEVMwithEVMController;
+
+const getComputeUnits = async (program: Program<SquirclDid>, sig: string) => {
+  const connection = program.provider.connection;
+
+  await connection.confirmTransaction(sig, "confirmed");
+
+  const tx = await connection.getTransaction(sig, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+
+  return tx.meta.computeUnitsConsumed;
+};
+
+const getZeroCopyKeys = (data: Buffer) => {
+  const count = data.readUInt16LE(ADDRESS_COUNT_OFFSET);
+
+  return [...Array(count).keys()].map((i) => {
+    const start = ZERO_COPY_HEADER_LEN + i * ADDRESS_SLOT_LEN + SLOT_KEY_OFFSET;
+
+    return data.subarray(start, start + SLOT_KEY_LEN);
+  });
+};
+
+// creates a DID with the given create and add address instructions, and
+// returns the compute units each of them consumed
+const benchmarkDID = async (
+  program: Program<SquirclDid>,
+  payer: any,
+  createIx: CreateIx,
+  addIx: AddIx,
+  didStr: string,
+  controller: HDNodeWallet,
+  newSigners: HDNodeWallet[],
+  nonce: number
+) => {
+  const didAccount = getDIDAccount(didStr, program);
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    createMessage(
+      program.programId,
+      didStr,
+      controller.address.toLowerCase(),
+      nonce
+    ),
+    controller
+  );
+
+  const createComputeUnits = await getComputeUnits(
+    program,
+    await createIx(
+      program,
+      didStr,
+      controller,
+      signature,
+      recoveryId,
+      didAccount,
+      actual_message,
+      payer,
+      nonce
+    )
+  );
+
+  const addComputeUnits = [];
+
+  for (const [i, newSigner] of newSigners.entries()) {
+    const addNonce = nonce + 1 + i;
+
+    const signAddMessage = (signer: HDNodeWallet) =>
+      signEthMessage(
+        addAddressMessage(
+          program.programId,
+          didStr,
+          signer.address.toLowerCase(),
+          "evm",
+          newSigner.address.toLowerCase(),
+          "admin",
+          addNonce
+        ),
+        signer
+      );
+
+    const {
+      actual_message: controllerActualMessage,
+      signature: controllerSignature,
+      recoveryId: controllerRecoveryId,
+    } = await signAddMessage(controller);
+
+    const {
+      actual_message: newAddressActualMessage,
+      signature: newAddressSignature,
+      recoveryId: newAddressRecoveryId,
+    } = await signAddMessage(newSigner);
+
+    addComputeUnits.push(
+      await getComputeUnits(
+        program,
+        await addIx(
+          program,
+          didStr,
+          didAccount,
+          payer,
+          controller,
+          newSigner,
+          controllerSignature,
+          controllerRecoveryId,
+          newAddressSignature,
+          newAddressRecoveryId,
+          controllerActualMessage,
+          newAddressActualMessage,
+          addNonce
+        )
+      )
+    );
+  }
+
+  return { createComputeUnits, addComputeUnits };
+};
+
+export const zeroCopyTest = async (
+  program: Program<SquirclDid>,
+  payer: any
+) => {
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const controller = ethers.Wallet.createRandom();
+  const newSigners = [...Array(ADDED_ADDRESSES)].map(() =>
+    ethers.Wallet.createRandom()
+  );
+
+  const borshDidStr = generateRandomDID();
+  const zeroCopyDidStr = generateRandomDID();
+
+  const borsh = await benchmarkDID(
+    program,
+    payer,
+    createDIDEVM,
+    addAddressEVMwithEVMController,
+    borshDidStr,
+    controller,
+    newSigners,
+    nonce
+  );
+  const zeroCopy = await benchmarkDID(
+    program,
+    payer,
+    createDIDZeroCopyEVM,
+    addAddressZeroCopyEVM,
+    zeroCopyDidStr,
+    controller,
+    newSigners,
+    nonce
+  );
+
+  // compute units vary with the addresses and the toolchain, so they are
+  // reported rather than asserted on
+  console.table([
+    {
+      instruction: "create_did",
+      borsh: borsh.createComputeUnits,
+      zeroCopy: zeroCopy.createComputeUnits,
+    },
+    ...borsh.addComputeUnits.map((borshComputeUnits, i) => ({
+      instruction: `add_address (${i + 2} addresses)`,
+      borsh: borshComputeUnits,
+      zeroCopy: zeroCopy.addComputeUnits[i],
+    })),
+  ]);
+
+  // the zero-copy DID grows a chunk of slots at a time and keeps its addresses
+  // sorted
+
+  const zeroCopyDidAccount = getDIDAccount(zeroCopyDidStr, program);
+
+  let { data } = await program.provider.connection.getAccountInfo(
+    zeroCopyDidAccount
+  );
+
+  const capacity = 2 * SLOT_CHUNK;
+
+  expect(data.readUInt16LE(ADDRESS_CAPACITY_OFFSET)).to.equal(capacity);
+  expect(data.length).to.equal(
+    ZERO_COPY_HEADER_LEN + capacity * ADDRESS_SLOT_LEN
+  );
+
+  const expectedKeys = [controller, ...newSigners]
+    .map((signer) =>
+      Buffer.concat([
+        Buffer.from(signer.address.slice(2), "hex"),
+        Buffer.alloc(SLOT_KEY_LEN - 20),
+      ])
+    )
+    .sort(Buffer.compare);
+
+  expect(getZeroCopyKeys(data)).to.deep.equal(expectedKeys);
+
+  // the controller can remove an address, which frees its slot
+
+  const toRemove = newSigners[0].address.toLowerCase();
+
+  const removeNonce = nonce + 1 + ADDED_ADDRESSES;
+
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    removeAddressMessage(
+      program.programId,
+      zeroCopyDidStr,
+      controller.address.toLowerCase(),
+      "evm",
+      toRemove,
+      removeNonce
+    ),
+    controller
+  );
+
+  await removeAddressZeroCopyEVM(
+    program,
+    zeroCopyDidStr,
+    zeroCopyDidAccount,
+    payer,
+    controller,
+    signature,
+    recoveryId,
+    actual_message,
+    toRemove,
+    { evm: {} },
+    removeNonce
+  );
+
+  ({ data } = await program.provider.connection.getAccountInfo(
+    zeroCopyDidAccount
+  ));
+
+  const removedKey = Buffer.concat([
+    Buffer.from(toRemove.slice(2), "hex"),
+    Buffer.alloc(SLOT_KEY_LEN - 20),
+  ]);
+
+  expect(getZeroCopyKeys(data)).to.deep.equal(
+    expectedKeys.filter((key) => !key.equals(removedKey))
+  );
+  expect(data.readUInt16LE(ADDRESS_CAPACITY_OFFSET)).to.equal(capacity);
+};
@@ -8,13 +8,38 @@ import {
   createDIDEvmTestInvalidSig,
   createDIDSolTest,
   createDIDSolTestInvalidSig,
+  createDIDInvalidDidStrTest,
+  createDerivedDIDSolTest,
+  createDIDBtcTest,
+  createDIDPasskeyTest,
+  createDIDEip712Test,
+  createDIDSolOffchainMessageTest,
+  createDIDEthereumRecoverTest,
+  createDIDBytesTest,
+  createDIDMalformedSigTest,
 } from "./did/createDID";
 import { addAddressEth, addAddressSol } from "./did/addAddress";
 import { removeAddressTest } from "./did/removeAddress";
 import {
   issueCredentialEvmTest,
   issueCredentialSolTest,
+  issueCredentialSolDigestTest,
+  issueCredentialAuthenticationTest,
 } from "./did/issueCredential";
+import { replayedSignatureTest } from "./did/nonceRegistry";
+import { changeRoleTest } from "./did/changeRole";
+import { transferControllerTest } from "./did/transferController";
+import { deactivateDIDTest } from "./did/deactivateDID";
+import { migrateDIDTest, migrateLegacyDIDTest } from "./did/migrateDID";
+import { zeroCopyTest } from "./did/zeroCopy";
+import { addressLookupTest } from "./did/addressLookup";
+import { aliasTest } from "./did/alias";
+import { eventsTest } from "./did/events";
+import { versionsTest } from "./did/versions";
+import { addressValidityTest } from "./did/addressValidity";
+import { thresholdTest } from "./did/threshold";
+import { recoveryTest } from "./did/recovery";
+import { servicesTest } from "./did/services";
 
 lumina();
 
@@ -42,6 +67,42 @@ describe("squircl_identity", () => {
     await createDIDSolTestInvalidSig(program, payer);
   });
 
+  it("should not create a did if the did string is invalid", async () => {
+    await createDIDInvalidDidStrTest(program, payer);
+  });
+
+  it("can create a did derived from the controller address", async () => {
+    await createDerivedDIDSolTest(program, payer);
+  });
+
+  it("should create a new did document with a bitcoin wallet", async () => {
+    await createDIDBtcTest(program, payer);
+  });
+
+  it("should create a new did document with a passkey", async () => {
+    await createDIDPasskeyTest(program, payer);
+  });
+
+  it("should create a new did document with an EIP-712 signature", async () => {
+    await createDIDEip712Test(program, payer);
+  });
+
+  it("should create a new did document with a solana off-chain message", async () => {
+    await createDIDSolOffchainMessageTest(program, payer);
+  });
+
+  it("should create a new did document with a recovered ethereum signature", async () => {
+    await createDIDEthereumRecoverTest(program, payer);
+  });
+
+  it("should create a new did document with raw byte signatures", async () => {
+    await createDIDBytesTest(program, payer);
+  });
+
+  it("should reject a malformed base58 address", async () => {
+    await createDIDMalformedSigTest(program, payer);
+  });
+
   it("can add a new eth and sol address to an existing did with eth controller", async () => {
     await addAddressEth(program, payer);
   });
@@ -61,4 +122,77 @@ describe("squircl_identity", () => {
   it("can issue a credential sol", async () => {
     await issueCredentialSolTest(program, payer);
   });
+
+  it("can issue a credential with a long uri by signing its digest", async () => {
+    await issueCredentialSolDigestTest(program, payer);
+  });
+
+  it("should not issue a credential with an authentication key", async () => {
+    await issueCredentialAuthenticationTest(program, payer);
+  });
+
+  it("can change the role of an address as controller", async () => {
+    await changeRoleTest(program, payer);
+  });
+
+  it("can transfer control of a did to another address", async () => {
+    await transferControllerTest(program, payer);
+  });
+
+  it("can deactivate a did and leave a tombstone behind", async () => {
+    await deactivateDIDTest(program, payer);
+  });
+
+  it("stores raw addresses and only migrates legacy dids", async () => {
+    await migrateDIDTest(program, payer);
+  });
+
+  it("migrates a legacy did to raw addresses", async () => {
+    await migrateLegacyDIDTest(program, payer);
+  });
+
+  // zero-copy dids are only enabled in programs built with the
+  // zero-copy-bench feature, ZERO_COPY_BENCH=1 runs their benchmark
+  (process.env.ZERO_COPY_BENCH ? it : it.skip)(
+    "keeps addresses sorted in a zero-copy did",
+    async () => {
+      await zeroCopyTest(program, payer);
+    }
+  );
+
+  it("keeps a lookup from addresses to their dids", async () => {
+    await addressLookupTest(program, payer);
+  });
+
+  it("lets a controller claim, transfer and release an alias", async () => {
+    await aliasTest(program, payer);
+  });
+
+  it("emits an event for each did mutation", async () => {
+    await eventsTest(program, payer);
+  });
+
+  it("chains every version of a did to the state it replaced", async () => {
+    await versionsTest(program, payer);
+  });
+
+  it("only accepts addresses within their validity window", async () => {
+    await addressValidityTest(program, payer);
+  });
+
+  it("requires as many distinct signatures as the did threshold", async () => {
+    await thresholdTest(program, payer);
+  });
+
+  it("lets a quorum of guardians recover a did after its delay", async () => {
+    await recoveryTest(program, payer);
+  });
+
+  it("can add and remove service endpoints", async () => {
+    await servicesTest(program, payer);
+  });
+
+  it("should not accept a replayed signature", async () => {
+    await replayedSignatureTest(program, payer);
+  });
 });
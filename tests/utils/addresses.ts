@@ -0,0 +1,45 @@
+import { getBytes, hexlify } from "ethers";
+import bs58 from "bs58";
+import { toP2PKHAddress } from "./signatures";
+
+type StoredAddress = {
+  address: Buffer;
+  chain: object;
+};
+
+// the string form of an address stored as its raw key, as it appears in messages
+export const getAddressString = ({ address, chain }: StoredAddress) => {
+  if ("evm" in chain) {
+    return hexlify(address);
+  }
+
+  if ("btc" in chain) {
+    if (address[0] !== 0) {
+      throw new Error("only P2PKH addresses are used in tests");
+    }
+
+    return toP2PKHAddress(address.subarray(1));
+  }
+
+  return bs58.encode(address);
+};
+
+// the raw key of an address in its string form, undecodable addresses fall
+// back to their bytes like they do in the program
+export const getAddressKey = (chain: string, address: string) => {
+  try {
+    if (chain === "evm") {
+      return Buffer.from(getBytes(address));
+    }
+
+    if (chain === "btc") {
+      // address type (0 for P2PKH, like its version byte) || hash160, without
+      // the checksum
+      return Buffer.from(bs58.decode(address)).subarray(0, 21);
+    }
+
+    return Buffer.from(bs58.decode(address));
+  } catch (e) {
+    return Buffer.from(address);
+  }
+};
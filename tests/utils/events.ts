@@ -0,0 +1,40 @@
+import { Program } from "@project-serum/anchor";
+import { SquirclDid } from "../../target/types/squircl_did";
+import bs58 from "bs58";
+
+// length of the tag anchor prepends to the data of a self-CPI event
+const EVENT_IX_TAG_LEN = 8;
+
+// decodes the events the program emitted through a self-CPI in the transaction
+export const getEvents = async (program: Program<SquirclDid>, sig: string) => {
+  const connection = program.provider.connection;
+
+  await connection.confirmTransaction(sig, "confirmed");
+
+  const tx = await connection.getTransaction(sig, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+
+  const accountKeys = tx.transaction.message.getAccountKeys().staticAccountKeys;
+
+  return tx.meta.innerInstructions
+    .flatMap(({ instructions }) => instructions)
+    .filter(({ programIdIndex }) =>
+      accountKeys[programIdIndex].equals(program.programId)
+    )
+    .map(({ data }) =>
+      program.coder.events.decode(
+        Buffer.from(bs58.decode(data))
+          .subarray(EVENT_IX_TAG_LEN)
+          .toString("base64")
+      )
+    )
+    .filter((event) => event !== null);
+};
+
+export const getEvent = async (
+  program: Program<SquirclDid>,
+  sig: string,
+  name: string
+) => (await getEvents(program, sig)).find((event) => event.name === name);
@@ -4,6 +4,123 @@ import base58 from "bs58";
 import { HDNodeWallet } from "ethers";
 import { arrayify } from "@ethersproject/bytes";
 import bs58 from "bs58";
+import {
+  getAddressLookupAccount,
+  getAliasAccount,
+  getDIDAccount,
+  getEventAuthorityAccount,
+  getNonceRegistryAccount,
+  getRecoveryAccount,
+} from "./pda";
+import { getAddressString } from "./addresses";
+
+export type SignedEthMessage = {
+  ethSigner: HDNodeWallet;
+  signature: Uint8Array;
+  recoveryId: number;
+  actual_message: Buffer;
+  nonce: number;
+};
+
+// sigs pointing at consecutive slots of the secp256k1 instruction at `index`
+const toEthSigs = (
+  messages: SignedEthMessage[],
+  index: number,
+  firstSlot: number = 0
+) =>
+  messages.map((message, i) => ({
+    eth: {
+      ethSig: {
+        addressBase58: base58.encode(
+          arrayify(message.ethSigner.address.toLowerCase())
+        ),
+        sigBase58: base58.encode(message.signature),
+        recoveryId: message.recoveryId,
+      },
+      index,
+      slot: firstSlot + i,
+      nonce: new anchor.BN(message.nonce),
+    },
+  }));
+
+// a single secp256k1 instruction verifying all the messages, to be placed at `index` in the
+// transaction, the offsets of each signature point back at its own data
+const toSecp256k1Instructions = (
+  messages: SignedEthMessage[],
+  index: number
+) => {
+  if (messages.length === 0) {
+    return [];
+  }
+
+  const offsets = Buffer.alloc(1 + 11 * messages.length);
+  offsets.writeUInt8(messages.length, 0);
+
+  const payloads: Buffer[] = [];
+  let dataOffset = offsets.length;
+
+  messages.forEach((message, i) => {
+    const entry = 1 + 11 * i;
+
+    // eth address (20) || signature (64) || recovery id (1) || message
+    offsets.writeUInt16LE(dataOffset + 20, entry); // signature offset
+    offsets.writeUInt8(index, entry + 2);
+    offsets.writeUInt16LE(dataOffset, entry + 3); // eth address offset
+    offsets.writeUInt8(index, entry + 5);
+    offsets.writeUInt16LE(dataOffset + 20 + 64 + 1, entry + 6); // message offset
+    offsets.writeUInt16LE(message.actual_message.length, entry + 8);
+    offsets.writeUInt8(index, entry + 10);
+
+    const payload = Buffer.concat([
+      Buffer.from(arrayify(message.ethSigner.address.toLowerCase())),
+      Buffer.from(message.signature),
+      Buffer.from([message.recoveryId]),
+      message.actual_message,
+    ]);
+
+    payloads.push(payload);
+    dataOffset += payload.length;
+  });
+
+  return [
+    new anchor.web3.TransactionInstruction({
+      programId: anchor.web3.Secp256k1Program.programId,
+      keys: [],
+      data: Buffer.concat([offsets, ...payloads]),
+    }),
+  ];
+};
+
+// the secp256r1 precompile shares the ed25519 layout, with a 33 byte public key
+const SECP256R1_PROGRAM_ID = new anchor.web3.PublicKey(
+  "Secp256r1SigVerify1111111111111111111111111"
+);
+
+const toSecp256r1Instruction = (
+  publicKey: Uint8Array,
+  message: Uint8Array,
+  signature: Uint8Array
+) => {
+  const publicKeyOffset = 16;
+  const signatureOffset = publicKeyOffset + publicKey.length;
+  const messageDataOffset = signatureOffset + signature.length;
+
+  const header = Buffer.alloc(publicKeyOffset);
+  header.writeUInt8(1, 0); // num_signatures
+  header.writeUInt16LE(signatureOffset, 2);
+  header.writeUInt16LE(0xffff, 4);
+  header.writeUInt16LE(publicKeyOffset, 6);
+  header.writeUInt16LE(0xffff, 8);
+  header.writeUInt16LE(messageDataOffset, 10);
+  header.writeUInt16LE(message.length, 12);
+  header.writeUInt16LE(0xffff, 14);
+
+  return new anchor.web3.TransactionInstruction({
+    programId: SECP256R1_PROGRAM_ID,
+    keys: [],
+    data: Buffer.concat([header, publicKey, signature, message]),
+  });
+};
 
 export const createDIDEVM = async (
   program: anchor.Program<SquirclDid>,
@@ -43,13 +160,22 @@ export const createDIDEVM = async (
           recoveryId: recoveryId,
         },
         index: 0,
+        slot: 0,
         nonce: new anchor.BN(nonce),
       },
     })
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        ethSigner.address.toLowerCase(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -86,6 +212,151 @@ export const createDIDEVM = async (
   return sig;
 };
 
+export const createDIDEVMRecover = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  nonce: number
+) => {
+  // the signer is recovered with the secp256k1_recover syscall, no precompile needed
+  const sig = await program.methods
+    .createDid(didStr, {
+      ethRecover: {
+        ethSig: {
+          addressBase58: base58.encode(
+            arrayify(ethSigner.address.toLowerCase())
+          ),
+          sigBase58: base58.encode(signature),
+          recoveryId: recoveryId,
+        },
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        ethSigner.address.toLowerCase(),
+        program
+      ),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .rpc();
+
+  return sig;
+};
+
+export const createDIDEIP712 = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  didAccount: anchor.web3.PublicKey,
+  actual_message: Buffer,
+  payer: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .createDid(didStr, {
+      eip712: {
+        ethSig: {
+          addressBase58: base58.encode(
+            arrayify(ethSigner.address.toLowerCase())
+          ),
+          sigBase58: base58.encode(signature),
+          recoveryId: recoveryId,
+        },
+        index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        ethSigner.address.toLowerCase(),
+        program
+      ),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+// same as createDIDEVM with the address and signature as raw bytes
+export const createDIDEVMBytes = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  didAccount: anchor.web3.PublicKey,
+  actual_message: Buffer,
+  payer: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .createDid(didStr, {
+      ethBytes: {
+        ethSig: {
+          address: Array.from(arrayify(ethSigner.address.toLowerCase())),
+          sig: Array.from(signature),
+          recoveryId: recoveryId,
+        },
+        index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        ethSigner.address.toLowerCase(),
+        program
+      ),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
 export const createDIDSOL = async (
   program: anchor.Program<SquirclDid>,
   didStr: string,
@@ -104,13 +375,70 @@ export const createDIDSOL = async (
           sigBase58: bs58.encode(signature),
         },
         index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "sol",
+        keypair.publicKey.toBase58(),
+        program
+      ),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Ed25519Program.createInstructionWithPublicKey({
+        publicKey: keypair.publicKey.toBytes(),
+        message: messageEncoded,
+        signature: signature,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+// same as createDIDSOL with the address and signature as raw bytes
+export const createDIDSOLBytes = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  keypair: anchor.web3.Keypair,
+  signature: Uint8Array,
+  messageEncoded: Uint8Array,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .createDid(didStr, {
+      solBytes: {
+        solSig: {
+          address: Array.from(keypair.publicKey.toBytes()),
+          sig: Array.from(signature),
+        },
+        index: 0,
+        slot: 0,
         nonce: new anchor.BN(nonce),
       },
     })
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "sol",
+        keypair.publicKey.toBase58(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -124,6 +452,92 @@ export const createDIDSOL = async (
   return sig;
 };
 
+export const createDIDBTC = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  address: string,
+  signature: Uint8Array,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  nonce: number
+) => {
+  // bitcoin signatures are checked with the secp256k1_recover syscall, no precompile needed
+  const sig = await program.methods
+    .createDid(didStr, {
+      btc: {
+        btcSig: {
+          address,
+          sigBase58: bs58.encode(signature),
+        },
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount("btc", address, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .rpc();
+
+  return sig;
+};
+
+export const createDIDPasskey = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  publicKey: Uint8Array,
+  signed: {
+    signature: Uint8Array;
+    authenticatorData: Buffer;
+    clientDataJSON: string;
+    signedData: Buffer;
+  },
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .createDid(didStr, {
+      passkey: {
+        passkeySig: {
+          addressBase58: bs58.encode(publicKey),
+          sigBase58: bs58.encode(signed.signature),
+          authenticatorData: signed.authenticatorData,
+          clientDataJson: signed.clientDataJSON,
+        },
+        index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "passkey",
+        bs58.encode(publicKey),
+        program
+      ),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      toSecp256r1Instruction(publicKey, signed.signedData, signed.signature),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+// bounds of the validity window of an added address, unbounded when left out
+export type AddressValidity = { validFrom?: number; validUntil?: number };
+
 export const addAddressEVMwithEVMController = async (
   program: anchor.Program<SquirclDid>,
   didStr: string,
@@ -137,7 +551,9 @@ export const addAddressEVMwithEVMController = async (
   newAddressRecoveryId: number,
   controllerActualMessage: Buffer,
   newAddressActualMessage: Buffer,
-  nonce: number
+  nonce: number,
+  role: any = { admin: {} },
+  { validFrom, validUntil }: AddressValidity = {}
 ) => {
   const sig = await program.methods
     .addAddress(
@@ -151,7 +567,8 @@ export const addAddressEVMwithEVMController = async (
             sigBase58: base58.encode(newAddressSignature),
             recoveryId: newAddressRecoveryId,
           },
-          index: 1,
+          index: 0,
+          slot: 1,
           nonce: new anchor.BN(nonce),
         },
       },
@@ -165,29 +582,51 @@ export const addAddressEVMwithEVMController = async (
             recoveryId: controllerRecoveryId,
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      role,
+      {
+        validFrom: validFrom === undefined ? null : new anchor.BN(validFrom),
+        validUntil: validUntil === undefined ? null : new anchor.BN(validUntil),
+      },
+      []
     )
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        newEthSigner.address.toLowerCase(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
-    .preInstructions([
-      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
-        ethAddress: ethSigner.address.toLowerCase().slice(2),
-        message: controllerActualMessage,
-        signature: controllerSignature,
-        recoveryId: controllerRecoveryId,
-      }),
-      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
-        ethAddress: newEthSigner.address.toLowerCase().slice(2),
-        message: newAddressActualMessage,
-        signature: newAddressSignature,
-        recoveryId: newAddressRecoveryId,
-      }),
-    ])
+    .preInstructions(
+      toSecp256k1Instructions(
+        [
+          {
+            ethSigner,
+            signature: controllerSignature,
+            recoveryId: controllerRecoveryId,
+            actual_message: controllerActualMessage,
+            nonce,
+          },
+          {
+            ethSigner: newEthSigner,
+            signature: newAddressSignature,
+            recoveryId: newAddressRecoveryId,
+            actual_message: newAddressActualMessage,
+            nonce,
+          },
+        ],
+        0
+      )
+    )
     .rpc();
 
   return sig;
@@ -205,7 +644,8 @@ export const addAddressEVMwithSOLController = async (
   newAddressSignature: Uint8Array,
   newAddressRecoveryId: number,
   newAddressActualMessage: Buffer,
-  nonce: number
+  nonce: number,
+  role: any = { admin: {} }
 ) => {
   const sig = await program.methods
     .addAddress(
@@ -220,6 +660,7 @@ export const addAddressEVMwithSOLController = async (
             recoveryId: newAddressRecoveryId,
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
       },
@@ -230,14 +671,26 @@ export const addAddressEVMwithSOLController = async (
             sigBase58: bs58.encode(controllerSignature),
           },
           index: 1,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      role,
+      { validFrom: null, validUntil: null },
+      []
     )
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "evm",
+        newEthSigner.address.toLowerCase(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -269,7 +722,8 @@ export const addAddressSOLWithEVMController = async (
   newAddress: anchor.web3.PublicKey,
   newSignature: Uint8Array,
   newMessageEncoded: Uint8Array,
-  nonce: number
+  nonce: number,
+  role: any = { admin: {} }
 ) => {
   const sig = await program.methods
     .addAddress(
@@ -281,6 +735,7 @@ export const addAddressSOLWithEVMController = async (
             sigBase58: bs58.encode(newSignature),
           },
           index: 1,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
       },
@@ -294,14 +749,26 @@ export const addAddressSOLWithEVMController = async (
             recoveryId: recoveryId,
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      role,
+      { validFrom: null, validUntil: null },
+      []
     )
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "sol",
+        newAddress.toBase58(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -332,7 +799,8 @@ export const addAddrsesSOLWithSOLController = async (
   newAddress: anchor.web3.PublicKey,
   newSignature: Uint8Array,
   newMessageEncoded: Uint8Array,
-  nonce: number
+  nonce: number,
+  role: any = { admin: {} }
 ) => {
   const sig = await program.methods
     .addAddress(
@@ -344,6 +812,7 @@ export const addAddrsesSOLWithSOLController = async (
             sigBase58: bs58.encode(newSignature),
           },
           index: 1,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
       },
@@ -354,14 +823,26 @@ export const addAddrsesSOLWithSOLController = async (
             sigBase58: bs58.encode(controllerSignature),
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      role,
+      { validFrom: null, validUntil: null },
+      []
     )
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        "sol",
+        newAddress.toBase58(),
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -394,23 +875,38 @@ export const removeAddressEVMRemover = async (
   nonce: number
 ) => {
   const sig = await program.methods
-    .removeAddress(didStr, toRemoveChain, addressToRemove, {
-      eth: {
-        ethSig: {
-          addressBase58: base58.encode(
-            arrayify(ethSigner.address.toLowerCase())
-          ),
-          sigBase58: base58.encode(signature),
-          recoveryId: recoveryId,
-        },
-        index: 0,
-        nonce: new anchor.BN(nonce),
-      },
-    })
-    .accounts({
-      did: didAccount,
+    .removeAddress(
+      didStr,
+      toRemoveChain,
+      addressToRemove,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        Object.keys(toRemoveChain)[0],
+        addressToRemove,
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -438,20 +934,35 @@ export const removeAddressSOLRemover = async (
   nonce: number
 ) => {
   const sig = await program.methods
-    .removeAddress(didStr, toRemoveChain, addressToRemove, {
-      sol: {
-        solSig: {
-          addressBase58: bs58.encode(removerAddress.toBuffer()),
-          sigBase58: bs58.encode(removerSignature),
+    .removeAddress(
+      didStr,
+      toRemoveChain,
+      addressToRemove,
+      {
+        sol: {
+          solSig: {
+            addressBase58: bs58.encode(removerAddress.toBuffer()),
+            sigBase58: bs58.encode(removerSignature),
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
         },
-        index: 0,
-        nonce: new anchor.BN(nonce),
       },
-    })
+      []
+    )
     .accounts({
       did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      addressLookup: getAddressLookupAccount(
+        Object.keys(toRemoveChain)[0],
+        addressToRemove,
+        program
+      ),
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -470,6 +981,7 @@ export const issueCredentialEth = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerEthSigner: HDNodeWallet,
   issuerSignature: Uint8Array,
@@ -481,16 +993,19 @@ export const issueCredentialEth = async (
   expiresAt: number,
   isMutable: boolean,
   isRevokable: boolean,
-  nonce: number
+  nonce: number,
+  coSigners: SignedEthMessage[] = []
 ) => {
   const sig = await program.methods
     .issueCredential(
       credentialId,
-      uri,
-      hash,
-      isMutable,
-      isRevokable,
-      new anchor.BN(expiresAt),
+      {
+        uri,
+        credentialHash: hash,
+        isMutable,
+        isRevokable,
+        expiresAt: new anchor.BN(expiresAt),
+      },
       {
         eth: {
           ethSig: {
@@ -501,16 +1016,21 @@ export const issueCredentialEth = async (
             recoveryId: issuerRecoveryId,
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      toEthSigs(coSigners, 1)
     )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -519,6 +1039,7 @@ export const issueCredentialEth = async (
         signature: issuerSignature,
         recoveryId: issuerRecoveryId,
       }),
+      ...toSecp256k1Instructions(coSigners, 1),
     ])
     .rpc();
 
@@ -530,6 +1051,7 @@ export const issueCredentialSol = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerAddress: anchor.web3.PublicKey,
   issuerSignature: Uint8Array,
@@ -545,11 +1067,13 @@ export const issueCredentialSol = async (
   const sig = await program.methods
     .issueCredential(
       credentialId,
-      uri,
-      hash,
-      isMutable,
-      isRevokable,
-      new anchor.BN(expiresAt),
+      {
+        uri,
+        credentialHash: hash,
+        isMutable,
+        isRevokable,
+        expiresAt: new anchor.BN(expiresAt),
+      },
       {
         sol: {
           solSig: {
@@ -557,16 +1081,21 @@ export const issueCredentialSol = async (
             sigBase58: bs58.encode(issuerSignature),
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      []
     )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -585,6 +1114,7 @@ export const updateCredentialEth = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerEthSigner: HDNodeWallet,
   issuerSignature: Uint8Array,
@@ -601,11 +1131,13 @@ export const updateCredentialEth = async (
   const sig = await program.methods
     .updateCredential(
       credentialId,
-      uri,
-      hash,
-      isMutable,
-      isRevokable,
-      new anchor.BN(expiresAt),
+      {
+        uri,
+        credentialHash: hash,
+        isMutable,
+        isRevokable,
+        expiresAt: new anchor.BN(expiresAt),
+      },
       {
         eth: {
           ethSig: {
@@ -616,16 +1148,21 @@ export const updateCredentialEth = async (
             recoveryId: issuerRecoveryId,
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      []
     )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -645,6 +1182,7 @@ export const updateCredentialSol = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerAddress: anchor.web3.PublicKey,
   issuerSignature: Uint8Array,
@@ -660,11 +1198,13 @@ export const updateCredentialSol = async (
   const sig = await program.methods
     .updateCredential(
       credentialId,
-      uri,
-      hash,
-      isMutable,
-      isRevokable,
-      new anchor.BN(expiresAt),
+      {
+        uri,
+        credentialHash: hash,
+        isMutable,
+        isRevokable,
+        expiresAt: new anchor.BN(expiresAt),
+      },
       {
         sol: {
           solSig: {
@@ -672,16 +1212,21 @@ export const updateCredentialSol = async (
             sigBase58: bs58.encode(issuerSignature),
           },
           index: 0,
+          slot: 0,
           nonce: new anchor.BN(nonce),
         },
-      }
+      },
+      []
     )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
       payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -700,6 +1245,7 @@ export const revokeCredentialEvm = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerEthSigner: HDNodeWallet,
   issuerSignature: Uint8Array,
@@ -709,25 +1255,33 @@ export const revokeCredentialEvm = async (
   nonce: number
 ) => {
   const sig = await program.methods
-    .revokeCredential(credentialId, {
-      eth: {
-        ethSig: {
-          addressBase58: base58.encode(
-            arrayify(issuerEthSigner.address.toLowerCase())
-          ),
-          sigBase58: base58.encode(issuerSignature),
-          recoveryId: issuerRecoveryId,
+    .revokeCredential(
+      credentialId,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(issuerEthSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(issuerSignature),
+            recoveryId: issuerRecoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
         },
-        index: 0,
-        nonce: new anchor.BN(nonce),
       },
-    })
+      []
+    )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       payer: payer.publicKey,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
@@ -747,6 +1301,7 @@ export const revokeCredentialSol = async (
   issuerDidAccount: anchor.web3.PublicKey,
   subjectDidAccount: anchor.web3.PublicKey,
   credentialAccount: anchor.web3.PublicKey,
+  nonceRegistryAccount: anchor.web3.PublicKey,
   payer: any,
   issuerAddress: anchor.web3.PublicKey,
   issuerSignature: Uint8Array,
@@ -755,22 +1310,30 @@ export const revokeCredentialSol = async (
   nonce: number
 ) => {
   const sig = await program.methods
-    .revokeCredential(credentialId, {
-      sol: {
-        solSig: {
-          addressBase58: bs58.encode(issuerAddress.toBuffer()),
-          sigBase58: bs58.encode(issuerSignature),
+    .revokeCredential(
+      credentialId,
+      {
+        sol: {
+          solSig: {
+            addressBase58: bs58.encode(issuerAddress.toBuffer()),
+            sigBase58: bs58.encode(issuerSignature),
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
         },
-        index: 0,
-        nonce: new anchor.BN(nonce),
       },
-    })
+      []
+    )
     .accounts({
       credential: credentialAccount,
       issuerDid: issuerDidAccount,
       subjectDid: subjectDidAccount,
+      nonceRegistry: nonceRegistryAccount,
       payer: payer.publicKey,
       ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
     })
     .preInstructions([
       anchor.web3.Ed25519Program.createInstructionWithPublicKey({
@@ -783,3 +1346,984 @@ export const revokeCredentialSol = async (
 
   return sig;
 };
+
+export const changeRoleEVMChanger = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  addressToChange: string,
+  toChangeChain: any,
+  role: any,
+  nonce: number,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .changeRole(
+      didStr,
+      toChangeChain,
+      addressToChange,
+      role,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      toEthSigs(coSigners, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+      ...toSecp256k1Instructions(coSigners, 1),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const setThresholdEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  threshold: number,
+  nonce: number,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .setThreshold(
+      didStr,
+      threshold,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      toEthSigs(coSigners, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+      ...toSecp256k1Instructions(coSigners, 1),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const setGuardiansEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  guardians: any[],
+  quorum: number,
+  delay: number,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .setGuardians(
+      didStr,
+      guardians,
+      quorum,
+      new anchor.BN(delay),
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      recovery: getRecoveryAccount(didStr, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+// guardians sign after the new controller, guardian dids are passed as remaining accounts
+export const startRecoveryEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  newController: SignedEthMessage,
+  guardians: SignedEthMessage[],
+  guardianDidAccounts: anchor.web3.PublicKey[] = []
+) => {
+  const sig = await program.methods
+    .startRecovery(
+      didStr,
+      toEthSigs([newController], 0)[0],
+      toEthSigs(guardians, 0, 1)
+    )
+    .accounts({
+      did: didAccount,
+      recovery: getRecoveryAccount(didStr, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .remainingAccounts(
+      guardianDidAccounts.map((pubkey) => ({
+        pubkey,
+        isWritable: false,
+        isSigner: false,
+      }))
+    )
+    .preInstructions(toSecp256k1Instructions([newController, ...guardians], 0))
+    .rpc();
+
+  return sig;
+};
+
+export const cancelRecoveryEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  controller: SignedEthMessage,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .cancelRecovery(
+      didStr,
+      toEthSigs([controller], 0)[0],
+      toEthSigs(coSigners, 0, 1)
+    )
+    .accounts({
+      did: didAccount,
+      recovery: getRecoveryAccount(didStr, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions(toSecp256k1Instructions([controller, ...coSigners], 0))
+    .rpc();
+
+  return sig;
+};
+
+export const executeRecoveryEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  controller: string,
+  newController: string
+) => {
+  const sig = await program.methods
+    .executeRecovery(didStr)
+    .accounts({
+      did: didAccount,
+      recovery: getRecoveryAccount(didStr, program),
+      controllerLookup: getAddressLookupAccount("evm", controller, program),
+      newControllerLookup: getAddressLookupAccount(
+        "evm",
+        newController,
+        program
+      ),
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .rpc();
+
+  return sig;
+};
+
+export const addServiceEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  signer: SignedEthMessage,
+  service: { id: string; serviceType: string; endpoint: string },
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .addService(
+      didStr,
+      service,
+      toEthSigs([signer], 0)[0],
+      toEthSigs(coSigners, 0, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions(toSecp256k1Instructions([signer, ...coSigners], 0))
+    .rpc();
+
+  return sig;
+};
+
+export const removeServiceEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  signer: SignedEthMessage,
+  serviceId: string,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .removeService(
+      didStr,
+      serviceId,
+      toEthSigs([signer], 0)[0],
+      toEthSigs(coSigners, 0, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions(toSecp256k1Instructions([signer, ...coSigners], 0))
+    .rpc();
+
+  return sig;
+};
+
+export const transferControllerEVMtoEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  newEthSigner: HDNodeWallet,
+  controllerSignature: Uint8Array,
+  controllerRecoveryId: number,
+  newControllerSignature: Uint8Array,
+  newControllerRecoveryId: number,
+  controllerActualMessage: Buffer,
+  newControllerActualMessage: Buffer,
+  nonce: number,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .transferController(
+      didStr,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(controllerSignature),
+            recoveryId: controllerRecoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(newEthSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(newControllerSignature),
+            recoveryId: newControllerRecoveryId,
+          },
+          index: 0,
+          slot: 1,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      toEthSigs(coSigners, 0, 2)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions(
+      toSecp256k1Instructions(
+        [
+          {
+            ethSigner,
+            signature: controllerSignature,
+            recoveryId: controllerRecoveryId,
+            actual_message: controllerActualMessage,
+            nonce,
+          },
+          {
+            ethSigner: newEthSigner,
+            signature: newControllerSignature,
+            recoveryId: newControllerRecoveryId,
+            actual_message: newControllerActualMessage,
+            nonce,
+          },
+          ...coSigners,
+        ],
+        0
+      )
+    )
+    .rpc();
+
+  return sig;
+};
+
+export const deactivateDIDEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  recipient: anchor.web3.PublicKey,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  nonce: number,
+  coSigners: SignedEthMessage[] = [],
+  aliases: anchor.web3.PublicKey[] = []
+) => {
+  // the lookups of all the addresses of the did are closed or cleared along
+  // with it, and so are the aliases passed
+  const { ethAddresses, solAddresses, btcAddresses, passkeyAddresses } =
+    await program.account.did.fetch(didAccount);
+
+  const addressLookups = [
+    ...ethAddresses,
+    ...solAddresses,
+    ...btcAddresses,
+    ...passkeyAddresses,
+  ].map((address) =>
+    getAddressLookupAccount(
+      Object.keys(address.chain)[0],
+      getAddressString(address),
+      program
+    )
+  );
+
+  const sig = await program.methods
+    .deactivateDid(
+      didStr,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      toEthSigs(coSigners, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      recovery: getRecoveryAccount(didStr, program),
+      recipient: recipient,
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .remainingAccounts(
+      [...addressLookups, ...aliases].map((pubkey) => ({
+        pubkey,
+        isWritable: true,
+        isSigner: false,
+      }))
+    )
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+      ...toSecp256k1Instructions(coSigners, 1),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const migrateDIDEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  recipient: anchor.web3.PublicKey,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  nonce: number,
+  coSigners: SignedEthMessage[] = []
+) => {
+  const sig = await program.methods
+    .migrateDid(
+      didStr,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      toEthSigs(coSigners, 1)
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      recipient: recipient,
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+      ...toSecp256k1Instructions(coSigners, 1),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const createDIDZeroCopyEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  didAccount: anchor.web3.PublicKey,
+  actual_message: Buffer,
+  payer: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .createDidZeroCopy(didStr, {
+      eth: {
+        ethSig: {
+          addressBase58: base58.encode(
+            arrayify(ethSigner.address.toLowerCase())
+          ),
+          sigBase58: base58.encode(signature),
+          recoveryId: recoveryId,
+        },
+        index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const addAddressZeroCopyEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  newEthSigner: HDNodeWallet,
+  controllerSignature: Uint8Array,
+  controllerRecoveryId: number,
+  newAddressSignature: Uint8Array,
+  newAddressRecoveryId: number,
+  controllerActualMessage: Buffer,
+  newAddressActualMessage: Buffer,
+  nonce: number,
+  role: any = { admin: {} }
+) => {
+  const sig = await program.methods
+    .addAddressZeroCopy(
+      didStr,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(newEthSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(newAddressSignature),
+            recoveryId: newAddressRecoveryId,
+          },
+          index: 0,
+          slot: 1,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(controllerSignature),
+            recoveryId: controllerRecoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      role
+    )
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions(
+      toSecp256k1Instructions(
+        [
+          {
+            ethSigner,
+            signature: controllerSignature,
+            recoveryId: controllerRecoveryId,
+            actual_message: controllerActualMessage,
+            nonce,
+          },
+          {
+            ethSigner: newEthSigner,
+            signature: newAddressSignature,
+            recoveryId: newAddressRecoveryId,
+            actual_message: newAddressActualMessage,
+            nonce,
+          },
+        ],
+        0
+      )
+    )
+    .rpc();
+
+  return sig;
+};
+
+export const removeAddressZeroCopyEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  addressToRemove: string,
+  toRemoveChain: any,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .removeAddressZeroCopy(didStr, toRemoveChain, addressToRemove, {
+      eth: {
+        ethSig: {
+          addressBase58: base58.encode(
+            arrayify(ethSigner.address.toLowerCase())
+          ),
+          sigBase58: base58.encode(signature),
+          recoveryId: recoveryId,
+        },
+        index: 0,
+        slot: 0,
+        nonce: new anchor.BN(nonce),
+      },
+    })
+    .accounts({
+      did: didAccount,
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const claimAliasEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  handle: string,
+  expiresAt: number | null,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .claimAlias(
+      didStr,
+      handle,
+      expiresAt === null ? null : new anchor.BN(expiresAt),
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      alias: getAliasAccount(handle, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const transferAliasEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  handle: string,
+  newDidStr: string,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .transferAlias(
+      didStr,
+      handle,
+      newDidStr,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      newDid: getDIDAccount(newDidStr, program),
+      alias: getAliasAccount(handle, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const releaseAliasEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  handle: string,
+  recipient: anchor.web3.PublicKey,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .releaseAlias(
+      didStr,
+      handle,
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      alias: getAliasAccount(handle, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      recipient: recipient,
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+export const setAliasExpiryEVM = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  ethSigner: HDNodeWallet,
+  signature: Uint8Array,
+  recoveryId: number,
+  actual_message: Buffer,
+  handle: string,
+  expiresAt: number | null,
+  nonce: number
+) => {
+  const sig = await program.methods
+    .setAliasExpiry(
+      didStr,
+      handle,
+      expiresAt === null ? null : new anchor.BN(expiresAt),
+      {
+        eth: {
+          ethSig: {
+            addressBase58: base58.encode(
+              arrayify(ethSigner.address.toLowerCase())
+            ),
+            sigBase58: base58.encode(signature),
+            recoveryId: recoveryId,
+          },
+          index: 0,
+          slot: 0,
+          nonce: new anchor.BN(nonce),
+        },
+      },
+      []
+    )
+    .accounts({
+      did: didAccount,
+      alias: getAliasAccount(handle, program),
+      nonceRegistry: getNonceRegistryAccount(didStr, program),
+      ixSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .preInstructions([
+      anchor.web3.Secp256k1Program.createInstructionWithEthAddress({
+        ethAddress: ethSigner.address.toLowerCase().slice(2),
+        message: actual_message,
+        signature: signature,
+        recoveryId: recoveryId,
+      }),
+    ])
+    .rpc();
+
+  return sig;
+};
+
+// expired addresses can be pruned by anyone, the lookups of all the pruned
+// addresses must be passed as remaining accounts
+export const pruneAddresses = async (
+  program: anchor.Program<SquirclDid>,
+  didStr: string,
+  didAccount: anchor.web3.PublicKey,
+  payer: any,
+  addressLookups: anchor.web3.PublicKey[] = []
+) => {
+  const sig = await program.methods
+    .pruneAddresses(didStr)
+    .accounts({
+      did: didAccount,
+      payer: payer.publicKey,
+      eventAuthority: getEventAuthorityAccount(program),
+      program: program.programId,
+    })
+    .remainingAccounts(
+      addressLookups.map((pubkey) => ({
+        pubkey,
+        isWritable: true,
+        isSigner: false,
+      }))
+    )
+    .rpc();
+
+  return sig;
+};
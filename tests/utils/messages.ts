@@ -0,0 +1,412 @@
+import * as anchor from "@project-serum/anchor";
+import * as crypto from "crypto";
+
+// must match CLUSTER in the program, tests run against a default (non devnet) build
+export const CLUSTER = "mainnet-beta";
+
+const MESSAGE_HEADER = "Squircl DID operation";
+const MESSAGE_VERSION = 1;
+
+const escapeValue = (value: string) =>
+  value.replace(/\\/g, "\\\\").replace(/\n/g, "\\n");
+
+// canonical operation message, mirrors get_operation_message in the program
+export const getOperationMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  operation: string,
+  signer: string,
+  params: [string, string][],
+  nonce: number
+) =>
+  [
+    MESSAGE_HEADER,
+    `Version: ${MESSAGE_VERSION}`,
+    `Program: ${programId.toBase58()}`,
+    `Cluster: ${CLUSTER}`,
+    `DID: ${did}`,
+    `Operation: ${operation}`,
+    `Signer: ${signer}`,
+    ...params.map(([key, value]) => `${key}: ${escapeValue(value)}`),
+    `Nonce: ${nonce}`,
+  ].join("\n");
+
+// fixed-size commitment a wallet can sign instead of the operation message,
+// mirrors get_operation_digest_message in the program
+export const getOperationDigestMessage = (message: string) =>
+  [
+    `${MESSAGE_HEADER} digest`,
+    `Version: ${MESSAGE_VERSION}`,
+    `Digest: ${crypto.createHash("sha256").update(message).digest("hex")}`,
+  ].join("\n");
+
+export const createMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  nonce: number
+) => getOperationMessage(programId, did, "create_did", signer, [], nonce);
+
+export const addAddressMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  chain: string,
+  address: string,
+  role: string,
+  nonce: number,
+  validFrom?: number,
+  validUntil?: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "add_address",
+    signer,
+    [
+      ["Chain", chain],
+      ["Address", address],
+      ["Role", role],
+      // only part of the message for addresses with a validity window
+      ...(validFrom === undefined
+        ? []
+        : [["Valid from", validFrom.toString()] as [string, string]]),
+      ...(validUntil === undefined
+        ? []
+        : [["Valid until", validUntil.toString()] as [string, string]]),
+    ],
+    nonce
+  );
+
+export const removeAddressMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  chain: string,
+  address: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "remove_address",
+    signer,
+    [
+      ["Chain", chain],
+      ["Address", address],
+    ],
+    nonce
+  );
+
+export const changeRoleMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  chain: string,
+  address: string,
+  role: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "change_role",
+    signer,
+    [
+      ["Chain", chain],
+      ["Address", address],
+      ["Role", role],
+    ],
+    nonce
+  );
+
+export const transferControllerMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  chain: string,
+  newController: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "transfer_controller",
+    signer,
+    [
+      ["Chain", chain],
+      ["New controller", newController],
+    ],
+    nonce
+  );
+
+export const setThresholdMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  threshold: number,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "set_threshold",
+    signer,
+    [["Threshold", `${threshold}`]],
+    nonce
+  );
+
+export const setGuardiansMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  guardians: string[],
+  quorum: number,
+  delay: number,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "set_guardians",
+    signer,
+    [
+      ["Guardians", guardians.join(", ")],
+      ["Quorum", `${quorum}`],
+      ["Delay", `${delay}`],
+    ],
+    nonce
+  );
+
+export const startRecoveryMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  chain: string,
+  newController: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "start_recovery",
+    signer,
+    [
+      ["Chain", chain],
+      ["New controller", newController],
+    ],
+    nonce
+  );
+
+export const cancelRecoveryMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  nonce: number
+) => getOperationMessage(programId, did, "cancel_recovery", signer, [], nonce);
+
+export const addServiceMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  service: { id: string; serviceType: string; endpoint: string },
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "add_service",
+    signer,
+    [
+      ["Service", service.id],
+      ["Type", service.serviceType],
+      ["Endpoint", service.endpoint],
+    ],
+    nonce
+  );
+
+export const removeServiceMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  id: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "remove_service",
+    signer,
+    [["Service", id]],
+    nonce
+  );
+
+export const deactivateMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  recipient: anchor.web3.PublicKey,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "deactivate_did",
+    signer,
+    [["Rent recipient", recipient.toBase58()]],
+    nonce
+  );
+
+export const migrateMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  recipient: anchor.web3.PublicKey,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "migrate_did",
+    signer,
+    [["Rent recipient", recipient.toBase58()]],
+    nonce
+  );
+
+const expiry = (expiresAt: number | null) =>
+  expiresAt === null ? "never" : `${expiresAt}`;
+
+const credentialMessage =
+  (operation: string) =>
+  (
+    programId: anchor.web3.PublicKey,
+    issuerDid: string,
+    signer: string,
+    credentialId: string,
+    subjectDid: string,
+    uri: string,
+    hash: string,
+    expiresAt: number | null,
+    isMutable: boolean,
+    isRevokable: boolean,
+    nonce: number
+  ) =>
+    getOperationMessage(
+      programId,
+      issuerDid,
+      operation,
+      signer,
+      [
+        ["Credential", credentialId],
+        ["Subject", subjectDid],
+        ["Uri", uri],
+        ["Hash", hash],
+        ["Mutable", isMutable.toString()],
+        ["Revokable", isRevokable.toString()],
+        ["Expires at", expiry(expiresAt)],
+      ],
+      nonce
+    );
+
+export const issueCredentialMessage = credentialMessage("issue_credential");
+export const updateCredentialMessage = credentialMessage("update_credential");
+
+export const revokeCredentialMessage = (
+  programId: anchor.web3.PublicKey,
+  issuerDid: string,
+  signer: string,
+  credentialId: string,
+  subjectDid: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    issuerDid,
+    "revoke_credential",
+    signer,
+    [
+      ["Credential", credentialId],
+      ["Subject", subjectDid],
+    ],
+    nonce
+  );
+
+export const claimAliasMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  handle: string,
+  expiresAt: number | null,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "claim_alias",
+    signer,
+    [
+      ["Alias", handle],
+      ["Expires at", expiry(expiresAt)],
+    ],
+    nonce
+  );
+
+export const transferAliasMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  handle: string,
+  newDid: string,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "transfer_alias",
+    signer,
+    [
+      ["Alias", handle],
+      ["New DID", newDid],
+    ],
+    nonce
+  );
+
+export const releaseAliasMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  handle: string,
+  recipient: anchor.web3.PublicKey,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "release_alias",
+    signer,
+    [
+      ["Alias", handle],
+      ["Rent recipient", recipient.toBase58()],
+    ],
+    nonce
+  );
+
+export const setAliasExpiryMessage = (
+  programId: anchor.web3.PublicKey,
+  did: string,
+  signer: string,
+  handle: string,
+  expiresAt: number | null,
+  nonce: number
+) =>
+  getOperationMessage(
+    programId,
+    did,
+    "set_alias_expiry",
+    signer,
+    [
+      ["Alias", handle],
+      ["Expires at", expiry(expiresAt)],
+    ],
+    nonce
+  );
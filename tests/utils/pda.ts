@@ -2,13 +2,23 @@ import { Program } from "@project-serum/anchor";
 import crypto from "crypto";
 import { SquirclDid } from "../../target/types/squircl_did";
 import * as anchor from "@project-serum/anchor";
+import { getAddressKey } from "./addresses";
 
 export const generateRandomDID = () => {
   const randomBytes = crypto.randomBytes(24); // 24 bytes = 48 characters
-  const randomDID = randomBytes.toString("hex");
+  const randomDID = "did:squircl:" + randomBytes.toString("hex");
   return randomDID;
 };
 
+export const getDerivedDID = (chain: "evm" | "sol", address: string) => {
+  const hexString = crypto
+    .createHash("sha256")
+    .update(address, "utf-8")
+    .digest("hex");
+
+  return `did:squircl:${chain}:${hexString.slice(0, 40)}`;
+};
+
 export const getDIDAccount = (didStr: string, program: Program<SquirclDid>) => {
   const hexString = crypto
     .createHash("sha256")
@@ -55,3 +65,82 @@ export const getCredentialAccount = (
 
   return credentialAccount;
 };
+
+export const getNonceRegistryAccount = (
+  didStr: string,
+  program: Program<SquirclDid>
+) => {
+  const hexString = crypto
+    .createHash("sha256")
+    .update(didStr, "utf-8")
+    .digest("hex");
+
+  const seed = Uint8Array.from(Buffer.from(hexString, "hex"));
+
+  const [nonceRegistryAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("nonce_registry"), seed],
+    program.programId
+  );
+
+  return nonceRegistryAccount;
+};
+
+export const getRecoveryAccount = (
+  didStr: string,
+  program: Program<SquirclDid>
+) => {
+  const hexString = crypto
+    .createHash("sha256")
+    .update(didStr, "utf-8")
+    .digest("hex");
+
+  const seed = Uint8Array.from(Buffer.from(hexString, "hex"));
+
+  const [recoveryAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("recovery"), seed],
+    program.programId
+  );
+
+  return recoveryAccount;
+};
+
+const CHAIN_SEEDS = { evm: 0, sol: 1, btc: 2, passkey: 3 };
+
+export const getAddressLookupAccount = (
+  chain: string,
+  address: string,
+  program: Program<SquirclDid>
+) => {
+  const seed = crypto
+    .createHash("sha256")
+    .update(getAddressKey(chain, address))
+    .digest();
+
+  const [addressLookupAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("address_lookup"), Buffer.from([CHAIN_SEEDS[chain]]), seed],
+    program.programId
+  );
+
+  return addressLookupAccount;
+};
+
+export const getAliasAccount = (
+  handle: string,
+  program: Program<SquirclDid>
+) => {
+  const [aliasAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("alias"), Buffer.from(handle)],
+    program.programId
+  );
+
+  return aliasAccount;
+};
+
+export const getEventAuthorityAccount = (program: Program<SquirclDid>) => {
+  const [eventAuthorityAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("__event_authority")],
+    program.programId
+  );
+
+  return eventAuthorityAccount;
+};
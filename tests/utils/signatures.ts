@@ -1,5 +1,10 @@
 import { arrayify } from "@ethersproject/bytes";
-import { HDNodeWallet, ethers } from "ethers";
+import { HDNodeWallet, SigningKey, ethers } from "ethers";
+import bs58 from "bs58";
+import * as crypto from "crypto";
+import * as anchor from "@project-serum/anchor";
+import { SignedEthMessage } from "./instructions";
+import { CLUSTER } from "./messages";
 
 export const signEthMessage = async (
   message: string,
@@ -30,3 +35,235 @@ export const signEthMessage = async (
     full_sig_bytes,
   };
 };
+
+export const EIP712_TYPES = {
+  CreateDid: [
+    { name: "did", type: "string" },
+    { name: "controller", type: "string" },
+    { name: "nonce", type: "int64" },
+  ],
+  AddAddress: [
+    { name: "did", type: "string" },
+    { name: "chain", type: "string" },
+    { name: "address", type: "string" },
+    { name: "role", type: "string" },
+    { name: "nonce", type: "int64" },
+  ],
+  RemoveAddress: [
+    { name: "did", type: "string" },
+    { name: "chain", type: "string" },
+    { name: "address", type: "string" },
+    { name: "nonce", type: "int64" },
+  ],
+  IssueCredential: [
+    { name: "credentialId", type: "string" },
+    { name: "issuerDid", type: "string" },
+    { name: "subjectDid", type: "string" },
+    { name: "uri", type: "string" },
+    { name: "hash", type: "string" },
+    { name: "isMutable", type: "bool" },
+    { name: "isRevokable", type: "bool" },
+    { name: "expiresAt", type: "int64" },
+    { name: "nonce", type: "int64" },
+  ],
+  UpdateCredential: [
+    { name: "credentialId", type: "string" },
+    { name: "issuerDid", type: "string" },
+    { name: "subjectDid", type: "string" },
+    { name: "uri", type: "string" },
+    { name: "hash", type: "string" },
+    { name: "isMutable", type: "bool" },
+    { name: "isRevokable", type: "bool" },
+    { name: "expiresAt", type: "int64" },
+    { name: "nonce", type: "int64" },
+  ],
+  RevokeCredential: [
+    { name: "credentialId", type: "string" },
+    { name: "issuerDid", type: "string" },
+    { name: "subjectDid", type: "string" },
+    { name: "nonce", type: "int64" },
+  ],
+};
+
+export const getEip712Domain = (programId: anchor.web3.PublicKey) => ({
+  name: "Squircl DID",
+  version: "1",
+  salt: ethers.keccak256(
+    Buffer.concat([programId.toBuffer(), Buffer.from(CLUSTER)])
+  ),
+});
+
+// EIP-712 signature over one of the typed operations, `actual_message` is what the secp256k1
+// precompile hashes: 0x1901 || domainSeparator || hashStruct(value)
+export const signEthTypedData = async (
+  programId: anchor.web3.PublicKey,
+  primaryType: keyof typeof EIP712_TYPES,
+  value: Record<string, any>,
+  ethSigner: HDNodeWallet
+) => {
+  const domain = getEip712Domain(programId);
+  const types = { [primaryType]: EIP712_TYPES[primaryType] };
+
+  const full_sig_bytes = arrayify(
+    await ethSigner.signTypedData(domain, types, value)
+  );
+
+  const signature = full_sig_bytes.slice(0, 64);
+  const recoveryId = full_sig_bytes[64] - 27;
+
+  const actual_message = Buffer.concat([
+    Buffer.from([0x19, 0x01]),
+    arrayify(ethers.TypedDataEncoder.hashDomain(domain)),
+    arrayify(ethers.TypedDataEncoder.hashStruct(primaryType, types, value)),
+  ]);
+
+  return { signature, recoveryId, actual_message };
+};
+
+export const signEthMessageWithNonce = async (
+  message: string,
+  ethSigner: HDNodeWallet,
+  nonce: number
+): Promise<SignedEthMessage> => {
+  const { actual_message, signature, recoveryId } = await signEthMessage(
+    message,
+    ethSigner
+  );
+
+  return { ethSigner, signature, recoveryId, actual_message, nonce };
+};
+
+const sha256 = (data: Uint8Array) =>
+  crypto.createHash("sha256").update(data).digest();
+
+// compressed P2PKH address of the key, base58check(0x00 || hash160(pubkey))
+export const getBtcAddress = (signingKey: SigningKey) =>
+  toP2PKHAddress(
+    arrayify(ethers.ripemd160(sha256(arrayify(signingKey.compressedPublicKey))))
+  );
+
+export const toP2PKHAddress = (pubkeyHash: Uint8Array) => {
+  const payload = Buffer.concat([Buffer.from([0x00]), pubkeyHash]);
+  const checksum = sha256(sha256(payload)).subarray(0, 4);
+
+  return bs58.encode(Buffer.concat([payload, checksum]));
+};
+
+const toBtcVarint = (n: number) => {
+  if (n < 0xfd) {
+    return Buffer.from([n]);
+  }
+
+  const varint = Buffer.alloc(3);
+  varint.writeUInt8(0xfd, 0);
+  varint.writeUInt16LE(n, 1);
+  return varint;
+};
+
+// BIP-137 "Bitcoin Signed Message" signature from a compressed P2PKH key
+export const signBtcMessage = (message: string, signingKey: SigningKey) => {
+  const messageBytes = Buffer.from(message);
+
+  const digest = sha256(
+    sha256(
+      Buffer.concat([
+        Buffer.from("\x18Bitcoin Signed Message:\n"),
+        toBtcVarint(messageBytes.length),
+        messageBytes,
+      ])
+    )
+  );
+
+  const sig = signingKey.sign(digest);
+
+  const signature = Buffer.concat([
+    Buffer.from([31 + sig.v - 27]),
+    arrayify(sig.r),
+    arrayify(sig.s),
+  ]);
+
+  return { signature, address: getBtcAddress(signingKey) };
+};
+
+const P256_ORDER = BigInt(
+  "0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"
+);
+
+export type Passkey = {
+  privateKey: crypto.KeyObject;
+  publicKey: Uint8Array; // compressed secp256r1 public key
+};
+
+export const createPasskey = (): Passkey => {
+  const { privateKey, publicKey } = crypto.generateKeyPairSync("ec", {
+    namedCurve: "prime256v1",
+  });
+
+  const jwk = publicKey.export({ format: "jwk" });
+  const x = Buffer.from(jwk.x, "base64url");
+  const y = Buffer.from(jwk.y, "base64url");
+
+  return {
+    privateKey,
+    publicKey: Buffer.concat([Buffer.from([2 + (y[31] & 1)]), x]),
+  };
+};
+
+// WebAuthn assertion over `message`, as a browser would produce it with the message as challenge
+export const signPasskeyMessage = (message: string, passkey: Passkey) => {
+  const authenticatorData = Buffer.concat([
+    sha256(Buffer.from("squircl.xyz")), // rpIdHash
+    Buffer.from([0x05]), // user present and user verified
+    Buffer.from([0, 0, 0, 0]), // signCount
+  ]);
+
+  const clientDataJSON = JSON.stringify({
+    type: "webauthn.get",
+    challenge: Buffer.from(message).toString("base64url"),
+    origin: "https://squircl.xyz",
+    crossOrigin: false,
+  });
+
+  const signedData = Buffer.concat([
+    authenticatorData,
+    sha256(Buffer.from(clientDataJSON)),
+  ]);
+
+  const sig = crypto.sign("sha256", signedData, {
+    key: passkey.privateKey,
+    dsaEncoding: "ieee-p1363",
+  });
+
+  // the secp256r1 precompile only accepts low s signatures
+  let s = BigInt("0x" + sig.subarray(32).toString("hex"));
+  if (s > P256_ORDER / BigInt(2)) {
+    s = P256_ORDER - s;
+  }
+
+  const signature = Buffer.concat([
+    sig.subarray(0, 32),
+    Buffer.from(s.toString(16).padStart(64, "0"), "hex"),
+  ]);
+
+  return { signature, authenticatorData, clientDataJSON, signedData };
+};
+
+// Solana off-chain message envelope (version 0), as signed by Ledger
+export const toOffchainMessage = (message: string) => {
+  const messageBytes = Buffer.from(message);
+
+  // printable ascii is restricted ascii (0), anything else (newlines) is
+  // limited utf-8 (1)
+  const isRestrictedAscii = messageBytes.every((b) => b >= 0x20 && b <= 0x7e);
+
+  const header = Buffer.alloc(4);
+  header.writeUInt8(0, 0); // version
+  header.writeUInt8(isRestrictedAscii ? 0 : 1, 1); // format
+  header.writeUInt16LE(messageBytes.length, 2);
+
+  return Buffer.concat([
+    Buffer.from("\xffsolana offchain", "latin1"),
+    header,
+    messageBytes,
+  ]);
+};